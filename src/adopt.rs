@@ -0,0 +1,320 @@
+//! Pulling files that were edited in the home directory back into the
+//! repository.
+//!
+//! Adopting a path copies the live file, or every file below a live
+//! directory, into the repository at the path the manifest would install it
+//! from, which is normally the same relative path. Each one is
+//! then swapped for whatever its strategy installs, in a single
+//! [`Transaction`], and the path gets an entry in `dotfiles.toml`.
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::discover::IGNORED;
+use crate::error::{Error, Result};
+use crate::fsutil::{is_missing, FileKind};
+use crate::installed::Installed;
+use crate::layout::Layout;
+use crate::manifest::{Manifest, MANIFEST};
+use crate::strategy::{inspect, Found, Strategy};
+use crate::template::Values;
+use crate::transaction::{Op, Transaction};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// Copied into the repository, which did not have the file before.
+    Added,
+    /// Copied over the repository's own, different version of the file.
+    Updated,
+    /// Already installed from the repository with no local edits.
+    AlreadyManaged,
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    /// Outcomes keyed by path relative to home.
+    pub results: Vec<(PathBuf, Outcome)>,
+    /// The backup holding the adopted files as they were found.
+    pub backup: Option<String>,
+    /// Whether a new entry was added to `dotfiles.toml`.
+    pub tracked: bool,
+}
+
+struct Adoption {
+    path: PathBuf,
+    /// Relative to the repository root.
+    source: PathBuf,
+    /// Whether the live file is an edited copy we installed, rather than
+    /// something the tool has never managed.
+    ours: bool,
+}
+
+/// Adopts `path`, which is either absolute or relative to the home
+/// directory.
+///
+/// Nothing is changed unless every file below `path` can be adopted:
+/// symlinks that do not point at the repository and special files are
+/// refused.
+pub fn adopt(layout: &Layout, path: &Path) -> Result<Report> {
+    if Transaction::is_pending(layout) {
+        return Err(Error::JournalPending);
+    }
+    let manifest = Manifest::load(layout.repo())?;
+    let rel = home_relative(layout, &manifest, path)?;
+    let installed = Installed::load(layout)?;
+    let values = Values::load(layout)?;
+
+    let mut files = Vec::new();
+    walk(layout, &rel, &mut files)?;
+
+    let mut report = Report::default();
+    let mut adoptions = Vec::new();
+    for file in files {
+        let source = manifest.source_for(&file);
+        let strategy = manifest.strategy_for(&source);
+        let dest = layout.dest(&file);
+        if strategy == Strategy::Template {
+            return Err(Error::CannotAdopt {
+                path: dest,
+                reason: format!(
+                    "it is rendered from {}; edit that instead",
+                    source.display()
+                ),
+            });
+        }
+        let recorded = installed.files.get(&file);
+        let ours = match inspect(&dest, &layout.source(&source), strategy, recorded, &values)? {
+            Found::Current | Found::Stale | Found::Missing => {
+                report.results.push((file, Outcome::AlreadyManaged));
+                continue;
+            }
+            Found::Modified => true,
+            Found::Foreign(FileKind::File) => false,
+            Found::Foreign(kind) => {
+                return Err(Error::CannotAdopt {
+                    path: dest,
+                    reason: format!("it is a {kind}"),
+                })
+            }
+        };
+        adoptions.push(Adoption {
+            path: file,
+            source,
+            ours,
+        });
+    }
+    if adoptions.is_empty() {
+        return Ok(report);
+    }
+
+    // The repository has to have the files before they can be installed
+    // from it, so it is changed first and put back if the install fails.
+    let mut changes = RepoChanges::default();
+    let result = adopt_into(
+        layout,
+        &manifest,
+        &rel,
+        &adoptions,
+        values,
+        &mut report,
+        &mut changes,
+    );
+    if let Err(e) = result {
+        changes.undo(layout)?;
+        return Err(e);
+    }
+    report.results.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(report)
+}
+
+fn adopt_into(
+    layout: &Layout,
+    manifest: &Manifest,
+    rel: &Path,
+    adoptions: &[Adoption],
+    values: Values,
+    report: &mut Report,
+    changes: &mut RepoChanges,
+) -> Result<()> {
+    let mut ops = Vec::new();
+    for adoption in adoptions {
+        let outcome = copy_into_repo(layout, &adoption.path, &adoption.source, changes)?;
+        report.results.push((adoption.path.clone(), outcome));
+        let path = adoption.path.clone();
+        ops.push(match adoption.ours {
+            true => Op::Discard { path: path.clone() },
+            false => Op::Backup { path: path.clone() },
+        });
+        ops.push(Op::Install {
+            source: layout.source(&adoption.source),
+            strategy: manifest.strategy_for(&adoption.source),
+            path,
+        });
+    }
+    changes.manifest = Some(snapshot(&layout.repo().join(MANIFEST))?);
+    report.tracked = Manifest::track(layout.repo(), &manifest.source_for(rel))?;
+    report.backup = Transaction::begin(layout, ops, values)?.commit()?;
+    Ok(())
+}
+
+/// What adopting has changed in the repository so far, to put back if
+/// the install fails.
+#[derive(Default)]
+struct RepoChanges {
+    /// Files written, with what they held before, if they existed.
+    files: Vec<(PathBuf, Option<Snapshot>)>,
+    /// Directories created, outermost first.
+    dirs: Vec<PathBuf>,
+    /// The manifest before it was changed, if it was.
+    manifest: Option<Option<Snapshot>>,
+}
+
+/// A file's content and mode, since adopting replaces scripts and hooks
+/// that have to stay executable.
+struct Snapshot {
+    bytes: Vec<u8>,
+    permissions: fs::Permissions,
+}
+
+impl RepoChanges {
+    fn undo(self, layout: &Layout) -> Result<()> {
+        if let Some(before) = self.manifest {
+            restore(&layout.repo().join(MANIFEST), before)?;
+        }
+        for (path, before) in self.files.into_iter().rev() {
+            restore(&path, before)?;
+        }
+        for dir in self.dirs.iter().rev() {
+            fs::remove_dir(dir).map_err(|e| Error::io(dir, e))?;
+        }
+        Ok(())
+    }
+}
+
+fn snapshot(path: &Path) -> Result<Option<Snapshot>> {
+    let read = fs::metadata(path).and_then(|meta| {
+        Ok(Snapshot {
+            bytes: fs::read(path)?,
+            permissions: meta.permissions(),
+        })
+    });
+    match read {
+        Ok(snapshot) => Ok(Some(snapshot)),
+        Err(e) if is_missing(&e) => Ok(None),
+        Err(e) => Err(Error::io(path, e)),
+    }
+}
+
+/// Puts `path` back to holding `before`, or to not existing.
+fn restore(path: &Path, before: Option<Snapshot>) -> Result<()> {
+    match before {
+        Some(before) => fs::write(path, before.bytes)
+            .and_then(|()| fs::set_permissions(path, before.permissions)),
+        None => match fs::remove_file(path) {
+            Err(e) if is_missing(&e) => Ok(()),
+            other => other,
+        },
+    }
+    .map_err(|e| Error::io(path, e))
+}
+
+/// Turns `path` into a path relative to home that discovery would pick up
+/// from the repository: a dot-file at the top of home, or something below
+/// the destination of a manifest entry.
+fn home_relative(layout: &Layout, manifest: &Manifest, path: &Path) -> Result<PathBuf> {
+    let cannot = |reason: &str| Error::CannotAdopt {
+        path: path.to_path_buf(),
+        reason: reason.to_string(),
+    };
+    let rel = if path.is_absolute() {
+        // Resolve the parent only: the path itself may well be a symlink.
+        let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+            return Err(cannot("it is not inside the home directory"));
+        };
+        let parent = fs::canonicalize(parent).map_err(|e| Error::io(parent, e))?;
+        parent
+            .join(name)
+            .strip_prefix(layout.home())
+            .map_err(|_| cannot("it is not inside the home directory"))?
+            .to_path_buf()
+    } else {
+        path.to_path_buf()
+    };
+    if !rel.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(cannot("it is not inside the home directory"));
+    }
+    let Some(first) = rel.components().next().and_then(|c| c.as_os_str().to_str()) else {
+        return Err(cannot("it is the home directory itself"));
+    };
+    let mapped = manifest.source_for(&rel) != rel;
+    if !mapped && (!first.starts_with('.') || IGNORED.contains(&first)) {
+        return Err(cannot("only dot-files at the top of home are managed"));
+    }
+    let dest = layout.dest(&rel);
+    if dest.starts_with(layout.state_dir()) || layout.state_dir().starts_with(&dest) {
+        return Err(cannot("it holds the tool's own state"));
+    }
+    if dest.starts_with(layout.repo()) || layout.repo().starts_with(&dest) {
+        return Err(cannot("it overlaps the repository"));
+    }
+    Ok(rel)
+}
+
+/// Collects every file at or below `rel`, skipping submodule `.git` entries
+/// just as discovery does.
+fn walk(layout: &Layout, rel: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
+    let full = layout.dest(rel);
+    let meta = fs::symlink_metadata(&full).map_err(|e| Error::io(&full, e))?;
+    if !meta.is_dir() {
+        out.push(rel.to_path_buf());
+        return Ok(());
+    }
+    let mut names = fs::read_dir(&full)
+        .map_err(|e| Error::io(&full, e))?
+        .map(|entry| entry.map(|e| e.file_name()))
+        .collect::<std::io::Result<Vec<_>>>()
+        .map_err(|e| Error::io(&full, e))?;
+    names.sort();
+    for name in names {
+        if name == ".git" {
+            continue;
+        }
+        walk(layout, &rel.join(name), out)?;
+    }
+    Ok(())
+}
+
+fn copy_into_repo(
+    layout: &Layout,
+    rel: &Path,
+    source: &Path,
+    changes: &mut RepoChanges,
+) -> Result<Outcome> {
+    let live = layout.dest(rel);
+    let source = layout.source(source);
+    let before = snapshot(&source)?;
+    let outcome = match fs::symlink_metadata(&source) {
+        Ok(meta) if meta.is_dir() => {
+            return Err(Error::CannotAdopt {
+                path: live,
+                reason: format!("{} is a directory", source.display()),
+            })
+        }
+        Ok(_) => {
+            fs::remove_file(&source).map_err(|e| Error::io(&source, e))?;
+            Outcome::Updated
+        }
+        Err(_) => Outcome::Added,
+    };
+    changes.files.push((source.clone(), before));
+    if let Some(parent) = source.parent() {
+        let mut missing: Vec<_> = parent.ancestors().take_while(|dir| !dir.exists()).collect();
+        missing.reverse();
+        fs::create_dir_all(parent).map_err(|e| Error::io(parent, e))?;
+        changes
+            .dirs
+            .extend(missing.into_iter().map(Path::to_path_buf));
+    }
+    fs::copy(&live, &source).map_err(|e| Error::io(&source, e))?;
+    Ok(outcome)
+}
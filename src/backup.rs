@@ -0,0 +1,397 @@
+//! Moving pre-existing files out of the way, and putting them back.
+//!
+//! Each install that displaces something gets its own backup directory under
+//! the state directory, named after the time it was taken:
+//!
+//! ```text
+//! backups/20261015T101500Z/manifest.json
+//! backups/20261015T101500Z/files/.vimrc
+//! ```
+//!
+//! Originals are renamed rather than copied, so content, mode, mtime and
+//! symlink-ness all survive the round trip untouched. The manifest records
+//! the metadata anyway so it can be re-applied if a rename ever has to fall
+//! back to a copy.
+
+use std::fs::{self, File};
+use std::io;
+use std::os::unix::fs::{symlink, MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::fsutil::{is_missing, read_json, write_json, FileKind};
+use crate::installed::{Installed, InstalledFile};
+use crate::layout::Layout;
+use crate::strategy::{inspect, Found};
+use crate::template::Values;
+
+const MANIFEST: &str = "manifest.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub id: String,
+    /// Seconds since the Unix epoch.
+    pub created: u64,
+    pub entries: Vec<BackedUp>,
+}
+
+/// One displaced file, identified by its path relative to the home directory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackedUp {
+    pub path: PathBuf,
+    pub kind: BackupKind,
+    pub mode: u32,
+    pub mtime: Timestamp,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_target: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupKind {
+    File,
+    Symlink,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Timestamp {
+    pub secs: i64,
+    pub nanos: u32,
+}
+
+impl Timestamp {
+    fn of(meta: &fs::Metadata) -> Self {
+        Timestamp {
+            secs: meta.mtime(),
+            nanos: meta.mtime_nsec() as u32,
+        }
+    }
+
+    fn to_system_time(self) -> SystemTime {
+        let nanos = Duration::new(0, self.nanos);
+        if self.secs >= 0 {
+            UNIX_EPOCH + Duration::from_secs(self.secs as u64) + nanos
+        } else {
+            UNIX_EPOCH - Duration::from_secs(self.secs.unsigned_abs()) + nanos
+        }
+    }
+}
+
+/// The collection of backups kept for one home directory.
+pub struct BackupStore {
+    root: PathBuf,
+}
+
+impl BackupStore {
+    pub fn new(layout: &Layout) -> Self {
+        BackupStore {
+            root: layout.state_dir().join("backups"),
+        }
+    }
+
+    /// Starts a new, empty backup with a fresh id.
+    pub fn create(&self) -> Result<Backup> {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        fs::create_dir_all(&self.root).map_err(|e| Error::io(&self.root, e))?;
+
+        let base = format_utc(secs);
+        let mut id = base.clone();
+        let mut n = 1;
+        let dir = loop {
+            let dir = self.root.join(&id);
+            match fs::create_dir(&dir) {
+                Ok(()) => break dir,
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    n += 1;
+                    id = format!("{base}-{n}");
+                }
+                Err(e) => return Err(Error::io(&dir, e)),
+            }
+        };
+        let backup = Backup {
+            dir,
+            manifest: Manifest {
+                id,
+                created: secs,
+                entries: Vec::new(),
+            },
+        };
+        backup.save()?;
+        Ok(backup)
+    }
+
+    /// Reopens an existing backup so more files can be taken into it or put
+    /// back out of it.
+    pub fn open(&self, id: &str) -> Result<Backup> {
+        Ok(Backup {
+            manifest: self.load(id)?,
+            dir: self.root.join(id),
+        })
+    }
+
+    /// Every backup in the store, oldest first.
+    pub fn list(&self) -> Result<Vec<Manifest>> {
+        let read = match fs::read_dir(&self.root) {
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(Error::io(&self.root, e)),
+        };
+        let mut manifests = Vec::new();
+        for entry in read {
+            let entry = entry.map_err(|e| Error::io(&self.root, e))?;
+            let path = entry.path().join(MANIFEST);
+            if path.exists() {
+                manifests.push(read_json::<Manifest>(&path)?);
+            }
+        }
+        manifests.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(manifests)
+    }
+
+    pub fn load(&self, id: &str) -> Result<Manifest> {
+        let path = self.root.join(id).join(MANIFEST);
+        if id.contains('/') || !path.exists() {
+            return Err(Error::BackupNotFound(id.to_string()));
+        }
+        read_json(&path)
+    }
+
+    /// Puts every file in backup `id` back where it came from.
+    ///
+    /// A destination may only be replaced if it is missing, is one of our
+    /// symlinks into `layout.repo()`, or is a file we installed that has
+    /// not been edited since; anything else aborts the restore before a
+    /// single file is moved. The restored paths are dropped from the install
+    /// record, and the backup is deleted once it is empty.
+    pub fn restore(&self, layout: &Layout, id: &str) -> Result<Vec<PathBuf>> {
+        let manifest = self.load(id)?;
+        let dir = self.root.join(id);
+        let mut installed = Installed::load(layout)?;
+        let values = Values::load(layout)?;
+
+        for entry in &manifest.entries {
+            let dest = layout.dest(&entry.path);
+            let recorded = installed.files.get(&entry.path);
+            if !is_replaceable(layout, &dest, recorded, &values)? {
+                return Err(Error::RestoreBlocked(dest));
+            }
+        }
+
+        let mut restored = Vec::new();
+        for entry in &manifest.entries {
+            let dest = layout.dest(&entry.path);
+            remove_if_present(&dest)?;
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent).map_err(|e| Error::io(parent, e))?;
+            }
+            move_file(&dir.join("files").join(&entry.path), &dest, entry)?;
+            installed.files.remove(&entry.path);
+            restored.push(entry.path.clone());
+        }
+        installed.save(layout)?;
+
+        fs::remove_dir_all(&dir).map_err(|e| Error::io(&dir, e))?;
+        Ok(restored)
+    }
+}
+
+/// A backup being filled in, or unwound, by an install.
+pub struct Backup {
+    dir: PathBuf,
+    manifest: Manifest,
+}
+
+impl Backup {
+    pub fn id(&self) -> &str {
+        &self.manifest.id
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.manifest.entries.is_empty()
+    }
+
+    /// Moves `home/rel` into the backup. Only files and symlinks can be
+    /// backed up.
+    ///
+    /// Taking a file that has already been moved in is a no-op, so an
+    /// interrupted install can simply repeat the step.
+    pub fn take(&mut self, home: &Path, rel: &Path) -> Result<()> {
+        let src = home.join(rel);
+        let stored = self.stored(rel);
+        let meta = match fs::symlink_metadata(&src) {
+            Ok(meta) => meta,
+            Err(e) if is_missing(&e) && fs::symlink_metadata(&stored).is_ok() => {
+                if !self.contains(rel) {
+                    // Moved, but the manifest was not written before we
+                    // were interrupted. Renaming kept the metadata intact.
+                    let meta = fs::symlink_metadata(&stored).map_err(|e| Error::io(&stored, e))?;
+                    let entry = describe(&stored, rel, &meta)?;
+                    self.manifest.entries.push(entry);
+                    self.save()?;
+                }
+                return Ok(());
+            }
+            Err(e) => return Err(Error::io(&src, e)),
+        };
+        let entry = describe(&src, rel, &meta)?;
+
+        if let Some(parent) = stored.parent() {
+            fs::create_dir_all(parent).map_err(|e| Error::io(parent, e))?;
+        }
+        move_file(&src, &stored, &entry)?;
+
+        self.manifest.entries.retain(|e| e.path != rel);
+        self.manifest.entries.push(entry);
+        self.save()
+    }
+
+    /// Undoes [`take`](Self::take): moves the file back to `home/rel`, which
+    /// must be free again by now. Returns whether there was anything to move.
+    pub fn put_back(&mut self, home: &Path, rel: &Path) -> Result<bool> {
+        let Some(index) = self.manifest.entries.iter().position(|e| e.path == rel) else {
+            return Ok(false);
+        };
+        let stored = self.stored(rel);
+        let present = fs::symlink_metadata(&stored).is_ok();
+        if present {
+            let dest = home.join(rel);
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent).map_err(|e| Error::io(parent, e))?;
+            }
+            move_file(&stored, &dest, &self.manifest.entries[index])?;
+        }
+        self.manifest.entries.remove(index);
+        self.save()?;
+        Ok(present)
+    }
+
+    /// Deletes the backup if nothing is left in it.
+    pub fn discard_if_empty(self) -> Result<()> {
+        if self.is_empty() {
+            fs::remove_dir_all(&self.dir).map_err(|e| Error::io(&self.dir, e))?;
+        }
+        Ok(())
+    }
+
+    fn contains(&self, rel: &Path) -> bool {
+        self.manifest.entries.iter().any(|e| e.path == rel)
+    }
+
+    fn stored(&self, rel: &Path) -> PathBuf {
+        self.dir.join("files").join(rel)
+    }
+
+    fn save(&self) -> Result<()> {
+        write_json(&self.dir.join(MANIFEST), &self.manifest)
+    }
+}
+
+fn describe(path: &Path, rel: &Path, meta: &fs::Metadata) -> Result<BackedUp> {
+    let kind = match FileKind::of(meta) {
+        FileKind::File => BackupKind::File,
+        FileKind::Symlink => BackupKind::Symlink,
+        other => return Err(Error::CannotBackUp(path.to_path_buf(), other)),
+    };
+    let link_target = match kind {
+        BackupKind::Symlink => Some(fs::read_link(path).map_err(|e| Error::io(path, e))?),
+        BackupKind::File => None,
+    };
+    Ok(BackedUp {
+        path: rel.to_path_buf(),
+        kind,
+        mode: meta.mode() & 0o7777,
+        mtime: Timestamp::of(meta),
+        link_target,
+    })
+}
+
+fn is_replaceable(
+    layout: &Layout,
+    dest: &Path,
+    recorded: Option<&InstalledFile>,
+    values: &Values,
+) -> Result<bool> {
+    match fs::symlink_metadata(dest) {
+        Ok(meta) if meta.file_type().is_symlink() => {
+            let target = fs::read_link(dest).map_err(|e| Error::io(dest, e))?;
+            Ok(target.starts_with(layout.repo()))
+        }
+        // A copy, hardlink or rendered template of ours, as installed.
+        Ok(_) => match recorded {
+            Some(file) => Ok(matches!(
+                inspect(dest, &file.source, file.strategy, Some(file), values)?,
+                Found::Current | Found::Stale
+            )),
+            None => Ok(false),
+        },
+        Err(e) if is_missing(&e) => Ok(true),
+        Err(e) => Err(Error::io(dest, e)),
+    }
+}
+
+fn remove_if_present(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if is_missing(&e) => Ok(()),
+        Err(e) => Err(Error::io(path, e)),
+    }
+}
+
+/// Renames `from` to `to`, falling back to copy-and-delete across
+/// filesystems and re-applying the recorded metadata in that case.
+fn move_file(from: &Path, to: &Path, entry: &BackedUp) -> Result<()> {
+    match fs::rename(from, to) {
+        Ok(()) => return Ok(()),
+        Err(e) if e.kind() != io::ErrorKind::CrossesDevices => return Err(Error::io(from, e)),
+        Err(_) => {}
+    }
+    match entry.kind {
+        BackupKind::Symlink => {
+            let target = entry.link_target.as_deref().unwrap_or(Path::new(""));
+            symlink(target, to).map_err(|e| Error::io(to, e))?;
+        }
+        BackupKind::File => {
+            fs::copy(from, to).map_err(|e| Error::io(to, e))?;
+            fs::set_permissions(to, fs::Permissions::from_mode(entry.mode))
+                .map_err(|e| Error::io(to, e))?;
+            File::open(to)
+                .and_then(|f| f.set_modified(entry.mtime.to_system_time()))
+                .map_err(|e| Error::io(to, e))?;
+        }
+    }
+    fs::remove_file(from).map_err(|e| Error::io(from, e))
+}
+
+/// Formats seconds since the epoch as a compact UTC timestamp such as
+/// `20261015T101500Z`.
+fn format_utc(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+    let (y, m, d) = civil_from_days(days);
+    format!(
+        "{y:04}{m:02}{d:02}T{:02}{:02}{:02}Z",
+        rem / 3600,
+        rem / 60 % 60,
+        rem % 60
+    )
+}
+
+/// Howard Hinnant's days-to-civil conversion.
+fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + i64::from(m <= 2);
+    (y, m, d)
+}
@@ -0,0 +1,357 @@
+//! The vim bundles the repository pulls in as git submodules.
+//!
+//! Every bundle is declared in `.gitmodules`, but a clone only gets them
+//! after `git submodule update --init`, and anything can happen to a
+//! checkout afterwards. [`status`] looks at each declared bundle the way
+//! `git submodule status` would, and also points out directories under
+//! `.vim/bundle` that are committed to the repository instead.
+//!
+//! [`fix_urls`] rewrites submodule URLs that can no longer be cloned, by
+//! the rules in the [`Manifest`].
+//!
+//! [`disable`] and [`enable`] switch a bundle off and on the two ways
+//! pathogen allows: naming it in `g:pathogen_disabled` in `.vimrc`, or
+//! giving its directory a trailing `~`.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use similar::TextDiff;
+
+use crate::error::{Error, Result};
+use crate::fsutil::is_missing;
+use crate::gitmodules::GitModules;
+use crate::layout::Layout;
+use crate::manifest::Manifest;
+use crate::vimrc::{Vimrc, VIMRC};
+
+/// Where pathogen looks for bundles, relative to the repository root.
+pub const BUNDLE_DIR: &str = ".vim/bundle";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum State {
+    /// Nothing at the submodule's path.
+    Missing,
+    /// The path exists but holds no checkout.
+    Uninitialized,
+    /// Checked out, but the repository records no commit for it.
+    Unrecorded { head: String },
+    /// Checked out at a different commit from the one recorded.
+    Moved { recorded: String, head: String },
+    /// Checked out at the recorded commit.
+    Current,
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            State::Missing => "missing",
+            State::Uninitialized => "uninitialized",
+            State::Unrecorded { .. } => "unrecorded",
+            State::Moved { .. } => "moved",
+            State::Current => "ok",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundleStatus {
+    /// The submodule's name in `.gitmodules`.
+    pub name: String,
+    /// Relative to the repository root.
+    pub path: PathBuf,
+    pub url: Option<String>,
+    pub state: State,
+    /// Whether the checkout has uncommitted changes or untracked files.
+    pub dirty: bool,
+}
+
+impl BundleStatus {
+    pub fn is_clean(&self) -> bool {
+        self.state == State::Current && !self.dirty
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    /// Every submodule, in the order `.gitmodules` declares them.
+    pub bundles: Vec<BundleStatus>,
+    /// Directories under [`BUNDLE_DIR`] that are not submodules, relative
+    /// to the repository root.
+    pub vendored: Vec<PathBuf>,
+}
+
+impl Report {
+    pub fn is_clean(&self) -> bool {
+        self.bundles.iter().all(BundleStatus::is_clean)
+    }
+}
+
+/// Checks every submodule declared in the repository's `.gitmodules`.
+pub fn status(layout: &Layout) -> Result<Report> {
+    let repo = layout.repo();
+    let modules = GitModules::load(repo)?;
+    let mut report = Report::default();
+    for module in &modules.modules {
+        let path = Path::new(module.require(repo, "path")?);
+        let (state, dirty) = check(repo, path)?;
+        report.bundles.push(BundleStatus {
+            name: module.name.clone(),
+            path: path.to_path_buf(),
+            url: module.url().map(str::to_string),
+            state,
+            dirty,
+        });
+    }
+
+    let dir = repo.join(BUNDLE_DIR);
+    let names = match fs::read_dir(&dir) {
+        Ok(entries) => entries
+            .map(|entry| entry.map(|e| e.file_name()))
+            .collect::<std::io::Result<Vec<_>>>()
+            .map_err(|e| Error::io(&dir, e))?,
+        Err(e) if is_missing(&e) => Vec::new(),
+        Err(e) => return Err(Error::io(&dir, e)),
+    };
+    for name in names {
+        let path = Path::new(BUNDLE_DIR).join(name);
+        if repo.join(&path).is_dir() && modules.by_path(&path).is_none() {
+            report.vendored.push(path);
+        }
+    }
+    report.vendored.sort();
+    Ok(report)
+}
+
+/// A submodule URL changed by [`fix_urls`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlChange {
+    pub name: String,
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UrlFix {
+    pub changes: Vec<UrlChange>,
+    /// Unified diff of `.gitmodules`; empty if nothing changed.
+    pub diff: String,
+}
+
+/// Rewrites every submodule URL a manifest rule applies to. Only the URLs
+/// change: the rest of `.gitmodules` is left exactly as it was. With
+/// `dry_run` the file is not written.
+pub fn fix_urls(layout: &Layout, dry_run: bool) -> Result<UrlFix> {
+    let repo = layout.repo();
+    let manifest = Manifest::load(repo)?;
+    let mut modules = GitModules::load(repo)?;
+    let before = modules.to_string();
+
+    let mut fix = UrlFix::default();
+    let mut edits = Vec::new();
+    for module in &modules.modules {
+        for field in module.fields.iter().filter(|f| f.key == "url") {
+            if let Some(to) = manifest.rewrite_url(&field.value) {
+                edits.push((field.line, to.clone()));
+                fix.changes.push(UrlChange {
+                    name: module.name.clone(),
+                    from: field.value.clone(),
+                    to,
+                });
+            }
+        }
+    }
+    if edits.is_empty() {
+        return Ok(fix);
+    }
+    for (line, url) in edits {
+        modules.set_value(line, &url);
+    }
+
+    let after = modules.to_string();
+    fix.diff = TextDiff::from_lines(&before, &after)
+        .unified_diff()
+        .header("a/.gitmodules", "b/.gitmodules")
+        .to_string();
+    if !dry_run {
+        modules.save(repo)?;
+    }
+    Ok(fix)
+}
+
+/// How [`disable`] switches a bundle off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mechanism {
+    /// Named in `g:pathogen_disabled` in `.vimrc`.
+    Listed,
+    /// Renamed to end in `~`.
+    Renamed,
+}
+
+/// Switches off the bundle called `name` in [`BUNDLE_DIR`] by `how`,
+/// undoing the other mechanism if it was used, so that a disabled bundle
+/// is disabled one way only. Returns whether anything changed.
+///
+/// Submodules and plugins are never renamed: git would see the submodule
+/// as deleted, and `plugins install` would clone the plugin again.
+pub fn disable(layout: &Layout, name: &str, how: Mechanism) -> Result<bool> {
+    toggle(layout, name, Some(how))
+}
+
+/// Switches the bundle called `name` back on, taking it out of
+/// `g:pathogen_disabled` and dropping the `~` from its directory. Returns
+/// whether anything changed.
+pub fn enable(layout: &Layout, name: &str) -> Result<bool> {
+    toggle(layout, name, None)
+}
+
+fn toggle(layout: &Layout, name: &str, how: Option<Mechanism>) -> Result<bool> {
+    let repo = layout.repo();
+    let name = name.strip_suffix('~').unwrap_or(name);
+    let plain = Path::new(BUNDLE_DIR).join(name);
+    let tilde = Path::new(BUNDLE_DIR).join(format!("{name}~"));
+    let mut vimrc = Vimrc::load(repo)?;
+    let listed = vimrc.disabled().iter().any(|n| n == name);
+    let renamed = repo.join(&tilde).is_dir();
+    if !listed && !renamed && !repo.join(&plain).is_dir() {
+        return Err(Error::UnknownBundle(name.to_string()));
+    }
+
+    let list = how == Some(Mechanism::Listed);
+    if list && !listed && vimrc.infect_source().is_none() {
+        return Err(Error::NoPathogen(repo.join(VIMRC)));
+    }
+    let rename = how == Some(Mechanism::Renamed);
+    if rename != renamed {
+        let (from, to) = if rename {
+            (&plain, &tilde)
+        } else {
+            (&tilde, &plain)
+        };
+        let refuse = |reason: String| Error::CannotRename {
+            path: from.clone(),
+            reason,
+        };
+        if rename && GitModules::load(repo)?.by_path(from).is_some() {
+            return Err(refuse("it is a submodule; disable it in .vimrc".into()));
+        }
+        if rename
+            && Manifest::load(repo)?
+                .plugins
+                .iter()
+                .any(|p| &p.path == from)
+        {
+            return Err(refuse("it is a plugin; disable it in .vimrc".into()));
+        }
+        if !repo.join(from).is_dir() {
+            return Err(Error::UnknownBundle(name.to_string()));
+        }
+        if fs::symlink_metadata(repo.join(to)).is_ok() {
+            return Err(refuse(format!("{} already exists", to.display())));
+        }
+        fs::rename(repo.join(from), repo.join(to)).map_err(|e| Error::io(repo.join(from), e))?;
+    }
+    if list != listed {
+        let mut names = vimrc.disabled().to_vec();
+        if list {
+            names.push(name.to_string());
+        } else {
+            names.retain(|n| n != name);
+        }
+        vimrc.set_disabled(&names);
+        vimrc.save(repo)?;
+    }
+    Ok(rename != renamed || list != listed)
+}
+
+fn check(repo: &Path, path: &Path) -> Result<(State, bool)> {
+    let dir = repo.join(path);
+    if fs::symlink_metadata(&dir).is_err() {
+        return Ok((State::Missing, false));
+    }
+    let Some(head) = head(&dir)? else {
+        return Ok((State::Uninitialized, false));
+    };
+    let dirty = is_dirty(&dir)?;
+    let state = match recorded(repo, path)? {
+        None => State::Unrecorded { head },
+        Some(recorded) if recorded == head => State::Current,
+        Some(recorded) => State::Moved { recorded, head },
+    };
+    Ok((state, dirty))
+}
+
+/// Whether the checkout in `dir` has uncommitted changes or untracked
+/// files, not counting the help tags [`helptags`](crate::helptags) writes.
+pub(crate) fn is_dirty(dir: &Path) -> Result<bool> {
+    let args = [
+        "status",
+        "--porcelain",
+        "--",
+        ".",
+        ":(exclude)doc/tags",
+        ":(exclude)doc/tags-??",
+    ];
+    Ok(!git(dir, &args)?.is_empty())
+}
+
+/// The commit checked out in `dir`, if it is a checkout of its own.
+pub(crate) fn head(dir: &Path) -> Result<Option<String>> {
+    // Without a `.git` of its own, git would find the superproject instead.
+    if fs::symlink_metadata(dir.join(".git")).is_err() {
+        return Ok(None);
+    }
+    try_git(dir, &["rev-parse", "--verify", "--quiet", "HEAD"])
+}
+
+/// The commit the superproject's index records for the submodule at
+/// `path`, if any.
+pub(crate) fn recorded(repo: &Path, path: &Path) -> Result<Option<String>> {
+    let path = path.to_string_lossy();
+    let Some(listing) = try_git(repo, &["ls-files", "--stage", "--", &path])? else {
+        return Ok(None);
+    };
+    // `<mode> <object> <stage>\t<path>`, with mode 160000 for a submodule.
+    Ok(listing.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        match (fields.next(), fields.next()) {
+            (Some("160000"), Some(object)) => Some(object.to_string()),
+            _ => None,
+        }
+    }))
+}
+
+fn run_git(dir: &Path, args: &[&str]) -> Result<Output> {
+    Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| Error::io("git", e))
+}
+
+/// Runs git in `dir` and returns its output, failing if git does.
+pub(crate) fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = run_git(dir, args)?;
+    if !output.status.success() {
+        return Err(Error::Git {
+            args: args.join(" "),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_string())
+}
+
+/// Runs git in `dir`, where it failing is an answer rather than an error.
+pub(crate) fn try_git(dir: &Path, args: &[&str]) -> Result<Option<String>> {
+    let output = run_git(dir, args)?;
+    Ok(output.status.success().then(|| {
+        String::from_utf8_lossy(&output.stdout)
+            .trim_end()
+            .to_string()
+    }))
+}
@@ -0,0 +1,339 @@
+//! Finding vim bundles that step on each other.
+//!
+//! Pathogen puts every directory in `.vim/bundle` on the runtimepath in
+//! sorted order, and Vim then sources each one's `plugin/` scripts in that
+//! order. Two bundles that ship the same plugin both get loaded; the first
+//! normally wins, since most plugins bail out early once a
+//! `g:loaded_<name>` guard is set, and the second's commands fail or
+//! quietly redefine the first's.
+//!
+//! Bundles are compared on what can be known from `.gitmodules` alone (the
+//! upstream URL and the project name) and, for those checked out, on the
+//! `plugin/` files they provide and the commands they define. Bundles
+//! pathogen leaves out, because their name ends in `~` or is in
+//! `g:pathogen_disabled`, cannot conflict with anything.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::bundles::BUNDLE_DIR;
+use crate::error::{Error, Result};
+use crate::fsutil::is_missing;
+use crate::gitmodules::GitModules;
+use crate::layout::Layout;
+use crate::manifest::Manifest;
+use crate::pathogen;
+
+/// Plugins that come with Vim itself, either loaded by default or as
+/// optional packages.
+pub const VIM_PLUGINS: &[&str] = &[
+    "comment",
+    "editorconfig",
+    "getscript",
+    "gzip",
+    "logipat",
+    "manpager",
+    "matchit",
+    "matchparen",
+    "netrw",
+    "rrhelper",
+    "spellfile",
+    "tar",
+    "termdebug",
+    "tohtml",
+    "vimball",
+    "zip",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Overlap {
+    /// Submodules cloned from the same repository.
+    SameUpstream(String),
+    /// Different repositories with the same project name, such as a fork
+    /// or a vim-scripts mirror.
+    SameName(String),
+    /// The same script under `plugin/`, relative to the bundle.
+    SameFile(PathBuf),
+    /// The same user command.
+    SameCommand(String),
+    /// A plugin Vim already ships with.
+    ShippedWithVim(&'static str),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub overlap: Overlap,
+    /// Relative to the repository root, in the order pathogen loads them.
+    /// For [`Overlap::ShippedWithVim`] there is only one.
+    pub bundles: Vec<PathBuf>,
+}
+
+impl Conflict {
+    /// The bundle whose copy takes effect: the first one on the
+    /// runtimepath. Bundles also come before Vim's own runtime files.
+    pub fn winner(&self) -> &Path {
+        &self.bundles[0]
+    }
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<_> = self
+            .bundles
+            .iter()
+            .map(|b| b.display().to_string())
+            .collect();
+        let names = names.join(", ");
+        match &self.overlap {
+            Overlap::SameUpstream(url) => write!(f, "{names} are all clones of {url}")?,
+            Overlap::SameName(name) => write!(f, "{names} are all called {name}")?,
+            Overlap::SameFile(file) => write!(f, "{names} all provide {}", file.display())?,
+            Overlap::SameCommand(command) => write!(f, "{names} all define :{command}")?,
+            Overlap::ShippedWithVim(plugin) => {
+                return write!(f, "{names} duplicates {plugin}, which ships with Vim");
+            }
+        }
+        write!(f, "; {} loads first", self.winner().display())
+    }
+}
+
+struct Bundle {
+    path: PathBuf,
+    url: Option<String>,
+    name: String,
+    files: Vec<PathBuf>,
+    commands: Vec<String>,
+}
+
+/// Every overlap between the bundles declared in `.gitmodules` or present
+/// in the bundle directory.
+pub fn conflicts(layout: &Layout) -> Result<Vec<Conflict>> {
+    let repo = layout.repo();
+    let manifest = Manifest::load(repo)?;
+    let bundles = load_bundles(layout, &manifest)?;
+
+    let mut conflicts = Vec::new();
+    let by_url = group(&bundles, |b| b.url.iter().cloned().collect());
+    for (url, paths) in &by_url {
+        conflicts.push(Conflict {
+            overlap: Overlap::SameUpstream(url.clone()),
+            bundles: paths.clone(),
+        });
+    }
+    for (name, paths) in group(&bundles, |b| vec![b.name.clone()]) {
+        // Already reported if they are all the same repository.
+        if !by_url.values().any(|same| *same == paths) {
+            conflicts.push(Conflict {
+                overlap: Overlap::SameName(name),
+                bundles: paths,
+            });
+        }
+    }
+    for (file, paths) in group(&bundles, |b| {
+        b.files
+            .iter()
+            .map(|f| f.to_string_lossy().into_owned())
+            .collect()
+    }) {
+        conflicts.push(Conflict {
+            overlap: Overlap::SameFile(file.into()),
+            bundles: paths,
+        });
+    }
+    for (command, paths) in group(&bundles, |b| b.commands.clone()) {
+        conflicts.push(Conflict {
+            overlap: Overlap::SameCommand(command),
+            bundles: paths,
+        });
+    }
+    for bundle in &bundles {
+        let shipped = VIM_PLUGINS.iter().find(|&&plugin| {
+            bundle.name == plugin
+                || bundle
+                    .files
+                    .contains(&Path::new("plugin").join(format!("{plugin}.vim")))
+        });
+        if let Some(plugin) = shipped {
+            conflicts.push(Conflict {
+                overlap: Overlap::ShippedWithVim(plugin),
+                bundles: vec![bundle.path.clone()],
+            });
+        }
+    }
+    Ok(conflicts)
+}
+
+/// Bundles, in runtimepath order, keyed by each of the values `keys`
+/// gives, keeping only the keys more than one bundle has.
+fn group(
+    bundles: &[Bundle],
+    keys: impl Fn(&Bundle) -> Vec<String>,
+) -> BTreeMap<String, Vec<PathBuf>> {
+    let mut groups: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    for bundle in bundles {
+        let mut keys = keys(bundle);
+        keys.sort();
+        keys.dedup();
+        for key in keys {
+            groups.entry(key).or_default().push(bundle.path.clone());
+        }
+    }
+    groups.retain(|_, paths| paths.len() > 1);
+    groups
+}
+
+/// The bundles pathogen puts on the runtimepath, in its order, followed
+/// by the submodules under the bundle directory that are not checked out
+/// yet, in path order, leaving out those it would disable.
+fn load_bundles(layout: &Layout, manifest: &Manifest) -> Result<Vec<Bundle>> {
+    let repo = layout.repo();
+    let rtp = pathogen::resolve(layout)?;
+    let mut urls: HashMap<PathBuf, String> = HashMap::new();
+    let mut unloaded = Vec::new();
+    for module in &GitModules::load(repo)?.modules {
+        let Some(path) = module.path() else { continue };
+        if path.parent() != Some(Path::new(BUNDLE_DIR)) {
+            continue;
+        }
+        if let Some(url) = module.url() {
+            urls.insert(path.to_path_buf(), url.to_string());
+        }
+        let disabled = pathogen::is_disabled(&path.to_string_lossy(), &rtp.disabled);
+        if !disabled && !repo.join(path).is_dir() {
+            unloaded.push(path.to_path_buf());
+        }
+    }
+    unloaded.sort();
+
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for dir in rtp.dirs() {
+        let Ok(path) = Path::new(&dir).strip_prefix(repo) else {
+            continue;
+        };
+        if path.parent() == Some(Path::new(BUNDLE_DIR)) && !paths.iter().any(|p| p == path) {
+            paths.push(path.to_path_buf());
+        }
+    }
+    paths.extend(unloaded);
+
+    let mut bundles = Vec::new();
+    for path in paths {
+        let url = urls.remove(&path);
+        let url = url.map(|url| normalize_url(&manifest.rewrite_url(&url).unwrap_or(url)));
+        let name = project_name(url.as_deref().unwrap_or(&path.to_string_lossy()));
+        let mut scripts = Vec::new();
+        collect_scripts(&repo.join(&path), Path::new(""), &mut scripts)?;
+        scripts.sort();
+        let mut files = Vec::new();
+        let mut commands = Vec::new();
+        for script in scripts {
+            if script.starts_with("plugin") {
+                files.push(script.clone());
+            }
+            let full = repo.join(&path).join(&script);
+            let bytes = fs::read(&full).map_err(|e| Error::io(&full, e))?;
+            commands.extend(defined_commands(&String::from_utf8_lossy(&bytes)));
+        }
+        bundles.push(Bundle {
+            path,
+            url,
+            name,
+            files,
+            commands,
+        });
+    }
+    Ok(bundles)
+}
+
+/// `https://github.com/tpope/vim-surround.git/` and
+/// `git@github.com:tpope/vim-surround` both become
+/// `github.com/tpope/vim-surround`.
+fn normalize_url(url: &str) -> String {
+    let url = url.trim_end_matches('/');
+    let url = url.strip_suffix(".git").unwrap_or(url);
+    let url = match url.split_once("://") {
+        Some((_, rest)) => rest.to_string(),
+        // scp-like `user@host:path`.
+        None => url.replacen(':', "/", 1),
+    };
+    let url = url.rsplit_once('@').map_or(url.as_str(), |(_, host)| host);
+    url.to_ascii_lowercase()
+}
+
+/// The plugin's name without the `vim-` or `.vim` decoration it usually
+/// carries, from the last part of a URL or path.
+fn project_name(url: &str) -> String {
+    let name = url.rsplit('/').next().unwrap_or(url).to_ascii_lowercase();
+    let name = name.strip_suffix(".git").unwrap_or(&name);
+    let name = name
+        .strip_suffix(".vim")
+        .or_else(|| name.strip_suffix("-vim"))
+        .or_else(|| name.strip_prefix("vim-"))
+        .unwrap_or(name);
+    name.to_string()
+}
+
+/// Every `.vim` file below `dir`, relative to it, skipping `.git`.
+pub(crate) fn collect_scripts(dir: &Path, rel: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
+    let full = dir.join(rel);
+    let entries = match fs::read_dir(&full) {
+        Ok(entries) => entries,
+        Err(e) if is_missing(&e) => return Ok(()),
+        Err(e) => return Err(Error::io(&full, e)),
+    };
+    for entry in entries {
+        let entry = entry.map_err(|e| Error::io(&full, e))?;
+        let name = entry.file_name();
+        if name == ".git" {
+            continue;
+        }
+        let child = rel.join(&name);
+        let file_type = entry.file_type().map_err(|e| Error::io(entry.path(), e))?;
+        if file_type.is_dir() {
+            collect_scripts(dir, &child, out)?;
+        } else if child.extension().is_some_and(|ext| ext == "vim") {
+            out.push(child);
+        }
+    }
+    Ok(())
+}
+
+/// Names of the global user commands a script defines with `:command`.
+/// Buffer-local ones cannot clash between bundles.
+pub fn defined_commands(script: &str) -> Vec<String> {
+    let mut commands = Vec::new();
+    for line in script.lines() {
+        let line = line.trim_start().trim_start_matches(':');
+        let end = line
+            .find(|c: char| c == '!' || c.is_whitespace())
+            .unwrap_or(line.len());
+        let word = &line[..end];
+        // `:com` through `:command`.
+        if word.len() < 3 || !"command".starts_with(word) {
+            continue;
+        }
+        let mut rest = line[end..].strip_prefix('!').unwrap_or(&line[end..]);
+        if !rest.starts_with(char::is_whitespace) {
+            continue;
+        }
+        let mut buffer = false;
+        let name = loop {
+            rest = rest.trim_start();
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let token = &rest[..end];
+            rest = &rest[end..];
+            match token.strip_prefix('-') {
+                Some(attr) => buffer |= attr == "buffer",
+                None => break token,
+            }
+        };
+        let valid = name.starts_with(|c: char| c.is_ascii_uppercase())
+            && name.chars().all(|c| c.is_ascii_alphanumeric());
+        if valid && !buffer {
+            commands.push(name.to_string());
+        }
+    }
+    commands
+}
@@ -0,0 +1,134 @@
+//! Finding the files in the repository that belong in a home directory.
+//!
+//! Every dot-prefixed entry at the repository root is a dotfile, except the
+//! handful that describe the repository itself, unless the
+//! [`Manifest`](crate::manifest::Manifest) turns that off. Whatever the
+//! manifest lists is a dotfile too. Directories such as `.vim/` are walked
+//! recursively so that each file is linked on its own and whatever else
+//! lives in `~/.vim` is left untouched.
+//!
+//! The manifest also decides where each file goes, how it is installed and
+//! which files are skipped on this host or ignored altogether. Finally the
+//! host's profile, if it has one, narrows the files down and lays its
+//! overlay on top.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::layout::Layout;
+use crate::manifest::Manifest;
+use crate::strategy::Strategy;
+
+/// Root entries that are repository metadata rather than dotfiles.
+pub(crate) const IGNORED: &[&str] = &[".git", ".gitignore", ".gitmodules"];
+
+/// A single file to be installed.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Entry {
+    /// Where the file goes, relative to home.
+    pub path: PathBuf,
+    /// Where the file comes from, relative to the repository root.
+    pub source: PathBuf,
+    #[serde(default)]
+    pub strategy: Strategy,
+}
+
+/// Returns every file under the repository to install on the layout's host,
+/// sorted by destination.
+pub fn discover(layout: &Layout) -> Result<Vec<Entry>> {
+    let repo = layout.repo();
+    let manifest = Manifest::load(repo)?;
+    let profile = manifest.select_profile(layout.host(), layout.profile())?;
+    let mut roots = BTreeSet::new();
+    if manifest.implicit {
+        for name in read_dir_sorted(repo)? {
+            let Some(s) = name.to_str() else { continue };
+            if s.starts_with('.') && !IGNORED.contains(&s) {
+                roots.insert(PathBuf::from(name));
+            }
+        }
+    }
+    roots.extend(manifest.entries.iter().map(|e| e.path.clone()));
+
+    let mut sources = BTreeSet::new();
+    for root in &roots {
+        walk(repo, &manifest, root, &mut sources)?;
+    }
+
+    let mut entries: BTreeMap<PathBuf, Entry> = BTreeMap::new();
+    for source in sources {
+        if manifest.is_overlay(&source)
+            || !manifest.applies_to(&source, layout.host())
+            || profile.is_some_and(|p| !p.selects(&source))
+        {
+            continue;
+        }
+        let entry = Entry {
+            path: manifest.dest_for(&source),
+            strategy: manifest.strategy_for(&source),
+            source,
+        };
+        if let Some(other) = entries.get(&entry.path) {
+            return Err(Error::DuplicateDest {
+                dest: entry.path,
+                sources: (other.source.clone(), entry.source),
+            });
+        }
+        entries.insert(entry.path.clone(), entry);
+    }
+
+    if let Some(overlay) = profile.and_then(|p| p.overlay.as_ref()) {
+        let mut sources = BTreeSet::new();
+        walk(repo, &manifest, overlay, &mut sources)?;
+        for source in sources {
+            let path = source
+                .strip_prefix(overlay)
+                .unwrap_or(&source)
+                .to_path_buf();
+            let strategy = entries.get(&path).map_or(manifest.strategy, |e| e.strategy);
+            entries.insert(
+                path.clone(),
+                Entry {
+                    path,
+                    source,
+                    strategy,
+                },
+            );
+        }
+    }
+    Ok(entries.into_values().collect())
+}
+
+fn walk(repo: &Path, manifest: &Manifest, rel: &Path, out: &mut BTreeSet<PathBuf>) -> Result<()> {
+    if manifest.is_ignored(rel) {
+        return Ok(());
+    }
+    let full = repo.join(rel);
+    let meta = fs::symlink_metadata(&full).map_err(|e| Error::io(&full, e))?;
+    if !meta.is_dir() {
+        out.insert(rel.to_path_buf());
+        return Ok(());
+    }
+    for name in read_dir_sorted(&full)? {
+        // Submodule checkouts carry a `.git` file or directory of their own.
+        if name == ".git" {
+            continue;
+        }
+        walk(repo, manifest, &rel.join(name), out)?;
+    }
+    Ok(())
+}
+
+fn read_dir_sorted(dir: &Path) -> Result<Vec<std::ffi::OsString>> {
+    let mut names = fs::read_dir(dir)
+        .map_err(|e| Error::io(dir, e))?
+        .map(|entry| entry.map(|e| e.file_name()))
+        .collect::<std::io::Result<Vec<_>>>()
+        .map_err(|e| Error::io(dir, e))?;
+    names.sort();
+    Ok(names)
+}
@@ -0,0 +1,226 @@
+use std::io;
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+use crate::fsutil::FileKind;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("HOME is not set; pass --home explicitly")]
+    NoHome,
+
+    #[error("{} is not a directory", .0.display())]
+    NotADirectory(PathBuf),
+
+    #[error("{}: {source}", path.display())]
+    Git2 {
+        path: PathBuf,
+        #[source]
+        source: git2::Error,
+    },
+
+    #[error("{}: {source}", path.display())]
+    Json {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    #[error("{}: {source}", path.display())]
+    Toml {
+        path: PathBuf,
+        #[source]
+        source: toml::de::Error,
+    },
+
+    #[error("{}:{line}:{column}: {message}", path.display())]
+    Manifest {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+
+    #[error("{}:{line}: {message}", path.display())]
+    Template {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+
+    #[error("{} would be installed from both {} and {}", dest.display(), sources.0.display(), sources.1.display())]
+    DuplicateDest {
+        dest: PathBuf,
+        sources: (PathBuf, PathBuf),
+    },
+
+    #[error("no profile named {0:?} in dotfiles.toml")]
+    UnknownProfile(String),
+
+    #[error("host {host} matches more than one profile ({}); pick one with --profile", profiles.join(", "))]
+    AmbiguousProfile { host: String, profiles: Vec<String> },
+
+    #[error("{0:?} is not a valid secret name")]
+    BadSecretName(String),
+
+    #[error("cannot unlock {}: wrong passphrase, or the file is damaged", .0.display())]
+    SecretsLocked(PathBuf),
+
+    #[error("deriving the secrets key: {0}")]
+    Kdf(String),
+
+    #[error("no passphrase for the secret store; set DOTFILES_PASSPHRASE or run from a terminal")]
+    NoPassphrase,
+
+    #[error("the passphrases do not match")]
+    PassphraseMismatch,
+
+    #[error("{}:{line}: {message}", path.display())]
+    GitModules {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+
+    #[error("{}:{line}: {message}", path.display())]
+    Vimrc {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+
+    #[error("{} never calls pathogen#infect()", .0.display())]
+    NoPathogen(PathBuf),
+
+    #[error("there is no bundle called {0} in .vim/bundle")]
+    UnknownBundle(String),
+
+    #[error("cannot find {0} in the runtimepath")]
+    NotInRuntimepath(String),
+
+    #[error("cannot rename {}: {reason}", path.display())]
+    CannotRename { path: PathBuf, reason: String },
+
+    #[error("{}: mix of help file encodings within a language", .0.display())]
+    HelpEncoding(PathBuf),
+
+    #[error("`git {args}` failed: {stderr}")]
+    Git { args: String, stderr: String },
+
+    #[error(
+        "{} is not checked out; run `git submodule update --init` or `dotfiles plugins install` first",
+        .0.display()
+    )]
+    NotCheckedOut(PathBuf),
+
+    #[error("no commit is recorded for {}; run `dotfiles bundles lock`", .0.display())]
+    NoPin(PathBuf),
+
+    #[error("{} is not pinned to a tag in dotfiles.toml", .0.display())]
+    NotPinnedToTag(PathBuf),
+
+    #[error("{} at {commit} is not in the cache", path.display())]
+    NotInCache { path: PathBuf, commit: String },
+
+    #[error("cannot install into {}: {reason}", path.display())]
+    BundleInTheWay { path: PathBuf, reason: String },
+
+    #[error("{}: unsupported lockfile version {version}", path.display())]
+    LockfileVersion { path: PathBuf, version: u32 },
+
+    #[error("no backup with id {0:?}")]
+    BackupNotFound(String),
+
+    #[error("cannot back up {}: it is a {}", .0.display(), .1)]
+    CannotBackUp(PathBuf, FileKind),
+
+    #[error("refusing to restore over {}: it is not something dotfiles installed", .0.display())]
+    RestoreBlocked(PathBuf),
+
+    #[error("cannot adopt {}: {reason}", path.display())]
+    CannotAdopt { path: PathBuf, reason: String },
+
+    #[error("an interrupted install is pending; run `dotfiles resume` or `dotfiles rollback`")]
+    JournalPending,
+
+    #[error("there is no interrupted install to resume or roll back")]
+    NoJournal,
+
+    #[error("{}: journal has backup steps but no backup", .0.display())]
+    JournalWithoutBackup(PathBuf),
+
+    #[error("plan was made for repository {} and home {}", repo.display(), home.display())]
+    PlanMismatch { repo: PathBuf, home: PathBuf },
+
+    #[error("plan is out of date; re-plan to pick up changes to {}", display_paths(.0))]
+    StalePlan(Vec<PathBuf>),
+
+    #[error("{step}: {source}")]
+    Step {
+        step: String,
+        #[source]
+        source: Box<Error>,
+    },
+
+    #[error("{source}; rolled back {rolled_back} completed step(s)")]
+    TransactionFailed {
+        rolled_back: usize,
+        #[source]
+        source: Box<Error>,
+    },
+
+    #[error("{source}; rolling back also failed ({rollback}), run `dotfiles rollback` to retry")]
+    RollbackFailed {
+        #[source]
+        source: Box<Error>,
+        rollback: Box<Error>,
+    },
+}
+
+impl Error {
+    pub fn io(path: impl Into<PathBuf>, source: io::Error) -> Self {
+        Error::Io {
+            path: path.into(),
+            source,
+        }
+    }
+
+    pub fn toml(path: impl Into<PathBuf>, source: toml::de::Error) -> Self {
+        Error::Toml {
+            path: path.into(),
+            source,
+        }
+    }
+
+    pub fn git2(path: impl Into<PathBuf>, source: git2::Error) -> Self {
+        Error::Git2 {
+            path: path.into(),
+            source,
+        }
+    }
+
+    pub fn json(path: impl Into<PathBuf>, source: serde_json::Error) -> Self {
+        Error::Json {
+            path: path.into(),
+            source,
+        }
+    }
+}
+
+fn display_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
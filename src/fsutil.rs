@@ -0,0 +1,108 @@
+//! Small filesystem helpers shared by the install, backup and restore paths.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileKind {
+    File,
+    Dir,
+    Symlink,
+    Other,
+}
+
+impl FileKind {
+    pub fn of(meta: &fs::Metadata) -> Self {
+        let ft = meta.file_type();
+        if ft.is_symlink() {
+            FileKind::Symlink
+        } else if ft.is_dir() {
+            FileKind::Dir
+        } else if ft.is_file() {
+            FileKind::File
+        } else {
+            FileKind::Other
+        }
+    }
+}
+
+impl fmt::Display for FileKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FileKind::File => "file",
+            FileKind::Dir => "directory",
+            FileKind::Symlink => "symlink",
+            FileKind::Other => "special file",
+        })
+    }
+}
+
+/// Whether an error from looking up a path means nothing is there, including
+/// the case where one of its parents is a file.
+pub fn is_missing(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::NotFound | io::ErrorKind::NotADirectory
+    )
+}
+
+/// Whether `dir` is an empty directory, or nothing at all, so that
+/// something can be cloned or copied into it.
+pub fn is_empty_dir(dir: &Path) -> Result<bool> {
+    match fs::read_dir(dir) {
+        Ok(mut entries) => Ok(entries.next().is_none()),
+        Err(e) if is_missing(&e) => Ok(true),
+        Err(e) => Err(Error::io(dir, e)),
+    }
+}
+
+/// Finds the first ancestor of `dest` below `home` that exists but is not a
+/// directory (following symlinks, so a linked `~/.vim` is fine).
+pub fn blocking_parent(home: &Path, dest: &Path) -> Result<Option<PathBuf>> {
+    let Ok(rel) = dest.strip_prefix(home) else {
+        return Ok(None);
+    };
+    let mut current = home.to_path_buf();
+    let mut components = rel.components().peekable();
+    while let Some(component) = components.next() {
+        if components.peek().is_none() {
+            break;
+        }
+        current.push(component);
+        match fs::metadata(&current) {
+            Ok(meta) if meta.is_dir() => {}
+            Ok(_) => return Ok(Some(current)),
+            // A dangling symlink is in the way just as much as a file is.
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let dangling = fs::symlink_metadata(&current).is_ok();
+                return Ok(dangling.then_some(current));
+            }
+            Err(e) => return Err(Error::io(&current, e)),
+        }
+    }
+    Ok(None)
+}
+
+pub fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let text = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+    serde_json::from_str(&text).map_err(|e| Error::json(path, e))
+}
+
+/// Writes `value` as pretty JSON, via a temporary file so that readers never
+/// see a half-written document.
+pub fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let text = serde_json::to_string_pretty(value).map_err(|e| Error::json(path, e))?;
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    fs::write(&tmp, text + "\n").map_err(|e| Error::io(&tmp, e))?;
+    fs::rename(&tmp, path).map_err(|e| Error::io(path, e))
+}
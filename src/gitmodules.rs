@@ -0,0 +1,245 @@
+//! Reading, and editing in place, the repository's `.gitmodules`.
+//!
+//! `.gitmodules` uses git's config syntax:
+//!
+//! ```text
+//! [submodule ".vim/bundle/vim-fugitive"]
+//!     path = .vim/bundle/vim-fugitive
+//!     url = git://github.com/tpope/vim-fugitive.git
+//! ```
+//!
+//! The file is kept as its original lines, and each submodule remembers
+//! which line every key came from. Changing a value rewrites only that
+//! line, so indentation, comments and ordering survive untouched.
+
+use std::fs;
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::fsutil::is_missing;
+
+pub const GITMODULES: &str = ".gitmodules";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Submodule {
+    pub name: String,
+    /// One-based line of the `[submodule]` header.
+    pub line: usize,
+    pub fields: Vec<Field>,
+}
+
+/// A `key = value` line within a submodule section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    /// Lowercased, as git compares keys case-insensitively.
+    pub key: String,
+    pub value: String,
+    /// One-based.
+    pub line: usize,
+}
+
+impl Submodule {
+    /// The last value set for `key`, which is the one git uses.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .rev()
+            .find(|f| f.key == key)
+            .map(|f| f.value.as_str())
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.get("path").map(Path::new)
+    }
+
+    pub fn url(&self) -> Option<&str> {
+        self.get("url")
+    }
+
+    /// The value of `key`, or an error pointing at this submodule in
+    /// `repo`'s `.gitmodules` if it has none.
+    pub fn require(&self, repo: &Path, key: &str) -> Result<&str> {
+        self.get(key).ok_or_else(|| Error::GitModules {
+            path: repo.join(GITMODULES),
+            line: self.line,
+            message: format!("submodule {} has no {key}", self.name),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GitModules {
+    lines: Vec<String>,
+    /// Whether the text ended with a newline.
+    trailing_newline: bool,
+    pub modules: Vec<Submodule>,
+}
+
+impl GitModules {
+    /// Reads `.gitmodules` from `repo`. A repository without one has no
+    /// submodules.
+    pub fn load(repo: &Path) -> Result<Self> {
+        let path = repo.join(GITMODULES);
+        match fs::read_to_string(&path) {
+            Ok(text) => GitModules::parse(&text).map_err(|(line, message)| Error::GitModules {
+                path,
+                line,
+                message,
+            }),
+            Err(e) if is_missing(&e) => Ok(GitModules::default()),
+            Err(e) => Err(Error::io(&path, e)),
+        }
+    }
+
+    pub fn save(&self, repo: &Path) -> Result<()> {
+        let path = repo.join(GITMODULES);
+        fs::write(&path, self.to_string()).map_err(|e| Error::io(&path, e))
+    }
+
+    /// Parses the text of a `.gitmodules`, or returns the one-based line of
+    /// the first thing that is not valid and what is wrong with it.
+    pub fn parse(text: &str) -> Result<Self, (usize, String)> {
+        let mut modules: Vec<Submodule> = Vec::new();
+        // Whether the current section is a submodule, as opposed to some
+        // other section, whose keys are kept but ignored. `None` before the
+        // first section.
+        let mut in_submodule = None;
+        let lines: Vec<String> = text.lines().map(str::to_string).collect();
+        for (index, raw) in lines.iter().enumerate() {
+            let line = index + 1;
+            let trimmed = raw.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with(';') {
+                continue;
+            }
+            if let Some(header) = trimmed.strip_prefix('[') {
+                let header = strip_comment(header)
+                    .strip_suffix(']')
+                    .ok_or((line, "section header is missing its `]`".to_string()))?;
+                in_submodule = Some(match header.split_once(char::is_whitespace) {
+                    Some((section, name)) if section.eq_ignore_ascii_case("submodule") => {
+                        let name = unquote(name.trim()).map_err(|m| (line, m))?;
+                        modules.push(Submodule {
+                            name,
+                            line,
+                            fields: Vec::new(),
+                        });
+                        true
+                    }
+                    _ => false,
+                });
+                continue;
+            }
+            let (key, value) = match trimmed.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                // A bare key is a boolean set to true.
+                None => (strip_comment(trimmed), "true"),
+            };
+            if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+                return Err((line, format!("`{key}` is not a valid key")));
+            }
+            let Some(in_submodule) = in_submodule else {
+                return Err((line, "key outside of any section".to_string()));
+            };
+            if in_submodule {
+                let value = unquote(strip_comment(value)).map_err(|m| (line, m))?;
+                let module = modules.last_mut().expect("in a submodule section");
+                module.fields.push(Field {
+                    key: key.to_ascii_lowercase(),
+                    value,
+                    line,
+                });
+            }
+        }
+        Ok(GitModules {
+            trailing_newline: text.ends_with('\n') || text.is_empty(),
+            lines,
+            modules,
+        })
+    }
+
+    /// The submodule whose path is `path`.
+    pub fn by_path(&self, path: &Path) -> Option<&Submodule> {
+        self.modules.iter().find(|m| m.path() == Some(path))
+    }
+
+    /// Replaces the value of the field on `line`, keeping the line's
+    /// indentation, the spacing around its `=` and any comment after it.
+    pub fn set_value(&mut self, line: usize, value: &str) {
+        let raw = &self.lines[line - 1];
+        let start = raw.find('=').map_or(raw.len(), |eq| {
+            eq + 1 + raw[eq + 1..].len() - raw[eq + 1..].trim_start().len()
+        });
+        let end = start + strip_comment(&raw[start..]).len();
+        let rewritten = format!("{}{}{}", &raw[..start], quote(value), &raw[end..]);
+        self.lines[line - 1] = rewritten;
+        for field in self.modules.iter_mut().flat_map(|m| &mut m.fields) {
+            if field.line == line {
+                field.value = value.to_string();
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for GitModules {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, line) in self.lines.iter().enumerate() {
+            f.write_str(line)?;
+            if i + 1 < self.lines.len() || self.trailing_newline {
+                f.write_str("\n")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Everything before a `#` or `;` that is not inside double quotes.
+fn strip_comment(s: &str) -> &str {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => quoted = !quoted,
+            '#' | ';' if !quoted => return s[..i].trim_end(),
+            _ => {}
+        }
+    }
+    s
+}
+
+/// Removes double quotes and backslash escapes as git does.
+fn unquote(s: &str) -> Result<String, String> {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {}
+            '\\' => match chars.next() {
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some(c @ ('"' | '\\')) => out.push(c),
+                Some(c) => return Err(format!("unknown escape `\\{c}`")),
+                None => return Err("line ends in a `\\`".to_string()),
+            },
+            c => out.push(c),
+        }
+    }
+    Ok(out)
+}
+
+/// Quotes a value only if git would otherwise read it differently.
+fn quote(value: &str) -> String {
+    let plain = !value.is_empty()
+        && value.trim() == value
+        && !value.contains(['"', '\\', '#', ';', '\n', '\t']);
+    if plain {
+        return value.to_string();
+    }
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t");
+    format!("\"{escaped}\"")
+}
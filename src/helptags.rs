@@ -0,0 +1,408 @@
+//! Vim's `:helptags`, without Vim.
+//!
+//! [`generate`] follows `helptags_one()` in Vim's `help.c` byte for byte,
+//! quirks included: lines are read into the same 1024-byte buffer, a tag
+//! cut short by its closing `*` hides a trailing ` >` from the check for
+//! the start of an example, and a help file is UTF-8 if its first line has
+//! valid non-ASCII text in it. English help, `*.txt`, goes in `tags`;
+//! translations such as `*.frx` go in `tags-fr`.
+//!
+//! [`helptags`] does what `pathogen#helptags()` does for every doc
+//! directory in the repository's runtimepath, and also points out tags
+//! that more than one of them defines, since `:help` only ever finds the
+//! first.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+use crate::fsutil::is_missing;
+use crate::layout::Layout;
+use crate::pathogen;
+
+/// Vim's `IOSIZE`, the buffer `vim_fgets` reads each line into.
+const IOSIZE: usize = 1024 + 1;
+
+/// How deep `**` goes in Vim's `glob()`.
+const MAX_DEPTH: usize = 100;
+
+/// A tags file as `:helptags` writes it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagsFile {
+    /// `tags`, or `tags-xx` for language `xx`.
+    pub name: String,
+    /// `en`, or the language of a translation.
+    pub lang: String,
+    /// In the order they are written.
+    pub tags: Vec<Tag>,
+    pub contents: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tag {
+    pub name: String,
+    /// The help file, relative to the doc directory.
+    pub file: PathBuf,
+}
+
+/// The tags files `:helptags doc` would write, one per language found.
+///
+/// Where Vim would complain that a language mixes UTF-8 help files with
+/// others, and leave its tags file empty, this fails instead.
+pub fn generate(doc: &Path) -> Result<Vec<TagsFile>> {
+    let mut files = Vec::new();
+    collect_files(doc, Path::new(""), 0, &mut files)?;
+
+    // Languages in the order their first file was found.
+    let mut langs: Vec<String> = Vec::new();
+    for file in &files {
+        let bytes = file.as_os_str().as_encoded_bytes();
+        let lang = match bytes {
+            [.., b'.', a, b, x] if a.is_ascii_alphabetic() && b.is_ascii_alphabetic() => {
+                let (a, b, x) = (
+                    a.to_ascii_lowercase(),
+                    b.to_ascii_lowercase(),
+                    x.to_ascii_lowercase(),
+                );
+                match (a, b, x) {
+                    (b't', b'x', b't') => "en".to_string(),
+                    (_, _, b'x') => String::from_utf8(vec![a, b]).expect("ASCII"),
+                    _ => continue,
+                }
+            }
+            _ => continue,
+        };
+        if !langs.contains(&lang) {
+            langs.push(lang);
+        }
+    }
+
+    let mut tags_files = Vec::new();
+    for lang in langs {
+        let (name, ext) = match lang.as_str() {
+            "en" => ("tags".to_string(), ".txt".to_string()),
+            _ => (format!("tags-{lang}"), format!(".{lang}x")),
+        };
+        // `dir/**/*.txt` is case-sensitive even though finding the
+        // languages was not, so `FOO.TXT` can leave nothing to do.
+        let matching: Vec<&PathBuf> = files
+            .iter()
+            .filter(|f| f.as_os_str().as_encoded_bytes().ends_with(ext.as_bytes()))
+            .collect();
+        if matching.is_empty() {
+            continue;
+        }
+        tags_files.push(tags_file(doc, name, lang, &matching)?);
+    }
+    Ok(tags_files)
+}
+
+/// Writes the tags files `:helptags doc` would, returning them.
+pub fn write(doc: &Path) -> Result<Vec<TagsFile>> {
+    let tags_files = generate(doc)?;
+    for tags_file in &tags_files {
+        let path = doc.join(&tags_file.name);
+        fs::write(&path, &tags_file.contents).map_err(|e| Error::io(&path, e))?;
+    }
+    Ok(tags_files)
+}
+
+fn tags_file(doc: &Path, name: String, lang: String, files: &[&PathBuf]) -> Result<TagsFile> {
+    let mut entries: Vec<Vec<u8>> = Vec::new();
+    let mut utf8: Option<bool> = None;
+    for &file in files {
+        let path = doc.join(file);
+        let bytes = fs::read(&path).map_err(|e| Error::io(&path, e))?;
+        let fname = file.as_os_str().as_encoded_bytes();
+        let mut in_example = false;
+        for (i, line) in read_lines(&bytes).into_iter().enumerate() {
+            let mut line = line;
+            if let Some(nul) = line.iter().position(|&b| b == 0) {
+                line.truncate(nul);
+            }
+            if i == 0 {
+                let this = is_utf8(&line);
+                match utf8 {
+                    None => utf8 = Some(this),
+                    Some(utf8) if utf8 != this => {
+                        return Err(Error::HelpEncoding(path));
+                    }
+                    Some(_) => {}
+                }
+            }
+            if in_example {
+                if matches!(line.first(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+                    continue;
+                }
+                in_example = false;
+            }
+            find_tags(&mut line, fname, &mut entries);
+            let len = c_len(&line);
+            if line[..len] == *b">\n" || (len >= 3 && line[len - 3..len] == *b" >\n") {
+                in_example = true;
+            }
+        }
+    }
+    entries.sort();
+
+    let mut contents = Vec::new();
+    if utf8 == Some(true) {
+        contents.extend_from_slice(b"!_TAG_FILE_ENCODING\tutf-8\t//\n");
+    }
+    let mut tags = Vec::new();
+    for entry in &entries {
+        let tab = entry.iter().position(|&b| b == b'\t').expect("tab");
+        contents.extend_from_slice(entry);
+        contents.extend_from_slice(b"\t/*");
+        for &b in &entry[..tab] {
+            if b == b'\\' || b == b'/' {
+                contents.push(b'\\');
+            }
+            contents.push(b);
+        }
+        contents.extend_from_slice(b"*\n");
+        tags.push(Tag {
+            name: String::from_utf8_lossy(&entry[..tab]).into_owned(),
+            file: PathBuf::from(String::from_utf8_lossy(&entry[tab + 1..]).into_owned()),
+        });
+    }
+    Ok(TagsFile {
+        name,
+        lang,
+        tags,
+        contents,
+    })
+}
+
+/// The `*tag*`s in `line`, as `tag\tfname`. Like Vim, this ends each tag
+/// by putting a NUL over its closing `*`.
+fn find_tags(line: &mut [u8], fname: &[u8], entries: &mut Vec<Vec<u8>>) {
+    let mut p1 = c_find(line, 0, b'*');
+    while let Some(start) = p1 {
+        let mut p2 = c_find(line, start + 1, b'*');
+        if let Some(end) = p2.filter(|&end| end > start + 1) {
+            let valid = !line[start + 1..end]
+                .iter()
+                .any(|&b| b == b' ' || b == b'\t' || b == b'|');
+            let before = start == 0 || line[start - 1] == b' ' || line[start - 1] == b'\t';
+            let after = matches!(
+                line.get(end + 1),
+                None | Some(b' ' | b'\t' | b'\n' | b'\r' | 0)
+            );
+            if valid && before && after {
+                line[end] = 0;
+                let mut entry = line[start + 1..end].to_vec();
+                entry.push(b'\t');
+                entry.extend_from_slice(fname);
+                entries.push(entry);
+                p2 = c_find(line, end + 1, b'*');
+            }
+        }
+        p1 = p2;
+    }
+}
+
+/// Where `byte` is at or after `from`, stopping at a NUL as C would.
+fn c_find(line: &[u8], from: usize, byte: u8) -> Option<usize> {
+    line.get(from..)?
+        .iter()
+        .take_while(|&&b| b != 0)
+        .position(|&b| b == byte)
+        .map(|i| from + i)
+}
+
+/// `STRLEN`.
+fn c_len(line: &[u8]) -> usize {
+    line.iter().position(|&b| b == 0).unwrap_or(line.len())
+}
+
+/// The lines of a file as `vim_fgets(IObuff, IOSIZE, fd)` returns them:
+/// with their newline, and cut short to fit the buffer, dropping the rest.
+fn read_lines(bytes: &[u8]) -> Vec<Vec<u8>> {
+    // `fgets(buf, size)`: up to `size - 1` bytes, stopping after a newline.
+    let fgets = |pos: usize, size: usize| -> &[u8] {
+        let rest = &bytes[pos..];
+        let max = rest.len().min(size - 1);
+        match rest[..max].iter().position(|&b| b == b'\n') {
+            Some(newline) => &rest[..=newline],
+            None => &rest[..max],
+        }
+    };
+    // Whether `fgets` filled the buffer without reaching a newline, by
+    // the byte `vim_fgets` looks at.
+    let full = |chunk: &[u8], size: usize| {
+        chunk.len() >= size - 1 && !matches!(chunk[size - 2], 0 | b'\n')
+    };
+
+    let mut lines = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let line = fgets(pos, IOSIZE);
+        pos += line.len();
+        if full(line, IOSIZE) {
+            loop {
+                let rest = fgets(pos, 200);
+                pos += rest.len();
+                if rest.is_empty() || !full(rest, 200) {
+                    break;
+                }
+            }
+        }
+        lines.push(line.to_vec());
+    }
+    lines
+}
+
+/// Whether a first line makes a help file UTF-8: it has non-ASCII bytes,
+/// and they all start sequences Vim's `utf_ptr2len` accepts.
+fn is_utf8(line: &[u8]) -> bool {
+    let mut found = false;
+    let mut i = 0;
+    while i < line.len() {
+        let lead = line[i];
+        if lead >= 0x80 {
+            found = true;
+            let len = match lead {
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf7 => 4,
+                0xf8..=0xfb => 5,
+                0xfc..=0xfd => 6,
+                _ => return false,
+            };
+            let continued = (1..len).all(|k| line.get(i + k).is_some_and(|&b| b & 0xc0 == 0x80));
+            if !continued {
+                return false;
+            }
+            i += len;
+        } else {
+            i += 1;
+        }
+    }
+    found
+}
+
+/// Every file below `doc`, as `doc/**` finds them: skipping names that
+/// start with a dot, and following symbolic links.
+fn collect_files(doc: &Path, rel: &Path, depth: usize, out: &mut Vec<PathBuf>) -> Result<()> {
+    let dir = doc.join(rel);
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if is_missing(&e) => return Ok(()),
+        Err(e) => return Err(Error::io(&dir, e)),
+    };
+    let mut names = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| Error::io(&dir, e))?;
+        names.push(entry.file_name());
+    }
+    names.sort();
+    for name in names {
+        if name.as_encoded_bytes().starts_with(b".") {
+            continue;
+        }
+        let child = rel.join(&name);
+        match fs::metadata(doc.join(&child)) {
+            Ok(meta) if meta.is_dir() => {
+                if depth < MAX_DEPTH {
+                    collect_files(doc, &child, depth + 1, out)?;
+                }
+            }
+            Ok(_) => out.push(child),
+            // A dangling link.
+            Err(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// A tag defined in more than one doc directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Duplicate {
+    pub tag: String,
+    pub lang: String,
+    /// The help files defining it, relative to the repository root, in
+    /// runtimepath order. `:help` goes to the first.
+    pub files: Vec<PathBuf>,
+}
+
+impl fmt::Display for Duplicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let files: Vec<_> = self.files.iter().map(|f| f.display().to_string()).collect();
+        write!(f, "*{}* is in {}", self.tag, files.join(", "))?;
+        if self.lang != "en" {
+            write!(f, " ({})", self.lang)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    /// Tags files written, relative to the repository root.
+    pub written: Vec<PathBuf>,
+    pub duplicates: Vec<Duplicate>,
+    /// Doc directories that could not be done, and why.
+    pub failed: Vec<(PathBuf, Error)>,
+}
+
+/// Runs `:helptags` on every doc directory in the repository's part of
+/// the runtimepath, skipping those `pathogen#helptags()` would: doc
+/// directories that are empty or read-only, and those whose tags file is
+/// read-only.
+pub fn helptags(layout: &Layout) -> Result<Report> {
+    let repo = layout.repo();
+    let mut report = Report::default();
+    let mut defined: BTreeMap<(String, String), Vec<PathBuf>> = BTreeMap::new();
+    for dir in pathogen::resolve(layout)?.dirs() {
+        let Ok(rel) = Path::new(&dir).strip_prefix(repo) else {
+            continue;
+        };
+        let doc = rel.join("doc");
+        if !is_writable_doc(&repo.join(&doc))? {
+            continue;
+        }
+        match write(&repo.join(&doc)) {
+            Ok(tags_files) => {
+                for tags_file in tags_files {
+                    report.written.push(doc.join(&tags_file.name));
+                    for tag in tags_file.tags {
+                        let key = (tags_file.lang.clone(), tag.name);
+                        defined.entry(key).or_default().push(doc.join(tag.file));
+                    }
+                }
+            }
+            Err(e) => report.failed.push((doc, e)),
+        }
+    }
+    for ((lang, tag), files) in defined {
+        if files.len() > 1 {
+            report.duplicates.push(Duplicate { tag, lang, files });
+        }
+    }
+    Ok(report)
+}
+
+fn is_writable_doc(doc: &Path) -> Result<bool> {
+    let meta = match fs::metadata(doc) {
+        Ok(meta) if meta.is_dir() => meta,
+        Ok(_) => return Ok(false),
+        Err(e) if is_missing(&e) => return Ok(false),
+        Err(e) => return Err(Error::io(doc, e)),
+    };
+    if meta.permissions().readonly() {
+        return Ok(false);
+    }
+    let mut entries = fs::read_dir(doc).map_err(|e| Error::io(doc, e))?;
+    let visible =
+        entries.any(|e| e.is_ok_and(|e| !e.file_name().as_encoded_bytes().starts_with(b".")));
+    if !visible {
+        return Ok(false);
+    }
+    match fs::metadata(doc.join("tags")) {
+        Ok(tags) => Ok(!tags.permissions().readonly()),
+        Err(_) => Ok(true),
+    }
+}
@@ -0,0 +1,33 @@
+//! The machine being installed onto, as far as manifest entries care.
+
+/// Operating system names an entry may require, as reported by Rust's
+/// `std::env::consts::OS`.
+pub const KNOWN_OS: &[&str] = &[
+    "linux",
+    "macos",
+    "freebsd",
+    "openbsd",
+    "netbsd",
+    "dragonfly",
+    "solaris",
+    "illumos",
+    "android",
+    "windows",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Host {
+    /// One of [`KNOWN_OS`].
+    pub os: String,
+    /// The hostname, as `hostname` prints it.
+    pub name: String,
+}
+
+impl Host {
+    pub fn current() -> Self {
+        Host {
+            os: std::env::consts::OS.to_string(),
+            name: gethostname::gethostname().to_string_lossy().into_owned(),
+        }
+    }
+}
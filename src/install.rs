@@ -0,0 +1,98 @@
+//! Installing discovered dotfiles into the home directory.
+
+use crate::discover::Entry;
+use crate::error::{Error, Result};
+use crate::layout::Layout;
+use crate::plan::{plan_with, Action, Plan};
+use crate::template::Values;
+use crate::transaction::Transaction;
+
+pub use crate::plan::Refusal;
+
+/// What happened to a single entry during an install.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// The file was installed where nothing was before.
+    Linked,
+    /// The existing file was moved into the backup and replaced.
+    Replaced,
+    /// Something installed earlier was swapped for an up-to-date one.
+    Updated,
+    /// The destination already is what we would have installed.
+    AlreadyLinked,
+    /// Something is in the way; the destination was left untouched.
+    Refused(Refusal),
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub results: Vec<(Entry, Outcome)>,
+    /// The backup holding anything that was replaced, if there was any.
+    pub backup: Option<String>,
+}
+
+impl Report {
+    pub fn refused(&self) -> impl Iterator<Item = (&Entry, &Refusal)> {
+        self.results.iter().filter_map(|(e, o)| match o {
+            Outcome::Refused(r) => Some((e, r)),
+            _ => None,
+        })
+    }
+}
+
+/// Installs every discovered dotfile into the home directory.
+///
+/// Files and foreign symlinks in the way are moved into a fresh backup
+/// first. Directories in the way are refused, and the rest of the install
+/// carries on regardless, as do copies that have been edited since they
+/// were installed. Everything else happens in a single [`Transaction`]: if
+/// any step fails, none of them are left behind.
+pub fn install(layout: &Layout) -> Result<Report> {
+    install_with(layout, Values::load(layout)?)
+}
+
+/// [`install`], rendering templates with `values`.
+pub fn install_with(layout: &Layout, values: Values) -> Result<Report> {
+    if Transaction::is_pending(layout) {
+        return Err(Error::JournalPending);
+    }
+    let plan = plan_with(layout, &values)?;
+    execute(layout, plan, values)
+}
+
+/// Carries out a plan made earlier, typically by `install --dry-run`.
+///
+/// The plan is checked against the home directory as it is now, and
+/// rejected if anything it covers has changed since it was made.
+pub fn apply(layout: &Layout, plan: Plan) -> Result<Report> {
+    apply_with(layout, plan, Values::load(layout)?)
+}
+
+/// [`apply`], rendering templates with `values`.
+pub fn apply_with(layout: &Layout, plan: Plan, values: Values) -> Result<Report> {
+    if Transaction::is_pending(layout) {
+        return Err(Error::JournalPending);
+    }
+    plan.validate(layout, &values)?;
+    execute(layout, plan, values)
+}
+
+fn execute(layout: &Layout, plan: Plan, values: Values) -> Result<Report> {
+    let backup = Transaction::begin(layout, plan.operations(layout), values)?.commit()?;
+
+    let results = plan
+        .items
+        .into_iter()
+        .map(|item| {
+            let outcome = match item.action {
+                Action::Create => Outcome::Linked,
+                Action::Replace { .. } => Outcome::Replaced,
+                Action::Update => Outcome::Updated,
+                Action::Skip => Outcome::AlreadyLinked,
+                Action::Refuse(why) => Outcome::Refused(why),
+            };
+            (item.entry, outcome)
+        })
+        .collect();
+    Ok(Report { results, backup })
+}
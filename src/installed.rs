@@ -0,0 +1,85 @@
+//! The record of what has been installed into a home directory.
+//!
+//! Every file a transaction installs, and every directory it had to make for
+//! one, is added to `installed.json` in the state directory when the
+//! transaction commits. Uninstalling works from this record rather than from
+//! the repository, so files that have since been removed from the repository
+//! are still cleaned up.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::fsutil::{is_missing, read_json, write_json};
+use crate::layout::Layout;
+use crate::strategy::Strategy;
+
+const INSTALLED: &str = "installed.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstalledFile {
+    /// The absolute path of the file in the repository.
+    pub source: PathBuf,
+    #[serde(default)]
+    pub strategy: Strategy,
+    /// The content hash of a copied or hardlinked file as installed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// The backup holding whatever the installed file replaced.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backup: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Installed {
+    /// Installed files keyed by their path relative to home.
+    pub files: BTreeMap<PathBuf, InstalledFile>,
+    /// Directories created to hold them, relative to home.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub dirs: BTreeSet<PathBuf>,
+}
+
+impl Installed {
+    /// Reads the record, which is empty if nothing has been installed yet.
+    pub fn load(layout: &Layout) -> Result<Self> {
+        let path = record_path(layout);
+        match fs::symlink_metadata(&path) {
+            Ok(_) => read_json(&path),
+            Err(e) if is_missing(&e) => Ok(Installed::default()),
+            Err(e) => Err(Error::io(&path, e)),
+        }
+    }
+
+    /// Writes the record back, removing it altogether once it is empty.
+    pub fn save(&self, layout: &Layout) -> Result<()> {
+        let path = record_path(layout);
+        if self.files.is_empty() && self.dirs.is_empty() {
+            return match fs::remove_file(&path) {
+                Err(e) if !is_missing(&e) => Err(Error::io(&path, e)),
+                _ => Ok(()),
+            };
+        }
+        let dir = layout.state_dir();
+        fs::create_dir_all(&dir).map_err(|e| Error::io(&dir, e))?;
+        write_json(&path, self)
+    }
+
+    /// Records a newly installed file. A backup from an earlier install of
+    /// the same path is kept unless this one replaced something too.
+    pub fn add(&mut self, path: &Path, mut file: InstalledFile) {
+        let previous = self.files.remove(path).and_then(|f| f.backup);
+        file.backup = file.backup.or(previous);
+        self.files.insert(path.to_path_buf(), file);
+    }
+
+    pub fn add_dir(&mut self, path: &Path) {
+        self.dirs.insert(path.to_path_buf());
+    }
+}
+
+fn record_path(layout: &Layout) -> PathBuf {
+    layout.state_dir().join(INSTALLED)
+}
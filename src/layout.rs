@@ -0,0 +1,82 @@
+//! Where things live: the repository being installed, the home directory it
+//! is installed into and the host that home directory is on, along with any
+//! profile picked for it by hand.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+use crate::host::Host;
+
+#[derive(Debug, Clone)]
+pub struct Layout {
+    repo: PathBuf,
+    home: PathBuf,
+    host: Host,
+    profile: Option<String>,
+}
+
+impl Layout {
+    /// Both directories must already exist. The repository path is
+    /// canonicalized because it ends up as the target of every symlink.
+    pub fn new(repo: impl AsRef<Path>, home: impl AsRef<Path>) -> Result<Self> {
+        Ok(Layout {
+            repo: canonical_dir(repo.as_ref())?,
+            home: canonical_dir(home.as_ref())?,
+            host: Host::current(),
+            profile: None,
+        })
+    }
+
+    /// Installs as if onto `host` instead of the machine we are running on.
+    pub fn with_host(mut self, host: Host) -> Self {
+        self.host = host;
+        self
+    }
+
+    pub fn repo(&self) -> &Path {
+        &self.repo
+    }
+
+    pub fn home(&self) -> &Path {
+        &self.home
+    }
+
+    /// Installs with the named manifest profile rather than the one that
+    /// matches the hostname.
+    pub fn with_profile(mut self, name: impl Into<String>) -> Self {
+        self.profile = Some(name.into());
+        self
+    }
+
+    pub fn host(&self) -> &Host {
+        &self.host
+    }
+
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    /// The file inside the repository for a repository-relative path.
+    pub fn source(&self, rel: &Path) -> PathBuf {
+        self.repo.join(rel)
+    }
+
+    /// The file inside the home directory for a home-relative path.
+    pub fn dest(&self, rel: &Path) -> PathBuf {
+        self.home.join(rel)
+    }
+
+    /// Where the tool keeps its own bookkeeping for this home directory.
+    pub fn state_dir(&self) -> PathBuf {
+        self.home.join(".local/state/dotfiles")
+    }
+}
+
+fn canonical_dir(path: &Path) -> Result<PathBuf> {
+    let canonical = fs::canonicalize(path).map_err(|e| Error::io(path, e))?;
+    if !canonical.is_dir() {
+        return Err(Error::NotADirectory(canonical));
+    }
+    Ok(canonical)
+}
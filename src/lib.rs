@@ -0,0 +1,34 @@
+//! Installs the dotfiles tracked in this repository into a home directory.
+
+pub mod adopt;
+pub mod backup;
+pub mod bundles;
+pub mod conflicts;
+pub mod discover;
+pub mod error;
+pub mod fsutil;
+pub mod gitmodules;
+pub mod helptags;
+pub mod host;
+pub mod install;
+pub mod installed;
+pub mod layout;
+pub mod lint;
+pub mod loadorder;
+pub mod lockfile;
+pub mod manifest;
+pub mod pathogen;
+pub mod plan;
+pub mod plugins;
+pub mod secrets;
+pub mod status;
+pub mod strategy;
+pub mod template;
+pub mod transaction;
+pub mod uninstall;
+pub mod update;
+pub mod vendor;
+pub mod vimrc;
+
+pub use error::{Error, Result};
+pub use layout::Layout;
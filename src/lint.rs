@@ -0,0 +1,1176 @@
+//! Checking the repository's own Vim scripts for mistakes Vim lets pass.
+//!
+//! `.vimrc` and the scripts under `.vim` are linted; bundles, and pathogen
+//! itself, are other people's code and are left alone. The scripts are
+//! read a command at a time: continuation lines are joined, and commands
+//! are split on `|` except where the command takes the rest of the line,
+//! as `:autocmd` and `:normal` do. Positions are 1-based lines and byte
+//! columns, as Vim gives them.
+//!
+//! The rules:
+//!
+//! - `global-set`: `:set` in an ftplugin, syntax or indent script. These
+//!   run for one buffer, but `:set` also changes the value every new
+//!   buffer starts with; `:setlocal` does not.
+//! - `autocmd-outside-augroup`: an `:autocmd` outside an `:augroup` and
+//!   not naming one. Sourcing the file again adds it a second time, since
+//!   nothing clears it first.
+//! - `missing-abort`: a `:function` without `abort`, which carries on
+//!   after an error instead of stopping.
+//! - `unknown-option`: `:let &name` for an option Vim does not have.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::bundles::BUNDLE_DIR;
+use crate::conflicts::collect_scripts;
+use crate::error::{Error, Result};
+use crate::layout::Layout;
+use crate::vimrc::VIMRC;
+
+/// Pathogen, which the repository vendors rather than writes.
+const PATHOGEN: &str = ".vim/autoload/pathogen.vim";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    GlobalSet,
+    AutocmdOutsideAugroup,
+    MissingAbort,
+    UnknownOption,
+}
+
+impl Rule {
+    pub fn code(self) -> &'static str {
+        match self {
+            Rule::GlobalSet => "global-set",
+            Rule::AutocmdOutsideAugroup => "autocmd-outside-augroup",
+            Rule::MissingAbort => "missing-abort",
+            Rule::UnknownOption => "unknown-option",
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Relative to the repository root.
+    pub path: PathBuf,
+    pub line: usize,
+    pub col: usize,
+    pub rule: Rule,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}: {}",
+            self.path.display(),
+            self.line,
+            self.col,
+            self.rule,
+            self.message
+        )
+    }
+}
+
+/// Lints `.vimrc` and every `.vim` script under `.vim` but those in
+/// bundles and pathogen's.
+pub fn lint(layout: &Layout) -> Result<Vec<Diagnostic>> {
+    let repo = layout.repo();
+    let mut scripts = Vec::new();
+    collect_scripts(repo, Path::new(".vim"), &mut scripts)?;
+    scripts.retain(|path| !path.starts_with(BUNDLE_DIR) && path != Path::new(PATHOGEN));
+    scripts.sort();
+    if repo.join(VIMRC).is_file() {
+        scripts.insert(0, PathBuf::from(VIMRC));
+    }
+    let mut diagnostics = Vec::new();
+    for path in scripts {
+        let full = repo.join(&path);
+        let bytes = fs::read(&full).map_err(|e| Error::io(&full, e))?;
+        diagnostics.extend(lint_script(&path, &String::from_utf8_lossy(&bytes)));
+    }
+    Ok(diagnostics)
+}
+
+/// Lints one script. Where it is, `path`, decides whether it is an
+/// ftplugin, syntax or indent script.
+pub fn lint_script(path: &Path, text: &str) -> Vec<Diagnostic> {
+    let buffer_kind = path
+        .parent()
+        .into_iter()
+        .flat_map(Path::components)
+        .filter_map(|c| c.as_os_str().to_str())
+        .rfind(|c| BUFFER_KINDS.contains(c));
+    let lines = logical_lines(text);
+    let commands: Vec<Command> = lines.iter().flat_map(commands).collect();
+    // `:autocmd` takes its first argument as a group if there is one by
+    // that name; these are the ones the script could have made.
+    let groups: HashSet<&str> = commands
+        .iter()
+        .filter(|c| is(c.name, "aug", "augroup") && !c.bang)
+        .map(|c| c.args.trim())
+        .filter(|name| !name.eq_ignore_ascii_case("end"))
+        .collect();
+
+    let mut diagnostics = Vec::new();
+    let mut report = |command: &Command, offset: usize, rule: Rule, message: String| {
+        let (line, col) = command.line.position(offset);
+        diagnostics.push(Diagnostic {
+            path: path.to_path_buf(),
+            line,
+            col,
+            rule,
+            message,
+        });
+    };
+    let mut in_augroup = false;
+    for command in &commands {
+        let args = command.args.trim();
+        if is(command.name, "aug", "augroup") && !command.bang {
+            in_augroup = !args.eq_ignore_ascii_case("end");
+        } else if is(command.name, "se", "set") {
+            if let Some(kind) = buffer_kind.filter(|_| !args.is_empty() && !args.starts_with('"')) {
+                report(
+                    command,
+                    command.start,
+                    Rule::GlobalSet,
+                    format!("`set` in a {kind} script changes every buffer; use `setlocal`"),
+                );
+            }
+        } else if is(command.name, "au", "autocmd") {
+            let fields: Vec<&str> = args.split_whitespace().collect();
+            let grouped = in_augroup || fields.first().is_some_and(|f| groups.contains(f));
+            if !grouped && fields.len() >= 3 {
+                report(
+                    command,
+                    command.start,
+                    Rule::AutocmdOutsideAugroup,
+                    "autocmd outside an augroup is added again every time the script is sourced"
+                        .to_string(),
+                );
+            }
+        } else if is(command.name, "fu", "function") {
+            if let Some((name, rest)) = args.split_once('(') {
+                let attributes = rest.split_once(')').map_or("", |(_, after)| after);
+                let abort = attributes
+                    .split_whitespace()
+                    .take_while(|word| !word.starts_with('"'))
+                    .any(|word| word == "abort");
+                if !abort {
+                    report(
+                        command,
+                        command.start,
+                        Rule::MissingAbort,
+                        format!("function {}() has no `abort`", name.trim()),
+                    );
+                }
+            }
+        } else if is(command.name, "let", "let") {
+            if let Some(rest) = args.strip_prefix('&') {
+                let rest = rest
+                    .strip_prefix("l:")
+                    .or_else(|| rest.strip_prefix("g:"))
+                    .unwrap_or(rest);
+                let end = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(rest.len());
+                let option = &rest[..end];
+                if !option.starts_with("t_") && OPTIONS.binary_search(&option).is_err() {
+                    let offset =
+                        command.args_start + (command.args.len() - command.args.trim_start().len());
+                    report(
+                        command,
+                        offset,
+                        Rule::UnknownOption,
+                        format!("there is no option called `{option}`"),
+                    );
+                }
+            }
+        }
+    }
+    diagnostics
+}
+
+/// The runtime directories whose scripts run for a single buffer.
+const BUFFER_KINDS: [&str; 3] = ["ftplugin", "syntax", "indent"];
+
+/// One line as Vim sees it, with its continuation lines joined on.
+struct Line {
+    text: String,
+    /// The 1-based line and column each byte of `text` came from.
+    positions: Vec<(usize, usize)>,
+}
+
+impl Line {
+    fn position(&self, offset: usize) -> (usize, usize) {
+        self.positions
+            .get(offset)
+            .or(self.positions.last())
+            .copied()
+            .unwrap_or((1, 1))
+    }
+}
+
+/// The lines of `text`, with each line starting with `\` joined onto the
+/// one before, and `"\ ` comments between them dropped.
+fn logical_lines(text: &str) -> Vec<Line> {
+    let mut lines: Vec<Line> = Vec::new();
+    for (i, raw) in text.lines().enumerate() {
+        let indent = raw.len() - raw.trim_start().len();
+        let trimmed = &raw[indent..];
+        if let Some(line) = lines.last_mut() {
+            if trimmed.starts_with("\"\\ ") {
+                continue;
+            }
+            if let Some(continued) = trimmed.strip_prefix('\\') {
+                line.text.push_str(continued);
+                line.positions
+                    .extend((0..continued.len()).map(|j| (i + 1, indent + 2 + j)));
+                continue;
+            }
+        }
+        lines.push(Line {
+            text: raw.to_string(),
+            positions: (0..raw.len()).map(|j| (i + 1, j + 1)).collect(),
+        });
+    }
+    lines
+}
+
+/// An Ex command, as written.
+struct Command<'a> {
+    line: &'a Line,
+    name: &'a str,
+    bang: bool,
+    args: &'a str,
+    /// Byte offsets into `line.text` of the name and the arguments.
+    start: usize,
+    args_start: usize,
+}
+
+/// The commands on a line, in order.
+fn commands(line: &Line) -> Vec<Command<'_>> {
+    let text = line.text.as_str();
+    let bytes = text.as_bytes();
+    let mut commands = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        while i < bytes.len() && (bytes[i].is_ascii_whitespace() || bytes[i] == b':') {
+            i += 1;
+        }
+        if i >= bytes.len() || bytes[i] == b'"' {
+            break;
+        }
+        // A range, such as `%` or `'<,'>`.
+        while i < bytes.len() {
+            match bytes[i] {
+                b'%' | b'.' | b'$' | b',' | b';' | b'+' | b'-' | b'0'..=b'9' => i += 1,
+                b'\'' if i + 1 < bytes.len() => i += 2,
+                _ => break,
+            }
+        }
+        let start = i;
+        if i < bytes.len() && !bytes[i].is_ascii_alphabetic() {
+            i += 1;
+        }
+        while i < bytes.len() && bytes[i].is_ascii_alphabetic() {
+            i += 1;
+        }
+        let name = &text[start..i];
+        let bang = name != "!" && bytes.get(i) == Some(&b'!');
+        if bang {
+            i += 1;
+        }
+        let end = if takes_bar(name) {
+            bytes.len()
+        } else {
+            find_bar(bytes, i, is_set_family(name))
+        };
+        commands.push(Command {
+            line,
+            name,
+            bang,
+            args: &text[i..end],
+            start,
+            args_start: i,
+        });
+        i = end + 1;
+    }
+    commands
+}
+
+/// Where the `|` ending a command's arguments is, or the end of the line.
+/// Quoted strings are skipped, except for `:set`, whose arguments have
+/// none and where `"` starts a comment.
+fn find_bar(bytes: &[u8], from: usize, set: bool) -> usize {
+    let mut i = from;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'|' => return i,
+            // Escaped with a backslash or CTRL-V.
+            b'\\' | 0x16 => i += 1,
+            b'"' if set && (i == from || bytes[i - 1].is_ascii_whitespace()) => {
+                return bytes.len();
+            }
+            b'"' | b'\'' if set => {}
+            quote @ (b'"' | b'\'') => match closing_quote(bytes, i + 1, quote) {
+                Some(close) => i = close,
+                // A `"` without an end starts a comment.
+                None => return bytes.len(),
+            },
+            _ => {}
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+fn closing_quote(bytes: &[u8], from: usize, quote: u8) -> Option<usize> {
+    let mut i = from;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if quote == b'"' => i += 1,
+            b'\'' if quote == b'\'' && bytes.get(i + 1) == Some(&b'\'') => i += 1,
+            b if b == quote => return Some(i),
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Whether `name` is `full` or an abbreviation of it at least as long as
+/// `short`, as in `fu[nction]`.
+fn is(name: &str, short: &str, full: &str) -> bool {
+    name.len() >= short.len() && full.starts_with(name)
+}
+
+fn is_set_family(name: &str) -> bool {
+    is(name, "se", "set") || is(name, "setl", "setlocal") || is(name, "setg", "setglobal")
+}
+
+/// Commands that see `|` as part of their arguments (`:help :bar`).
+fn takes_bar(name: &str) -> bool {
+    name == "!"
+        || is(name, "au", "autocmd")
+        || is(name, "com", "command")
+        || is(name, "g", "global")
+        || is(name, "v", "vglobal")
+        || is(name, "norm", "normal")
+        || is(name, "ter", "terminal")
+        || [
+            "argdo", "bufdo", "cdo", "cfdo", "ldo", "lfdo", "tabdo", "windo",
+        ]
+        .contains(&name)
+}
+
+/// Every option in Vim 9.0, long and short names, sorted.
+const OPTIONS: &[&str] = &[
+    "acd",
+    "ai",
+    "akm",
+    "al",
+    "aleph",
+    "allowrevins",
+    "altkeymap",
+    "ambiwidth",
+    "ambw",
+    "anti",
+    "antialias",
+    "ar",
+    "arab",
+    "arabic",
+    "arabicshape",
+    "ari",
+    "arshape",
+    "asd",
+    "autochdir",
+    "autoindent",
+    "autoread",
+    "autoshelldir",
+    "autowrite",
+    "autowriteall",
+    "aw",
+    "awa",
+    "background",
+    "backspace",
+    "backup",
+    "backupcopy",
+    "backupdir",
+    "backupext",
+    "backupskip",
+    "balloondelay",
+    "ballooneval",
+    "balloonevalterm",
+    "balloonexpr",
+    "bdir",
+    "bdlay",
+    "belloff",
+    "beval",
+    "bevalterm",
+    "bex",
+    "bexpr",
+    "bg",
+    "bh",
+    "bin",
+    "binary",
+    "biosk",
+    "bioskey",
+    "bk",
+    "bkc",
+    "bl",
+    "bo",
+    "bomb",
+    "breakat",
+    "breakindent",
+    "breakindentopt",
+    "bri",
+    "briopt",
+    "brk",
+    "browsedir",
+    "bs",
+    "bsdir",
+    "bsk",
+    "bt",
+    "bufhidden",
+    "buflisted",
+    "buftype",
+    "casemap",
+    "cb",
+    "cc",
+    "ccv",
+    "cd",
+    "cdh",
+    "cdhome",
+    "cdpath",
+    "cedit",
+    "cf",
+    "cfu",
+    "ch",
+    "charconvert",
+    "ci",
+    "cin",
+    "cindent",
+    "cink",
+    "cinkeys",
+    "cino",
+    "cinoptions",
+    "cinscopedecls",
+    "cinsd",
+    "cinw",
+    "cinwords",
+    "clipboard",
+    "cm",
+    "cmdheight",
+    "cmdwinheight",
+    "cmp",
+    "cms",
+    "co",
+    "cocu",
+    "cole",
+    "colorcolumn",
+    "columns",
+    "com",
+    "comments",
+    "commentstring",
+    "compatible",
+    "complete",
+    "completefunc",
+    "completeopt",
+    "completepopup",
+    "completeslash",
+    "concealcursor",
+    "conceallevel",
+    "confirm",
+    "consk",
+    "conskey",
+    "copyindent",
+    "cot",
+    "cp",
+    "cpo",
+    "cpoptions",
+    "cpp",
+    "cpt",
+    "crb",
+    "cryptmethod",
+    "cscopepathcomp",
+    "cscopeprg",
+    "cscopequickfix",
+    "cscoperelative",
+    "cscopetag",
+    "cscopetagorder",
+    "cscopeverbose",
+    "csl",
+    "cspc",
+    "csprg",
+    "csqf",
+    "csre",
+    "cst",
+    "csto",
+    "csverb",
+    "cuc",
+    "cul",
+    "culopt",
+    "cursorbind",
+    "cursorcolumn",
+    "cursorline",
+    "cursorlineopt",
+    "cwh",
+    "debug",
+    "deco",
+    "def",
+    "define",
+    "delcombine",
+    "dex",
+    "dg",
+    "dict",
+    "dictionary",
+    "diff",
+    "diffexpr",
+    "diffopt",
+    "digraph",
+    "dip",
+    "dir",
+    "directory",
+    "display",
+    "dy",
+    "ea",
+    "ead",
+    "eadirection",
+    "eb",
+    "ed",
+    "edcompatible",
+    "ef",
+    "efm",
+    "ei",
+    "ek",
+    "emo",
+    "emoji",
+    "enc",
+    "encoding",
+    "endoffile",
+    "endofline",
+    "eof",
+    "eol",
+    "ep",
+    "equalalways",
+    "equalprg",
+    "errorbells",
+    "errorfile",
+    "errorformat",
+    "esckeys",
+    "et",
+    "eventignore",
+    "ex",
+    "expandtab",
+    "exrc",
+    "fcl",
+    "fcs",
+    "fdc",
+    "fde",
+    "fdi",
+    "fdl",
+    "fdls",
+    "fdm",
+    "fdn",
+    "fdo",
+    "fdt",
+    "fen",
+    "fenc",
+    "fencs",
+    "fex",
+    "ff",
+    "ffs",
+    "fic",
+    "fileencoding",
+    "fileencodings",
+    "fileformat",
+    "fileformats",
+    "fileignorecase",
+    "filetype",
+    "fillchars",
+    "fixendofline",
+    "fixeol",
+    "fk",
+    "fkmap",
+    "flp",
+    "fml",
+    "fmr",
+    "fo",
+    "foldclose",
+    "foldcolumn",
+    "foldenable",
+    "foldexpr",
+    "foldignore",
+    "foldlevel",
+    "foldlevelstart",
+    "foldmarker",
+    "foldmethod",
+    "foldminlines",
+    "foldnestmax",
+    "foldopen",
+    "foldtext",
+    "formatexpr",
+    "formatlistpat",
+    "formatoptions",
+    "formatprg",
+    "fp",
+    "fs",
+    "fsync",
+    "ft",
+    "gcr",
+    "gd",
+    "gdefault",
+    "gfm",
+    "gfn",
+    "gfs",
+    "gfw",
+    "ghr",
+    "gli",
+    "go",
+    "gp",
+    "grepformat",
+    "grepprg",
+    "gtl",
+    "gtt",
+    "guicursor",
+    "guifont",
+    "guifontset",
+    "guifontwide",
+    "guiheadroom",
+    "guiligatures",
+    "guioptions",
+    "guipty",
+    "guitablabel",
+    "guitabtooltip",
+    "helpfile",
+    "helpheight",
+    "helplang",
+    "hf",
+    "hh",
+    "hi",
+    "hid",
+    "hidden",
+    "highlight",
+    "history",
+    "hk",
+    "hkmap",
+    "hkmapp",
+    "hkp",
+    "hl",
+    "hlg",
+    "hls",
+    "hlsearch",
+    "ic",
+    "icon",
+    "iconstring",
+    "ignorecase",
+    "im",
+    "imactivatefunc",
+    "imactivatekey",
+    "imaf",
+    "imak",
+    "imc",
+    "imcmdline",
+    "imd",
+    "imdisable",
+    "imi",
+    "iminsert",
+    "ims",
+    "imsearch",
+    "imsf",
+    "imst",
+    "imstatusfunc",
+    "imstyle",
+    "inc",
+    "include",
+    "includeexpr",
+    "incsearch",
+    "inde",
+    "indentexpr",
+    "indentkeys",
+    "indk",
+    "inex",
+    "inf",
+    "infercase",
+    "insertmode",
+    "is",
+    "isf",
+    "isfname",
+    "isi",
+    "isident",
+    "isk",
+    "iskeyword",
+    "isp",
+    "isprint",
+    "joinspaces",
+    "js",
+    "key",
+    "keymap",
+    "keymodel",
+    "keyprotocol",
+    "keywordprg",
+    "km",
+    "kmp",
+    "kp",
+    "kpc",
+    "langmap",
+    "langmenu",
+    "langnoremap",
+    "langremap",
+    "laststatus",
+    "lazyredraw",
+    "lbr",
+    "lcs",
+    "linebreak",
+    "lines",
+    "linespace",
+    "lisp",
+    "lispoptions",
+    "lispwords",
+    "list",
+    "listchars",
+    "lm",
+    "lmap",
+    "lnr",
+    "loadplugins",
+    "lop",
+    "lpl",
+    "lrm",
+    "ls",
+    "lsp",
+    "luadll",
+    "lw",
+    "lz",
+    "ma",
+    "macatsui",
+    "magic",
+    "makeef",
+    "makeencoding",
+    "makeprg",
+    "mat",
+    "matchpairs",
+    "matchtime",
+    "maxcombine",
+    "maxfuncdepth",
+    "maxmapdepth",
+    "maxmem",
+    "maxmempattern",
+    "maxmemtot",
+    "mco",
+    "mef",
+    "menc",
+    "menuitems",
+    "mfd",
+    "mh",
+    "mis",
+    "mkspellmem",
+    "ml",
+    "mle",
+    "mls",
+    "mm",
+    "mmd",
+    "mmp",
+    "mmt",
+    "mod",
+    "modeline",
+    "modelineexpr",
+    "modelines",
+    "modifiable",
+    "modified",
+    "more",
+    "mouse",
+    "mousef",
+    "mousefocus",
+    "mousehide",
+    "mousem",
+    "mousemev",
+    "mousemodel",
+    "mousemoveevent",
+    "mouses",
+    "mouseshape",
+    "mouset",
+    "mousetime",
+    "mp",
+    "mps",
+    "msm",
+    "mzq",
+    "mzquantum",
+    "mzschemedll",
+    "mzschemegcdll",
+    "nf",
+    "nrformats",
+    "nu",
+    "number",
+    "numberwidth",
+    "nuw",
+    "odev",
+    "oft",
+    "ofu",
+    "omnifunc",
+    "opendevice",
+    "operatorfunc",
+    "opfunc",
+    "osfiletype",
+    "pa",
+    "packpath",
+    "para",
+    "paragraphs",
+    "paste",
+    "pastetoggle",
+    "patchexpr",
+    "patchmode",
+    "path",
+    "pdev",
+    "penc",
+    "perldll",
+    "pex",
+    "pexpr",
+    "pfn",
+    "ph",
+    "pheader",
+    "pi",
+    "pm",
+    "pmbcs",
+    "pmbfn",
+    "popt",
+    "pp",
+    "preserveindent",
+    "previewheight",
+    "previewpopup",
+    "previewwindow",
+    "printdevice",
+    "printencoding",
+    "printexpr",
+    "printfont",
+    "printheader",
+    "printmbcharset",
+    "printmbfont",
+    "printoptions",
+    "prompt",
+    "pt",
+    "pumheight",
+    "pumwidth",
+    "pvh",
+    "pvp",
+    "pvw",
+    "pw",
+    "pythondll",
+    "pythonhome",
+    "pythonthreedll",
+    "pythonthreehome",
+    "pyx",
+    "pyxversion",
+    "qe",
+    "qftf",
+    "quickfixtextfunc",
+    "quoteescape",
+    "rdt",
+    "re",
+    "readonly",
+    "redrawtime",
+    "regexpengine",
+    "relativenumber",
+    "remap",
+    "renderoptions",
+    "report",
+    "restorescreen",
+    "revins",
+    "ri",
+    "rightleft",
+    "rightleftcmd",
+    "rl",
+    "rlc",
+    "rnu",
+    "ro",
+    "rop",
+    "rs",
+    "rtp",
+    "ru",
+    "rubydll",
+    "ruf",
+    "ruler",
+    "rulerformat",
+    "runtimepath",
+    "sb",
+    "sbo",
+    "sbr",
+    "sc",
+    "scb",
+    "scf",
+    "scl",
+    "scr",
+    "scroll",
+    "scrollbind",
+    "scrollfocus",
+    "scrolljump",
+    "scrolloff",
+    "scrollopt",
+    "scs",
+    "sect",
+    "sections",
+    "secure",
+    "sel",
+    "selection",
+    "selectmode",
+    "sessionoptions",
+    "sft",
+    "sh",
+    "shcf",
+    "shell",
+    "shellcmdflag",
+    "shellpipe",
+    "shellquote",
+    "shellredir",
+    "shellslash",
+    "shelltemp",
+    "shelltype",
+    "shellxescape",
+    "shellxquote",
+    "shiftround",
+    "shiftwidth",
+    "shm",
+    "shortmess",
+    "shortname",
+    "showbreak",
+    "showcmd",
+    "showcmdloc",
+    "showfulltag",
+    "showmatch",
+    "showmode",
+    "showtabline",
+    "shq",
+    "si",
+    "sidescroll",
+    "sidescrolloff",
+    "signcolumn",
+    "siso",
+    "sj",
+    "slm",
+    "sloc",
+    "sm",
+    "smartcase",
+    "smartindent",
+    "smarttab",
+    "smc",
+    "smd",
+    "smoothscroll",
+    "sms",
+    "sn",
+    "so",
+    "softtabstop",
+    "sol",
+    "sp",
+    "spc",
+    "spell",
+    "spellcapcheck",
+    "spellfile",
+    "spelllang",
+    "spelloptions",
+    "spellsuggest",
+    "spf",
+    "spk",
+    "spl",
+    "splitbelow",
+    "splitkeep",
+    "splitright",
+    "spo",
+    "spr",
+    "sps",
+    "sr",
+    "srr",
+    "ss",
+    "ssl",
+    "ssop",
+    "st",
+    "sta",
+    "stal",
+    "startofline",
+    "statusline",
+    "stl",
+    "stmp",
+    "sts",
+    "su",
+    "sua",
+    "suffixes",
+    "suffixesadd",
+    "sw",
+    "swapfile",
+    "swapsync",
+    "swb",
+    "swf",
+    "switchbuf",
+    "sws",
+    "sxe",
+    "sxq",
+    "syn",
+    "synmaxcol",
+    "syntax",
+    "ta",
+    "tabline",
+    "tabpagemax",
+    "tabstop",
+    "tag",
+    "tagbsearch",
+    "tagcase",
+    "tagfunc",
+    "taglength",
+    "tagrelative",
+    "tags",
+    "tagstack",
+    "tal",
+    "tb",
+    "tbi",
+    "tbidi",
+    "tbis",
+    "tbs",
+    "tc",
+    "tcldll",
+    "tenc",
+    "term",
+    "termbidi",
+    "termencoding",
+    "termguicolors",
+    "termwinkey",
+    "termwinscroll",
+    "termwinsize",
+    "termwintype",
+    "terse",
+    "textauto",
+    "textmode",
+    "textwidth",
+    "tf",
+    "tfu",
+    "tgc",
+    "tgst",
+    "thesaurus",
+    "thesaurusfunc",
+    "tildeop",
+    "timeout",
+    "timeoutlen",
+    "title",
+    "titlelen",
+    "titleold",
+    "titlestring",
+    "tl",
+    "tm",
+    "to",
+    "toolbar",
+    "toolbariconsize",
+    "top",
+    "tpm",
+    "tr",
+    "ts",
+    "tsl",
+    "tsr",
+    "tsrfu",
+    "ttimeout",
+    "ttimeoutlen",
+    "ttm",
+    "tty",
+    "ttybuiltin",
+    "ttyfast",
+    "ttym",
+    "ttymouse",
+    "ttyscroll",
+    "ttytype",
+    "tw",
+    "twk",
+    "tws",
+    "twsl",
+    "twt",
+    "tx",
+    "uc",
+    "udf",
+    "udir",
+    "ul",
+    "undodir",
+    "undofile",
+    "undolevels",
+    "undoreload",
+    "updatecount",
+    "updatetime",
+    "ur",
+    "ut",
+    "varsofttabstop",
+    "vartabstop",
+    "vb",
+    "vbs",
+    "vdir",
+    "ve",
+    "verbose",
+    "verbosefile",
+    "vfile",
+    "vi",
+    "viewdir",
+    "viewoptions",
+    "vif",
+    "viminfo",
+    "viminfofile",
+    "virtualedit",
+    "visualbell",
+    "vop",
+    "vsts",
+    "vts",
+    "wa",
+    "wak",
+    "warn",
+    "wb",
+    "wc",
+    "wcm",
+    "wcr",
+    "wd",
+    "weirdinvert",
+    "wfh",
+    "wfw",
+    "wh",
+    "whichwrap",
+    "wi",
+    "wic",
+    "wig",
+    "wildchar",
+    "wildcharm",
+    "wildignore",
+    "wildignorecase",
+    "wildmenu",
+    "wildmode",
+    "wildoptions",
+    "wim",
+    "winaltkeys",
+    "wincolor",
+    "window",
+    "winfixheight",
+    "winfixwidth",
+    "winheight",
+    "winminheight",
+    "winminwidth",
+    "winptydll",
+    "winwidth",
+    "wiv",
+    "wiw",
+    "wm",
+    "wmh",
+    "wmnu",
+    "wmw",
+    "wop",
+    "wrap",
+    "wrapmargin",
+    "wrapscan",
+    "write",
+    "writeany",
+    "writebackup",
+    "writedelay",
+    "ws",
+    "ww",
+    "xtermcodes",
+];
@@ -0,0 +1,244 @@
+//! What Vim sources from the runtimepath, and in what order.
+//!
+//! Everything under `plugin/` and `ftdetect/` is sourced at startup, in
+//! runtimepath order and, within a directory, in the order Vim's `glob()`
+//! sorts paths. For a filetype, Vim sources every matching `ftplugin/` and
+//! `syntax/` file in the same way, but by convention each `<filetype>.vim`
+//! stops early if one before it already set the buffer up, so only the
+//! first takes effect; the files in `after` directories, and the
+//! `<filetype>/*.vim` (and for ftplugins `<filetype>_*.vim`) files after
+//! it, add to it. An `autoload/` script is only ever sourced from the
+//! first directory that has it.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::Result;
+use crate::layout::Layout;
+use crate::pathogen::{self, Runtimepath};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kind {
+    Plugin,
+    Ftdetect,
+    Ftplugin,
+    Syntax,
+    Autoload,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 5] = [
+        Kind::Plugin,
+        Kind::Ftdetect,
+        Kind::Ftplugin,
+        Kind::Syntax,
+        Kind::Autoload,
+    ];
+
+    pub fn dir(self) -> &'static str {
+        match self {
+            Kind::Plugin => "plugin",
+            Kind::Ftdetect => "ftdetect",
+            Kind::Ftplugin => "ftplugin",
+            Kind::Syntax => "syntax",
+            Kind::Autoload => "autoload",
+        }
+    }
+}
+
+/// The files of one kind from the runtimepath's `after` directories, or
+/// from the rest of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub kind: Kind,
+    pub after: bool,
+    /// In the order Vim sources them.
+    pub files: Vec<RuntimeFile>,
+}
+
+impl Section {
+    /// `syntax/`, or `after/syntax/`.
+    pub fn heading(&self) -> String {
+        match self.after {
+            false => format!("{}/", self.kind.dir()),
+            true => format!("after/{}/", self.kind.dir()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeFile {
+    pub path: PathBuf,
+    /// What it is sourced for: the filetype for ftplugins and syntax
+    /// files, otherwise the path below the kind's directory.
+    pub name: String,
+    /// The file sourced before it that takes effect instead.
+    pub overridden_by: Option<PathBuf>,
+    /// The file sourced before it that it adds to.
+    pub extends: Option<PathBuf>,
+    /// The files sourced after it that take its place, for autoload
+    /// scripts, or would have, for ftplugins and syntax files.
+    pub overrides: Vec<PathBuf>,
+    /// The files sourced after it that add to it.
+    pub extended_by: Vec<PathBuf>,
+}
+
+/// [`analyze`] for the runtimepath `.vimrc` leaves Vim with.
+pub fn load_order(layout: &Layout) -> Result<Vec<Section>> {
+    Ok(analyze(&pathogen::resolve(layout)?))
+}
+
+/// Every runtime file of each [`Kind`] on `rtp`, grouped by kind and by
+/// whether it comes from an `after` directory. Kinds with no files are
+/// left out. A file reached through more than one entry is listed once.
+pub fn analyze(rtp: &Runtimepath) -> Vec<Section> {
+    let dirs = rtp.dirs();
+    let mut sections = Vec::new();
+    for kind in Kind::ALL {
+        let mut seen = HashSet::new();
+        let mut files: Vec<(bool, RuntimeFile)> = Vec::new();
+        // Indexes into `files` of the file taking effect for each name.
+        let mut winners: HashMap<String, usize> = HashMap::new();
+        for dir in &dirs {
+            let after = Path::new(dir)
+                .file_name()
+                .is_some_and(|name| name == "after");
+            for (relative, name, main) in scripts(&Path::new(dir).join(kind.dir()), kind) {
+                let path = Path::new(dir).join(kind.dir()).join(&relative);
+                if !seen.insert(fs::canonicalize(&path).unwrap_or_else(|_| path.clone())) {
+                    continue;
+                }
+                let mut file = RuntimeFile {
+                    path,
+                    name,
+                    overridden_by: None,
+                    extends: None,
+                    overrides: Vec::new(),
+                    extended_by: Vec::new(),
+                };
+                let index = files.len();
+                match (kind, winners.get(&file.name).copied()) {
+                    (Kind::Plugin | Kind::Ftdetect, _) => {}
+                    (Kind::Autoload, None) => {
+                        winners.insert(file.name.clone(), index);
+                    }
+                    (Kind::Autoload, Some(winner)) => {
+                        file.overridden_by = Some(files[winner].1.path.clone());
+                        files[winner].1.overrides.push(file.path.clone());
+                    }
+                    (_, None) => {
+                        if main && !after {
+                            winners.insert(file.name.clone(), index);
+                        }
+                    }
+                    (_, Some(winner)) if main && !after => {
+                        file.overridden_by = Some(files[winner].1.path.clone());
+                        files[winner].1.overrides.push(file.path.clone());
+                    }
+                    (_, Some(winner)) => {
+                        file.extends = Some(files[winner].1.path.clone());
+                        files[winner].1.extended_by.push(file.path.clone());
+                    }
+                }
+                files.push((after, file));
+            }
+        }
+        for after in [false, true] {
+            let section: Vec<RuntimeFile> = files
+                .iter()
+                .filter(|(a, _)| *a == after)
+                .map(|(_, file)| file.clone())
+                .collect();
+            if !section.is_empty() {
+                sections.push(Section {
+                    kind,
+                    after,
+                    files: section,
+                });
+            }
+        }
+    }
+    sections
+}
+
+/// The scripts of `kind` in `dir`, relative to it, in the order Vim
+/// sources them, each with the name it is sourced for and whether it is
+/// the filetype's own `<filetype>.vim`.
+fn scripts(dir: &Path, kind: Kind) -> Vec<(String, String, bool)> {
+    let depth = match kind {
+        Kind::Plugin | Kind::Autoload => usize::MAX,
+        Kind::Ftdetect => 1,
+        Kind::Ftplugin | Kind::Syntax => 2,
+    };
+    let mut paths = Vec::new();
+    walk(dir, "", depth, &mut paths);
+    // `glob()` sorts each pattern's matches with separators first.
+    paths.sort_by_key(|path| vim_path_order(path));
+    let mut scripts: Vec<_> = paths
+        .into_iter()
+        .filter_map(|relative| {
+            let stem = relative.strip_suffix(".vim")?;
+            match kind {
+                Kind::Plugin | Kind::Ftdetect | Kind::Autoload => {
+                    Some((relative.clone(), relative, false))
+                }
+                // `runtime! syntax/<ft>.vim syntax/<ft>/*.vim`
+                Kind::Syntax => match stem.split_once('/') {
+                    None => Some((relative.clone(), stem.to_string(), true)),
+                    Some((filetype, _)) => Some((relative.clone(), filetype.to_string(), false)),
+                },
+                // `runtime! ftplugin/<ft>.vim ftplugin/<ft>_*.vim
+                // ftplugin/<ft>/*.vim`
+                Kind::Ftplugin => match stem.split_once('/') {
+                    Some((filetype, _)) => Some((relative.clone(), filetype.to_string(), false)),
+                    None => match stem.split_once('_') {
+                        Some((filetype, _)) => {
+                            Some((relative.clone(), filetype.to_string(), false))
+                        }
+                        None => Some((relative.clone(), stem.to_string(), true)),
+                    },
+                },
+            }
+        })
+        .collect();
+    if matches!(kind, Kind::Ftplugin | Kind::Syntax) {
+        // A filetype's patterns are tried one after the other.
+        scripts.sort_by_key(|(relative, name, main)| (name.clone(), !main, relative.contains('/')));
+    }
+    scripts
+}
+
+/// The files below `dir`, as `relative` paths under `prefix`, going at
+/// most `depth` levels down and skipping dot files as `glob()` does.
+fn walk(dir: &Path, prefix: &str, depth: usize, paths: &mut Vec<String>) {
+    if depth == 0 {
+        return;
+    }
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+        if name.starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        let relative = format!("{prefix}{name}");
+        if path.is_dir() {
+            walk(&path, &format!("{relative}/"), depth - 1, paths);
+        } else {
+            paths.push(relative);
+        }
+    }
+}
+
+/// A sort key putting `/` before every other byte, as Vim's `pathcmp()`
+/// does.
+fn vim_path_order(path: &str) -> Vec<u8> {
+    path.bytes()
+        .map(|b| if b == b'/' { 0 } else { b })
+        .collect()
+}
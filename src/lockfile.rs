@@ -0,0 +1,361 @@
+//! `bundles.lock`: the exact vim bundles a working setup was made of.
+//!
+//! ```toml
+//! version = 1
+//!
+//! [[bundle]]
+//! path = ".vim/bundle/vim-fugitive"
+//! url = "https://github.com/tpope/vim-fugitive.git"
+//! commit = "0b7ef3d7a0b5c0c3e5bd0b2e0e47e3d6b1e5b26c"
+//! tree = "sha256:9f2c…"
+//! ```
+//!
+//! Bundles are the submodules in `.gitmodules` and the plugins in the
+//! manifest. A submodule's URL is the one from `.gitmodules` after the
+//! manifest's rewrites, so it can actually be cloned. The tree hash covers
+//! the content of every file git tracks in the checkout, and so catches
+//! edits made in place that the commit alone would not. A bundle that is
+//! not checked out is locked without one, at the commit the repository
+//! records for a submodule or the one a plugin is pinned to.
+
+use std::fmt;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::bundles::{git, head, recorded};
+use crate::error::{Error, Result};
+use crate::fsutil::is_missing;
+use crate::gitmodules::GitModules;
+use crate::layout::Layout;
+use crate::manifest::{Manifest, Pin};
+use crate::strategy::hash_bytes;
+
+pub const LOCKFILE: &str = "bundles.lock";
+
+const VERSION: u32 = 1;
+
+const HEADER: &str =
+    "# Written by `dotfiles bundles lock`; check with `dotfiles bundles verify`.\n\n";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockfile {
+    pub version: u32,
+    #[serde(default, rename = "bundle")]
+    pub bundles: Vec<LockedBundle>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedBundle {
+    /// Relative to the repository root.
+    pub path: PathBuf,
+    pub url: String,
+    pub commit: String,
+    /// `None` if the bundle was not checked out when it was locked, in
+    /// which case only its commit is checked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tree: Option<String>,
+}
+
+impl Lockfile {
+    /// Reads the lockfile in `repo`, if there is one.
+    pub fn load(repo: &Path) -> Result<Option<Self>> {
+        let path = repo.join(LOCKFILE);
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if is_missing(&e) => return Ok(None),
+            Err(e) => return Err(Error::io(&path, e)),
+        };
+        let lockfile: Lockfile = toml::from_str(&text).map_err(|e| Error::toml(&path, e))?;
+        if lockfile.version != VERSION {
+            return Err(Error::LockfileVersion {
+                path,
+                version: lockfile.version,
+            });
+        }
+        Ok(Some(lockfile))
+    }
+
+    pub fn save(&self, repo: &Path) -> Result<()> {
+        let path = repo.join(LOCKFILE);
+        let text = toml::to_string(self).expect("lockfile serializes");
+        fs::write(&path, format!("{HEADER}{text}")).map_err(|e| Error::io(&path, e))
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&LockedBundle> {
+        self.bundles.iter().find(|b| b.path == path)
+    }
+}
+
+/// A bundle the repository declares, as a submodule or as a plugin in the
+/// manifest.
+pub(crate) struct Declared {
+    /// Relative to the repository root.
+    pub path: PathBuf,
+    /// With the manifest's rewrites applied, for submodules.
+    pub url: String,
+    /// The branch it follows, if not the upstream's default one.
+    pub branch: Option<String>,
+    /// For plugins, what the manifest pins them to.
+    pub pin: Option<Pin>,
+}
+
+/// The submodules in `.gitmodules`, then the plugins in the manifest. A
+/// plugin at a submodule's path stands in for it, as `plugins migrate`
+/// has it.
+pub(crate) fn declared(repo: &Path, manifest: &Manifest) -> Result<Vec<Declared>> {
+    let mut bundles = Vec::new();
+    for module in &GitModules::load(repo)?.modules {
+        let path = PathBuf::from(module.require(repo, "path")?);
+        if manifest.plugins.iter().any(|p| p.path == path) {
+            continue;
+        }
+        let url = module.require(repo, "url")?;
+        bundles.push(Declared {
+            url: manifest.rewrite_url(url).unwrap_or_else(|| url.to_string()),
+            branch: module.get("branch").map(str::to_string),
+            pin: None,
+            path,
+        });
+    }
+    for plugin in &manifest.plugins {
+        bundles.push(Declared {
+            path: plugin.path.clone(),
+            url: plugin.url.clone(),
+            branch: match &plugin.pin {
+                Pin::Branch(branch) => Some(branch.clone()),
+                _ => None,
+            },
+            pin: Some(plugin.pin.clone()),
+        });
+    }
+    Ok(bundles)
+}
+
+/// Records every bundle's URL, checked-out commit and tree hash in
+/// `bundles.lock`, for submodules and the manifest's plugins alike.
+/// Bundles that are not checked out are recorded without a tree hash, at
+/// the commit the repository records for a submodule or the one a plugin
+/// is pinned to.
+pub fn lock(layout: &Layout) -> Result<Lockfile> {
+    let repo = layout.repo();
+    let manifest = Manifest::load(repo)?;
+    let mut bundles = Vec::new();
+    for bundle in declared(repo, &manifest)? {
+        match record_or_pinned(repo, &bundle)? {
+            Some(locked) => bundles.push(locked),
+            None => return Err(Error::NotCheckedOut(bundle.path)),
+        }
+    }
+    let lockfile = Lockfile {
+        version: VERSION,
+        bundles,
+    };
+    lockfile.save(repo)?;
+    Ok(lockfile)
+}
+
+/// Records the bundles at `paths` afresh, keeping every other locked
+/// bundle as it was. Bundles not locked yet are recorded too, as [`lock`]
+/// records them. The bundles at `paths` have to be checked out.
+pub fn relock(layout: &Layout, paths: &[PathBuf]) -> Result<Lockfile> {
+    let repo = layout.repo();
+    let manifest = Manifest::load(repo)?;
+    let old = Lockfile::load(repo)?;
+    let mut bundles = Vec::new();
+    for bundle in declared(repo, &manifest)? {
+        let locked = old.as_ref().and_then(|l| l.get(&bundle.path));
+        if let (Some(locked), false) = (locked, paths.contains(&bundle.path)) {
+            bundles.push(locked.clone());
+            continue;
+        }
+        if paths.contains(&bundle.path) {
+            match record(repo, &bundle)? {
+                Some(locked) => bundles.push(locked),
+                None => return Err(Error::NotCheckedOut(bundle.path)),
+            }
+        } else {
+            bundles.extend(record_or_pinned(repo, &bundle)?);
+        }
+    }
+    let lockfile = Lockfile {
+        version: VERSION,
+        bundles,
+    };
+    lockfile.save(repo)?;
+    Ok(lockfile)
+}
+
+/// The entry for `bundle`, if it is checked out.
+fn record(repo: &Path, bundle: &Declared) -> Result<Option<LockedBundle>> {
+    let dir = repo.join(&bundle.path);
+    let (Some(commit), Some(tree)) = (head(&dir)?, tree_hash(&dir)?) else {
+        return Ok(None);
+    };
+    Ok(Some(LockedBundle {
+        path: bundle.path.clone(),
+        url: bundle.url.clone(),
+        commit,
+        tree: Some(tree),
+    }))
+}
+
+/// [`record`], or if the bundle is not checked out, the commit the
+/// repository records for a submodule or a plugin is pinned to, if there
+/// is one.
+fn record_or_pinned(repo: &Path, bundle: &Declared) -> Result<Option<LockedBundle>> {
+    if let Some(locked) = record(repo, bundle)? {
+        return Ok(Some(locked));
+    }
+    let commit = match &bundle.pin {
+        None => recorded(repo, &bundle.path)?,
+        Some(Pin::Commit(commit)) => Some(commit.clone()),
+        Some(_) => None,
+    };
+    Ok(commit.map(|commit| LockedBundle {
+        path: bundle.path.clone(),
+        url: bundle.url.clone(),
+        commit,
+        tree: None,
+    }))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    /// There is no `bundles.lock` at all.
+    NoLockfile,
+    /// Declared in `.gitmodules` or the manifest but not locked.
+    NotLocked,
+    /// Locked, but no longer declared in either.
+    NotDeclared,
+    /// Declared and locked, but not checked out. Not a failure: there is
+    /// nothing there to differ.
+    NotCheckedOut,
+    /// `.gitmodules` or the manifest now points somewhere else.
+    Url { locked: String, declared: String },
+    /// Checked out at another commit.
+    Commit { locked: String, head: String },
+    /// At the locked commit, but with different content.
+    Tree,
+}
+
+impl Mismatch {
+    pub fn is_failure(&self) -> bool {
+        *self != Mismatch::NotCheckedOut
+    }
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mismatch::NoLockfile => {
+                write!(f, "there is no {LOCKFILE}; run `dotfiles bundles lock`")
+            }
+            Mismatch::NotLocked => f.write_str("not in the lockfile"),
+            Mismatch::NotDeclared => f.write_str("locked, but not in .gitmodules or the manifest"),
+            Mismatch::NotCheckedOut => f.write_str("not checked out"),
+            Mismatch::Url { locked, declared } => {
+                write!(f, "url is {declared}, locked {locked}")
+            }
+            Mismatch::Commit { locked, head } => write!(f, "at {head}, locked {locked}"),
+            Mismatch::Tree => f.write_str("files differ from the locked commit's"),
+        }
+    }
+}
+
+/// Compares the checked-out bundles with `bundles.lock`, returning each
+/// bundle that does not match, relative to the repository root.
+pub fn verify(layout: &Layout) -> Result<Vec<(PathBuf, Mismatch)>> {
+    let repo = layout.repo();
+    let Some(lockfile) = Lockfile::load(repo)? else {
+        return Ok(vec![(PathBuf::from(LOCKFILE), Mismatch::NoLockfile)]);
+    };
+    let manifest = Manifest::load(repo)?;
+    let bundles = declared(repo, &manifest)?;
+    let mut mismatches = Vec::new();
+    for bundle in &bundles {
+        let path = bundle.path.clone();
+        let Some(locked) = lockfile.get(&path) else {
+            mismatches.push((path, Mismatch::NotLocked));
+            continue;
+        };
+        let declared = bundle.url.clone();
+        if declared != locked.url {
+            let locked = locked.url.clone();
+            mismatches.push((path.clone(), Mismatch::Url { locked, declared }));
+        }
+        let dir = repo.join(&path);
+        let mismatch = match (head(&dir)?, tree_hash(&dir)?) {
+            (Some(head), Some(tree)) => {
+                if head != locked.commit {
+                    let locked = locked.commit.clone();
+                    Mismatch::Commit { locked, head }
+                } else if locked.tree.as_ref().is_some_and(|locked| *locked != tree) {
+                    Mismatch::Tree
+                } else {
+                    continue;
+                }
+            }
+            _ => Mismatch::NotCheckedOut,
+        };
+        mismatches.push((path, mismatch));
+    }
+    for locked in &lockfile.bundles {
+        if !bundles.iter().any(|b| b.path == locked.path) {
+            mismatches.push((locked.path.clone(), Mismatch::NotDeclared));
+        }
+    }
+    Ok(mismatches)
+}
+
+/// The tree hash of the checkout in `dir`, if it is one.
+fn tree_hash(dir: &Path) -> Result<Option<String>> {
+    if fs::symlink_metadata(dir.join(".git")).is_err() {
+        return Ok(None);
+    }
+    let listing = git(dir, &["ls-files", "-z"])?;
+    let files: Vec<PathBuf> = listing
+        .split('\0')
+        .filter(|f| !f.is_empty())
+        .map(PathBuf::from)
+        .collect();
+    hash_tree(dir, &files).map(Some)
+}
+
+/// Hashes `files`, relative to `dir`, by name, type and content. Files
+/// that are missing are left out, and directories (nested submodules) are
+/// skipped.
+pub fn hash_tree(dir: &Path, files: &[PathBuf]) -> Result<String> {
+    let mut files = files.to_vec();
+    files.sort();
+    let mut hasher = Sha256::new();
+    for file in files {
+        let full = dir.join(&file);
+        let meta = match fs::symlink_metadata(&full) {
+            Ok(meta) => meta,
+            Err(e) if is_missing(&e) => continue,
+            Err(e) => return Err(Error::io(&full, e)),
+        };
+        let (kind, hash) = if meta.is_symlink() {
+            let target = fs::read_link(&full).map_err(|e| Error::io(&full, e))?;
+            ("link", hash_bytes(target.as_os_str().as_encoded_bytes()))
+        } else if meta.is_file() {
+            let bytes = fs::read(&full).map_err(|e| Error::io(&full, e))?;
+            let kind = if meta.permissions().mode() & 0o111 != 0 {
+                "exec"
+            } else {
+                "file"
+            };
+            (kind, hash_bytes(&bytes))
+        } else {
+            continue;
+        };
+        hasher.update(format!("{kind} {hash} ").as_bytes());
+        hasher.update(file.as_os_str().as_encoded_bytes());
+        hasher.update(b"\0");
+    }
+    Ok(format!("sha256:{:x}", hasher.finalize()))
+}
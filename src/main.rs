@@ -0,0 +1,798 @@
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+
+use dotfiles::adopt::{self, Outcome as AdoptOutcome};
+use dotfiles::backup::BackupStore;
+use dotfiles::bundles::{self, Mechanism, State as BundleState};
+use dotfiles::conflicts;
+use dotfiles::helptags;
+use dotfiles::install::{self, Outcome};
+use dotfiles::lint;
+use dotfiles::loadorder;
+use dotfiles::lockfile::{self, LOCKFILE};
+use dotfiles::pathogen;
+use dotfiles::plan::{self, Plan};
+use dotfiles::plugins::{self, Outcome as PluginOutcome};
+use dotfiles::secrets::{self, SecretStore};
+use dotfiles::status::{self, State};
+use dotfiles::transaction::Transaction;
+use dotfiles::uninstall::{self, Outcome as UninstallOutcome};
+use dotfiles::update::{self, Update};
+use dotfiles::vendor::{self, Outcome as VendorOutcome};
+use dotfiles::{Error, Layout, Result};
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// The dotfiles repository to install from.
+    #[arg(long, global = true, default_value = ".")]
+    repo: PathBuf,
+
+    /// The home directory to install into. Defaults to $HOME.
+    #[arg(long, global = true)]
+    home: Option<PathBuf>,
+
+    /// The manifest profile to use instead of the one matching the
+    /// hostname.
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Install every dotfile into the home directory, backing up anything
+    /// in the way.
+    Install {
+        /// Print what would be done instead of doing it.
+        #[arg(long)]
+        dry_run: bool,
+        /// Print the dry-run plan as JSON, suitable for `apply --plan`.
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+    },
+    /// Carry out a plan saved from `install --dry-run --json`.
+    Apply {
+        /// The plan file, or `-` for standard input.
+        #[arg(long)]
+        plan: PathBuf,
+    },
+    /// Copy a file or directory from the home directory into the
+    /// repository and install it from there.
+    Adopt {
+        /// The path to adopt, relative to the current directory.
+        path: PathBuf,
+    },
+    /// Remove every link the tool created and restore what they replaced.
+    Uninstall,
+    /// Compare the repository with the home directory. Exits non-zero if
+    /// anything is not linked.
+    Status {
+        /// Leave out the diffs of diverged copies.
+        #[arg(long)]
+        no_diff: bool,
+    },
+    /// Manage the encrypted store behind `secret://` template placeholders.
+    Secrets {
+        #[command(subcommand)]
+        command: SecretsCommand,
+    },
+    /// Inspect the vim bundles declared in `.gitmodules`.
+    Bundles {
+        #[command(subcommand)]
+        command: BundlesCommand,
+    },
+    /// Manage the vim plugins listed in `dotfiles.toml`.
+    Plugins {
+        #[command(subcommand)]
+        command: PluginsCommand,
+    },
+    /// List every copy of a runtime file, such as `syntax/nginx.vim`, in
+    /// the order Vim finds them: the first wins, the rest are shadowed.
+    WhichRuntime {
+        /// The file, relative to a runtimepath directory.
+        file: String,
+        /// List the runtime files and directories starting with `file`
+        /// instead, for shell completion.
+        #[arg(long)]
+        complete: bool,
+    },
+    /// Check `.vimrc` and the scripts under `.vim` for common Vimscript
+    /// mistakes. Exits non-zero if there are any.
+    Lint,
+    /// List the backups taken by previous installs.
+    Backups,
+    /// Put every file from a backup back where it was.
+    Restore {
+        /// The backup to restore, as printed by `install` or `backups`.
+        id: String,
+    },
+    /// Finish an install that was interrupted part-way through.
+    Resume,
+    /// Undo whatever an interrupted install had already done.
+    Rollback,
+}
+
+#[derive(Subcommand)]
+enum SecretsCommand {
+    /// Store a secret, read from the terminal or standard input.
+    Set {
+        /// The name templates use, as in `secret://NAME`.
+        name: String,
+    },
+    /// List the names of the stored secrets.
+    List,
+    /// Delete a secret.
+    Remove { name: String },
+}
+
+#[derive(Subcommand)]
+enum BundlesCommand {
+    /// Show whether each bundle is checked out at its recorded commit.
+    /// Exits non-zero if any is not.
+    Status,
+    /// List bundles that provide the same plugin, files or commands, and
+    /// which of them wins. Exits non-zero if there are any.
+    Conflicts,
+    /// Record every bundle's URL, commit and content hash in
+    /// `bundles.lock`.
+    Lock,
+    /// Check the checked-out bundles against `bundles.lock`. Exits
+    /// non-zero if any differ.
+    Verify,
+    /// Copy every bundle's pinned commit into a local cache, for machines
+    /// without a network.
+    Vendor {
+        /// The cache directory, created if need be.
+        #[arg(long)]
+        cache: PathBuf,
+    },
+    /// Check out every bundle at its pinned commit.
+    Install {
+        /// Take commits from this cache made by `bundles vendor`.
+        #[arg(long)]
+        cache: Option<PathBuf>,
+        /// Use nothing but the cache.
+        #[arg(long, requires = "cache")]
+        offline: bool,
+    },
+    /// Fetch every bundle, show the commits each would pull in, and check
+    /// out and lock the updates you accept.
+    Update {
+        /// How many bundles to fetch at once.
+        #[arg(long, default_value_t = plugins::DEFAULT_JOBS)]
+        jobs: usize,
+        /// Accept every update without asking.
+        #[arg(long)]
+        yes: bool,
+    },
+    /// List the plugin, ftdetect, ftplugin, syntax and autoload scripts Vim
+    /// sources from the runtimepath, in order, and which of them override
+    /// or add to others.
+    LoadOrder,
+    /// Write the `doc/tags` files Vim's `:helptags` would for every bundle
+    /// pathogen loads, and list tags defined more than once. Also done by
+    /// every install.
+    Helptags,
+    /// Stop pathogen loading a bundle, and show the runtimepath that
+    /// leaves.
+    Disable {
+        /// The bundle's directory name in `.vim/bundle`.
+        name: String,
+        /// Rename the directory to end in `~` instead of listing it in
+        /// `g:pathogen_disabled` in `.vimrc`.
+        #[arg(long)]
+        rename: bool,
+    },
+    /// Let pathogen load a disabled bundle again, and show the runtimepath.
+    Enable {
+        /// The bundle's directory name in `.vim/bundle`.
+        name: String,
+    },
+    /// Rewrite submodule URLs that can no longer be cloned, by the rules
+    /// in `dotfiles.toml`, and show the change to `.gitmodules`.
+    FixUrls {
+        /// Show the diff without writing `.gitmodules`.
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum PluginsCommand {
+    /// Clone missing plugins and check each out on its pin.
+    Install {
+        /// How many plugins to clone or fetch at once.
+        #[arg(long, default_value_t = plugins::DEFAULT_JOBS)]
+        jobs: usize,
+    },
+    /// List the submodules in `.gitmodules` as plugins in `dotfiles.toml`
+    /// and stop tracking them as submodules.
+    Migrate,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("dotfiles: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<ExitCode> {
+    let home = match cli.home {
+        Some(home) => home,
+        None => std::env::var_os("HOME")
+            .map(PathBuf::from)
+            .ok_or(Error::NoHome)?,
+    };
+    let mut layout = Layout::new(&cli.repo, &home)?;
+    if let Some(profile) = cli.profile {
+        layout = layout.with_profile(profile);
+    }
+
+    match cli.command {
+        Command::Install { dry_run: false, .. } => run_install(&layout),
+        Command::Install {
+            dry_run: true,
+            json,
+        } => run_dry_run(&layout, json),
+        Command::Apply { plan } => run_apply(&layout, &plan),
+        Command::Adopt { path } => run_adopt(&layout, &path),
+        Command::Uninstall => run_uninstall(&layout),
+        Command::Status { no_diff } => run_status(&layout, !no_diff),
+        Command::Secrets { command } => run_secrets(&layout, command),
+        Command::Bundles { command } => run_bundles(&layout, command),
+        Command::Plugins { command } => run_plugins(&layout, command),
+        Command::WhichRuntime { file, complete } => run_which_runtime(&layout, &file, complete),
+        Command::Lint => run_lint(&layout),
+        Command::Backups => run_backups(&layout),
+        Command::Restore { id } => run_restore(&layout, &id),
+        Command::Resume => run_resume(&layout),
+        Command::Rollback => run_rollback(&layout),
+    }
+}
+
+fn run_install(layout: &Layout) -> Result<ExitCode> {
+    let code = print_report(&install::install(layout)?)?;
+    install_helptags(layout);
+    Ok(code)
+}
+
+fn run_dry_run(layout: &Layout, json: bool) -> Result<ExitCode> {
+    let plan = plan::plan(layout)?;
+    if json {
+        let text = serde_json::to_string_pretty(&plan).map_err(|e| Error::json("<stdout>", e))?;
+        println!("{text}");
+    } else {
+        print!("{plan}");
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_apply(layout: &Layout, path: &Path) -> Result<ExitCode> {
+    let text = if path == Path::new("-") {
+        io::read_to_string(io::stdin()).map_err(|e| Error::io("<stdin>", e))?
+    } else {
+        fs::read_to_string(path).map_err(|e| Error::io(path, e))?
+    };
+    let plan: Plan = serde_json::from_str(&text).map_err(|e| Error::json(path, e))?;
+    let code = print_report(&install::apply(layout, plan)?)?;
+    install_helptags(layout);
+    Ok(code)
+}
+
+fn print_report(report: &install::Report) -> Result<ExitCode> {
+    for (entry, outcome) in &report.results {
+        let path = entry.path.display();
+        match outcome {
+            Outcome::Linked => println!("linked   {path}"),
+            Outcome::Replaced => println!("replaced {path} (original backed up)"),
+            Outcome::Updated => println!("updated  {path}"),
+            Outcome::AlreadyLinked => println!("skipped  {path} (up to date)"),
+            Outcome::Refused(why) => println!("refused  {path} ({why})"),
+        }
+    }
+    if let Some(id) = &report.backup {
+        println!("backup   {id} (undo with `dotfiles restore {id}`)");
+    }
+    Ok(if report.refused().next().is_some() {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+fn run_adopt(layout: &Layout, path: &Path) -> Result<ExitCode> {
+    let path = std::path::absolute(path).map_err(|e| Error::io(path, e))?;
+    let report = adopt::adopt(layout, &path)?;
+    for (path, outcome) in &report.results {
+        let path = path.display();
+        match outcome {
+            AdoptOutcome::Added => println!("adopted  {path}"),
+            AdoptOutcome::Updated => println!("adopted  {path} (repository copy overwritten)"),
+            AdoptOutcome::AlreadyManaged => println!("skipped  {path} (already managed)"),
+        }
+    }
+    if report.tracked {
+        println!("tracked  new entry in dotfiles.toml");
+    }
+    if let Some(id) = &report.backup {
+        println!("backup   {id} (originals as they were adopted)");
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_uninstall(layout: &Layout) -> Result<ExitCode> {
+    let report = uninstall::uninstall(layout)?;
+    for (path, outcome) in &report.results {
+        let path = path.display();
+        match outcome {
+            UninstallOutcome::Removed => println!("removed  {path}"),
+            UninstallOutcome::Restored(id) => println!("restored {path} (from {id})"),
+            UninstallOutcome::Missing { restored: Some(id) } => {
+                println!("restored {path} (from {id}; link was already gone)")
+            }
+            UninstallOutcome::Missing { restored: None } => {
+                println!("skipped  {path} (already gone)")
+            }
+            UninstallOutcome::LeftAlone(why) => {
+                eprintln!("warning: left {path} alone: {why}")
+            }
+        }
+    }
+    for id in &report.kept_backups {
+        eprintln!("warning: backup {id} still holds originals; see `dotfiles backups`");
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_status(layout: &Layout, show_diffs: bool) -> Result<ExitCode> {
+    let mut clean = true;
+    for file in status::status(layout)? {
+        clean &= file.state.is_clean();
+        let path = file.path.display();
+        let state = &file.state;
+        match state {
+            State::Linked | State::Missing => println!("{state:<9} {path}"),
+            State::Diverged { edited, diff } => {
+                let why = if *edited {
+                    "edited locally"
+                } else {
+                    "repository changed"
+                };
+                println!("{state:<9} {path} ({}, {why})", file.strategy);
+                if show_diffs {
+                    print!("{diff}");
+                }
+            }
+            State::Foreign(kind) => println!("{state:<9} {path} ({kind})"),
+            State::BrokenLink(target) => println!("{state:<9} {path} -> {}", target.display()),
+        }
+    }
+    Ok(if clean {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+fn run_secrets(layout: &Layout, command: SecretsCommand) -> Result<ExitCode> {
+    let store = SecretStore::new(layout);
+    let passphrase = secrets::passphrase(&store, !store.exists())?;
+    let mut secrets = store.open(&passphrase)?;
+    match command {
+        SecretsCommand::Set { name } => {
+            let value = if io::stdin().is_terminal() {
+                rpassword::prompt_password(format!("Value for {name}: "))
+                    .map_err(|e| Error::io("<tty>", e))?
+            } else {
+                let mut value =
+                    io::read_to_string(io::stdin()).map_err(|e| Error::io("<stdin>", e))?;
+                if value.ends_with('\n') {
+                    value.pop();
+                }
+                value
+            };
+            secrets.set(&name, value)?;
+            store.save(&secrets, &passphrase)?;
+        }
+        SecretsCommand::List => {
+            for name in secrets.names() {
+                println!("{name}");
+            }
+        }
+        SecretsCommand::Remove { name } => {
+            if !secrets.remove(&name) {
+                eprintln!("dotfiles: no secret named {name}");
+                return Ok(ExitCode::FAILURE);
+            }
+            store.save(&secrets, &passphrase)?;
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_bundles(layout: &Layout, command: BundlesCommand) -> Result<ExitCode> {
+    match command {
+        BundlesCommand::Status => {
+            let report = bundles::status(layout)?;
+            for bundle in &report.bundles {
+                let path = bundle.path.display();
+                let state = &bundle.state;
+                let mut notes = Vec::new();
+                match state {
+                    BundleState::Missing => notes.extend(bundle.url.clone()),
+                    BundleState::Uninitialized => {
+                        notes.push("run `git submodule update --init`".into())
+                    }
+                    BundleState::Unrecorded { head } => {
+                        notes.push(format!("at {}, no commit recorded", short(head)))
+                    }
+                    BundleState::Moved { recorded, head } => {
+                        notes.push(format!("at {}, recorded {}", short(head), short(recorded)))
+                    }
+                    BundleState::Current => {}
+                }
+                if bundle.dirty {
+                    notes.push("uncommitted changes".into());
+                }
+                if notes.is_empty() {
+                    println!("{state:<13} {path}");
+                } else {
+                    println!("{state:<13} {path} ({})", notes.join(", "));
+                }
+            }
+            for path in &report.vendored {
+                println!(
+                    "{:<13} {} (committed, not a submodule)",
+                    "vendored",
+                    path.display()
+                );
+            }
+            Ok(if report.is_clean() {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            })
+        }
+        BundlesCommand::Conflicts => {
+            let conflicts = conflicts::conflicts(layout)?;
+            for conflict in &conflicts {
+                println!("{conflict}");
+            }
+            Ok(if conflicts.is_empty() {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            })
+        }
+        BundlesCommand::Lock => {
+            let lockfile = lockfile::lock(layout)?;
+            println!("locked {} bundle(s) in {LOCKFILE}", lockfile.bundles.len());
+            Ok(ExitCode::SUCCESS)
+        }
+        BundlesCommand::Verify => {
+            let mut ok = true;
+            for (path, mismatch) in lockfile::verify(layout)? {
+                ok &= !mismatch.is_failure();
+                println!("{}: {mismatch}", path.display());
+            }
+            Ok(if ok {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            })
+        }
+        BundlesCommand::Vendor { cache } => {
+            for (path, commit) in vendor::vendor(layout, &cache)? {
+                println!("vendored {} at {}", path.display(), short(&commit));
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        BundlesCommand::Install { cache, offline } => {
+            for (path, outcome) in vendor::install(layout, cache.as_deref(), offline)? {
+                let path = path.display();
+                match outcome {
+                    VendorOutcome::Installed => println!("installed {path}"),
+                    VendorOutcome::Updated => println!("updated   {path}"),
+                    VendorOutcome::Current => println!("skipped   {path} (up to date)"),
+                }
+            }
+            install_helptags(layout);
+            Ok(ExitCode::SUCCESS)
+        }
+        BundlesCommand::LoadOrder => {
+            let show = |path: &Path| match path.strip_prefix(layout.repo()) {
+                Ok(relative) => relative.display().to_string(),
+                Err(_) => path.display().to_string(),
+            };
+            for section in loadorder::load_order(layout)? {
+                println!("{}", section.heading());
+                for file in &section.files {
+                    let mut line = format!("    {}", show(&file.path));
+                    if let Some(by) = &file.overridden_by {
+                        line += &format!(" (overridden by {})", show(by));
+                    }
+                    if let Some(base) = &file.extends {
+                        line += &format!(" (extends {})", show(base));
+                    }
+                    println!("{line}");
+                }
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        BundlesCommand::Helptags => Ok(if print_helptags(layout)? {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        }),
+        BundlesCommand::Update { jobs, yes } => run_update(layout, jobs, yes),
+        BundlesCommand::Disable { name, rename } => {
+            let how = if rename {
+                Mechanism::Renamed
+            } else {
+                Mechanism::Listed
+            };
+            if !bundles::disable(layout, &name, how)? {
+                println!("{name} is already disabled that way");
+            } else if rename {
+                println!("disabled {name} (renamed to {name}~)");
+            } else {
+                println!("disabled {name} (listed in g:pathogen_disabled)");
+            }
+            print_runtimepath(layout)
+        }
+        BundlesCommand::Enable { name } => {
+            if bundles::enable(layout, &name)? {
+                println!("enabled {name}");
+            } else {
+                println!("{name} is not disabled");
+            }
+            print_runtimepath(layout)
+        }
+        BundlesCommand::FixUrls { dry_run } => {
+            let fix = bundles::fix_urls(layout, dry_run)?;
+            if fix.changes.is_empty() {
+                println!("every submodule URL is already up to date");
+                return Ok(ExitCode::SUCCESS);
+            }
+            print!("{}", fix.diff);
+            if !dry_run {
+                println!(
+                    "rewrote {} URL(s); run `git submodule sync` to update existing checkouts",
+                    fix.changes.len()
+                );
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
+
+fn print_runtimepath(layout: &Layout) -> Result<ExitCode> {
+    println!("runtimepath:");
+    for dir in pathogen::resolve(layout)?.dirs() {
+        println!("    {dir}");
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_which_runtime(layout: &Layout, file: &str, complete: bool) -> Result<ExitCode> {
+    let rtp = pathogen::resolve(layout)?;
+    if complete {
+        for candidate in rtp.complete(file) {
+            println!("{candidate}");
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+    let found = rtp.find(file);
+    let Some((wins, shadowed)) = found.split_first() else {
+        return Err(Error::NotInRuntimepath(file.to_string()));
+    };
+    println!("wins      {}", wins.display());
+    for path in shadowed {
+        println!("shadowed  {}", path.display());
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// How many subjects to list for one bundle before eliding the rest.
+const MAX_SUBJECTS: usize = 20;
+
+fn run_update(layout: &Layout, jobs: usize, yes: bool) -> Result<ExitCode> {
+    let mut ok = true;
+    let mut accepted = Vec::new();
+    let mut answers = io::stdin().lock().lines();
+    for (path, update) in update::check(layout, jobs)? {
+        let path = path.display();
+        let update = match update {
+            Ok(Some(update)) => update,
+            Ok(None) => {
+                println!("skipped  {path} (up to date)");
+                continue;
+            }
+            Err(e) => {
+                ok = false;
+                println!("failed   {path} ({e})");
+                continue;
+            }
+        };
+        print_update(&update);
+        let accept = yes || {
+            print!("update {path}? [y/N] ");
+            io::stdout().flush().map_err(|e| Error::io("<stdout>", e))?;
+            let answer = answers.next().transpose();
+            let answer = answer.map_err(|e| Error::io("<stdin>", e))?;
+            matches!(answer.as_deref().map(str::trim), Some("y" | "Y" | "yes"))
+        };
+        if accept {
+            accepted.push(update);
+        } else {
+            println!("kept     {path} at {}", short(&update.from));
+        }
+    }
+    if !accepted.is_empty() {
+        update::apply(layout, &accepted)?;
+        for update in &accepted {
+            println!(
+                "updated  {} to {}",
+                update.path.display(),
+                short(&update.to)
+            );
+        }
+        println!("locked {} update(s) in {LOCKFILE}", accepted.len());
+    }
+    Ok(if ok {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+fn print_update(update: &Update) {
+    let mut notes = vec![format!("{} commit(s)", update.commits.len())];
+    if let Some(tag) = &update.tag {
+        notes.push(format!("tag {tag}"));
+    }
+    if update.breaking() > 0 {
+        notes.push(format!("{} breaking", update.breaking()));
+    }
+    if !update.fast_forward {
+        notes.push("not a fast-forward".into());
+    }
+    println!(
+        "{}: {}..{} ({})",
+        update.path.display(),
+        short(&update.from),
+        short(&update.to),
+        notes.join(", ")
+    );
+    for commit in update.commits.iter().take(MAX_SUBJECTS) {
+        let marker = if commit.breaking { "  [breaking]" } else { "" };
+        println!("    {} {}{marker}", short(&commit.id), commit.subject);
+    }
+    if update.commits.len() > MAX_SUBJECTS {
+        println!("    ... and {} more", update.commits.len() - MAX_SUBJECTS);
+    }
+}
+
+/// Regenerates the help tags after an install. Failing to is reported but
+/// does not fail the install.
+fn install_helptags(layout: &Layout) {
+    if let Err(e) = print_helptags(layout) {
+        eprintln!("dotfiles: helptags: {e}");
+    }
+}
+
+/// Returns whether every doc directory got its tags.
+fn print_helptags(layout: &Layout) -> Result<bool> {
+    let report = helptags::helptags(layout)?;
+    for path in &report.written {
+        println!("helptags {}", path.display());
+    }
+    for duplicate in &report.duplicates {
+        println!("duplicate {duplicate}");
+    }
+    for (doc, e) in &report.failed {
+        println!("failed   {} ({e})", doc.display());
+    }
+    Ok(report.failed.is_empty())
+}
+
+/// An abbreviated commit id, as git prints them.
+fn short(commit: &str) -> &str {
+    &commit[..commit.len().min(7)]
+}
+
+fn run_plugins(layout: &Layout, command: PluginsCommand) -> Result<ExitCode> {
+    match command {
+        PluginsCommand::Install { jobs } => {
+            let mut ok = true;
+            for (plugin, outcome) in plugins::install(layout, jobs)? {
+                let name = &plugin.name;
+                match outcome {
+                    Ok(PluginOutcome::Cloned) => println!("cloned   {name} ({})", plugin.pin),
+                    Ok(PluginOutcome::CheckedOut) => println!("checkout {name} ({})", plugin.pin),
+                    Ok(PluginOutcome::Current) => println!("skipped  {name} (up to date)"),
+                    Err(e) => {
+                        ok = false;
+                        println!("failed   {name} ({e})");
+                    }
+                }
+            }
+            install_helptags(layout);
+            Ok(if ok {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            })
+        }
+        PluginsCommand::Migrate => {
+            for plugin in plugins::migrate(layout)? {
+                println!("migrated {} ({})", plugin.path.display(), plugin.pin);
+            }
+            println!("removed  .gitmodules; commit dotfiles.toml, .gitignore and .gitmodules");
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
+
+fn run_lint(layout: &Layout) -> Result<ExitCode> {
+    let diagnostics = lint::lint(layout)?;
+    for diagnostic in &diagnostics {
+        println!("{diagnostic}");
+    }
+    Ok(if diagnostics.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+fn run_backups(layout: &Layout) -> Result<ExitCode> {
+    for manifest in BackupStore::new(layout).list()? {
+        println!("{}  {} file(s)", manifest.id, manifest.entries.len());
+        for entry in &manifest.entries {
+            println!("    {}", entry.path.display());
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_restore(layout: &Layout, id: &str) -> Result<ExitCode> {
+    for path in BackupStore::new(layout).restore(layout, id)? {
+        println!("restored {}", path.display());
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_resume(layout: &Layout) -> Result<ExitCode> {
+    let transaction = Transaction::load(layout)?;
+    println!(
+        "resuming: {} step(s) done, {} remaining",
+        transaction.completed(),
+        transaction.remaining()
+    );
+    if let Some(id) = transaction.commit()? {
+        println!("backup   {id} (undo with `dotfiles restore {id}`)");
+    }
+    println!("install complete");
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_rollback(layout: &Layout) -> Result<ExitCode> {
+    let transaction = Transaction::load(layout)?;
+    let undone = transaction.completed();
+    transaction.rollback()?;
+    println!("rolled back {undone} step(s)");
+    Ok(ExitCode::SUCCESS)
+}
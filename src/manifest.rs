@@ -0,0 +1,773 @@
+//! The optional `dotfiles.toml` at the repository root.
+//!
+//! ```toml
+//! # How files are installed unless an entry says otherwise.
+//! strategy = "symlink"
+//!
+//! # Whether dot-files at the repository root that no entry lists are
+//! # installed too. Defaults to true.
+//! implicit = false
+//!
+//! # Never installed, wherever they are.
+//! ignore = ["*.swp", "tags"]
+//!
+//! # Hosts that mount home over NFS want a real file here.
+//! [[entry]]
+//! path = ".gitconfig"
+//! strategy = "copy"
+//!
+//! [[entry]]
+//! path = "bin"
+//! dest = ".local/bin"
+//! os = ["linux"]
+//! hosts = ["build-*"]
+//! ignore = ["*.orig"]
+//!
+//! # Jump hosts get no Vim plugins and a tmux prefix that nests.
+//! [profile.jump]
+//! hosts = ["jump-*"]
+//! exclude = [".vim/bundle"]
+//! overlay = "profiles/jump"
+//!
+//! # How `bundles fix-urls` rewrites submodule URLs. These two are the
+//! # defaults when none are given.
+//! [[bundles.rewrite]]
+//! from = "git://github.com/"
+//! to = "https://github.com/"
+//!
+//! [[bundles.rewrite]]
+//! from = "git@github.com:"
+//! to = "https://github.com/"
+//!
+//! # Vim plugins, cloned into `.vim/bundle/<name>` by `plugins install`.
+//! [[plugin]]
+//! url = "https://github.com/tpope/vim-fugitive.git"
+//! tag = "v3.7"
+//!
+//! [[plugin]]
+//! url = "https://github.com/tpope/vim-pathogen.git"
+//! path = ".vim/vim-pathogen"
+//! ```
+//!
+//! An entry's path may name a directory, in which case it applies to every
+//! file below it. The most specific entry wins for `strategy` and `dest`,
+//! while `os` and `hosts` must be satisfied by every entry a file is under.
+//! Ignore patterns without a `/` match any single path component; patterns
+//! with one match the path from the root, or from the entry they belong to.
+//!
+//! A profile narrows the files to those matching its `include` patterns, if
+//! it has any, and drops those matching `exclude`; both match repository
+//! paths. Files in its `overlay` directory are installed as if they were at
+//! the root of the repository, in place of the base file with the same
+//! destination if there is one. The profile whose `hosts` match the
+//! hostname is picked unless one is chosen explicitly.
+//!
+//! A URL rewrite replaces the start of any submodule URL that begins with
+//! `from`; when several match, the longest `from` wins, as with git's
+//! `insteadOf`.
+//!
+//! A plugin's name defaults to the last part of its URL without `.git`, and
+//! `path` replaces `.vim/bundle/<name>` altogether. It follows the remote's
+//! default branch unless pinned with one of `branch`, `tag` or `commit`.
+//!
+//! Mistakes in the file are reported with the line and column they are on.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+
+use globset::{GlobBuilder, GlobMatcher};
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::bundles::BUNDLE_DIR;
+use crate::error::{Error, Result};
+use crate::fsutil::is_missing;
+use crate::host::{Host, KNOWN_OS};
+use crate::strategy::Strategy;
+
+pub const MANIFEST: &str = "dotfiles.toml";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    pub strategy: Strategy,
+    pub implicit: bool,
+    pub ignore: Vec<Pattern>,
+    pub entries: Vec<ManifestEntry>,
+    /// Sorted by name.
+    pub profiles: Vec<Profile>,
+    pub rewrites: Vec<Rewrite>,
+    pub plugins: Vec<Plugin>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plugin {
+    pub name: String,
+    /// Where it is cloned, relative to the repository root.
+    pub path: PathBuf,
+    pub url: String,
+    pub pin: Pin,
+}
+
+/// What a plugin's checkout follows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pin {
+    /// Whatever the remote's default branch is.
+    Default,
+    Branch(String),
+    Tag(String),
+    Commit(String),
+}
+
+impl fmt::Display for Pin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pin::Default => f.write_str("default branch"),
+            Pin::Branch(branch) => write!(f, "branch {branch}"),
+            Pin::Tag(tag) => write!(f, "tag {tag}"),
+            Pin::Commit(commit) => write!(f, "commit {commit}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    /// Relative to the repository root.
+    pub path: PathBuf,
+    /// Relative to home; the same as `path` if not given.
+    pub dest: Option<PathBuf>,
+    pub strategy: Option<Strategy>,
+    /// Operating systems the entry is installed on; any if empty.
+    pub os: Vec<String>,
+    /// Hostname patterns the entry is installed on; any if empty.
+    pub hosts: Vec<Pattern>,
+    /// Relative to `path`.
+    pub ignore: Vec<Pattern>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub name: String,
+    /// Hostname patterns that select this profile automatically.
+    pub hosts: Vec<Pattern>,
+    pub include: Vec<Pattern>,
+    pub exclude: Vec<Pattern>,
+    /// Relative to the repository root.
+    pub overlay: Option<PathBuf>,
+}
+
+impl Profile {
+    /// Whether a repository-relative file from the base configuration is
+    /// part of this profile.
+    pub fn selects(&self, rel: &Path) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|p| p.matches(rel)))
+            && !self.exclude.iter().any(|p| p.matches(rel))
+    }
+}
+
+/// Replaces the prefix `from` of a submodule URL with `to`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rewrite {
+    pub from: String,
+    pub to: String,
+}
+
+impl Rewrite {
+    fn new(from: &str, to: &str) -> Self {
+        Rewrite {
+            from: from.to_string(),
+            to: to.to_string(),
+        }
+    }
+
+    /// The URLs GitHub no longer serves, or that need a key.
+    pub fn defaults() -> Vec<Rewrite> {
+        vec![
+            Rewrite::new("git://github.com/", "https://github.com/"),
+            Rewrite::new("git@github.com:", "https://github.com/"),
+        ]
+    }
+}
+
+/// A shell-style glob in which `*` does not cross a `/`.
+#[derive(Clone)]
+pub struct Pattern {
+    text: String,
+    matcher: GlobMatcher,
+}
+
+impl Pattern {
+    pub fn new(text: &str) -> Result<Self, globset::Error> {
+        let glob = GlobBuilder::new(text).literal_separator(true).build()?;
+        Ok(Pattern {
+            text: text.to_string(),
+            matcher: glob.compile_matcher(),
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Whether `path` or anything it is inside of matches.
+    pub fn matches(&self, path: &Path) -> bool {
+        if self.text.contains('/') {
+            path.ancestors()
+                .take_while(|a| !a.as_os_str().is_empty())
+                .any(|a| self.matcher.is_match(a))
+        } else {
+            path.components()
+                .any(|c| self.matcher.is_match(c.as_os_str()))
+        }
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.text == other.text
+    }
+}
+
+impl Eq for Pattern {}
+
+impl fmt::Debug for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.text, f)
+    }
+}
+
+impl Default for Manifest {
+    fn default() -> Self {
+        Manifest {
+            strategy: Strategy::default(),
+            implicit: true,
+            ignore: Vec::new(),
+            entries: Vec::new(),
+            profiles: Vec::new(),
+            rewrites: Rewrite::defaults(),
+            plugins: Vec::new(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawManifest {
+    #[serde(default)]
+    strategy: Strategy,
+    #[serde(default = "yes")]
+    implicit: bool,
+    #[serde(default)]
+    ignore: Vec<Spanned<String>>,
+    #[serde(default, rename = "entry")]
+    entries: Vec<RawEntry>,
+    #[serde(default, rename = "profile")]
+    profiles: BTreeMap<String, RawProfile>,
+    #[serde(default)]
+    bundles: RawBundles,
+    #[serde(default, rename = "plugin")]
+    plugins: Vec<RawPlugin>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawPlugin {
+    url: Spanned<String>,
+    name: Option<Spanned<String>>,
+    path: Option<Spanned<PathBuf>>,
+    branch: Option<Spanned<String>>,
+    tag: Option<Spanned<String>>,
+    commit: Option<Spanned<String>>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawBundles {
+    rewrite: Option<Vec<RawRewrite>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRewrite {
+    from: Spanned<String>,
+    to: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawEntry {
+    path: Spanned<PathBuf>,
+    dest: Option<Spanned<PathBuf>>,
+    strategy: Option<Strategy>,
+    #[serde(default)]
+    os: Vec<Spanned<String>>,
+    #[serde(default)]
+    hosts: Vec<Spanned<String>>,
+    #[serde(default)]
+    ignore: Vec<Spanned<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawProfile {
+    #[serde(default)]
+    hosts: Vec<Spanned<String>>,
+    #[serde(default)]
+    include: Vec<Spanned<String>>,
+    #[serde(default)]
+    exclude: Vec<Spanned<String>>,
+    overlay: Option<Spanned<PathBuf>>,
+}
+
+fn yes() -> bool {
+    true
+}
+
+impl Manifest {
+    /// Reads `dotfiles.toml` from `repo`, or returns the defaults if there
+    /// is none.
+    pub fn load(repo: &Path) -> Result<Self> {
+        let path = repo.join(MANIFEST);
+        match fs::read_to_string(&path) {
+            Ok(text) => Checker {
+                repo,
+                file: &path,
+                text: &text,
+            }
+            .parse(),
+            Err(e) if is_missing(&e) => Ok(Manifest::default()),
+            Err(e) => Err(Error::io(&path, e)),
+        }
+    }
+
+    /// Appends an entry for `rel` to the manifest in `repo`, creating the
+    /// file if need be, unless an existing entry already covers it. The
+    /// file is appended to rather than rewritten so its comments and layout
+    /// survive; [`add_plugins`](Self::add_plugins) works the same way.
+    /// Returns whether an entry was added.
+    pub fn track(repo: &Path, rel: &Path) -> Result<bool> {
+        let manifest = Manifest::load(repo)?;
+        if manifest.entries.iter().any(|e| rel.starts_with(&e.path)) {
+            return Ok(false);
+        }
+        let quoted = toml::Value::String(rel.to_string_lossy().into_owned());
+        append(repo, &format!("[[entry]]\npath = {quoted}\n"))?;
+        Ok(true)
+    }
+
+    /// The entries `rel` is under, outermost first.
+    fn enclosing<'a>(&'a self, rel: &'a Path) -> impl Iterator<Item = &'a ManifestEntry> + 'a {
+        let mut entries: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|entry| rel.starts_with(&entry.path))
+            .collect();
+        entries.sort_by_key(|entry| entry.path.components().count());
+        entries.into_iter()
+    }
+
+    /// The strategy for a repository-relative file path.
+    pub fn strategy_for(&self, rel: &Path) -> Strategy {
+        self.enclosing(rel)
+            .filter_map(|entry| entry.strategy)
+            .last()
+            .unwrap_or(self.strategy)
+    }
+
+    /// Where a repository-relative file path goes, relative to home.
+    pub fn dest_for(&self, rel: &Path) -> PathBuf {
+        self.enclosing(rel)
+            .filter_map(|entry| {
+                let rest = rel.strip_prefix(&entry.path).ok()?;
+                Some(entry.dest.as_ref()?.join(rest))
+            })
+            .last()
+            .unwrap_or_else(|| rel.to_path_buf())
+    }
+
+    /// The repository-relative path that would be installed at `dest`, the
+    /// inverse of [`dest_for`](Self::dest_for).
+    pub fn source_for(&self, dest: &Path) -> PathBuf {
+        self.entries
+            .iter()
+            .filter_map(|entry| {
+                let rest = dest.strip_prefix(entry.dest.as_ref()?).ok()?;
+                Some((entry.path.components().count(), entry.path.join(rest)))
+            })
+            .max_by_key(|(depth, _)| *depth)
+            .map_or_else(|| dest.to_path_buf(), |(_, source)| source)
+    }
+
+    /// Whether every entry `rel` is under allows installing onto `host`.
+    pub fn applies_to(&self, rel: &Path, host: &Host) -> bool {
+        self.enclosing(rel).all(|entry| {
+            (entry.os.is_empty() || entry.os.contains(&host.os))
+                && (entry.hosts.is_empty()
+                    || entry.hosts.iter().any(|p| p.matches(Path::new(&host.name))))
+        })
+    }
+
+    /// The profile to install with on `host`: the one called `name` if that
+    /// is given, otherwise the one whose hosts match the hostname, if any.
+    pub fn select_profile(&self, host: &Host, name: Option<&str>) -> Result<Option<&Profile>> {
+        if let Some(name) = name {
+            return match self.profiles.iter().find(|p| p.name == name) {
+                Some(profile) => Ok(Some(profile)),
+                None => Err(Error::UnknownProfile(name.to_string())),
+            };
+        }
+        let hostname = Path::new(&host.name);
+        let matching: Vec<&Profile> = self
+            .profiles
+            .iter()
+            .filter(|p| p.hosts.iter().any(|h| h.matches(hostname)))
+            .collect();
+        match matching[..] {
+            [] => Ok(None),
+            [profile] => Ok(Some(profile)),
+            _ => Err(Error::AmbiguousProfile {
+                host: host.name.clone(),
+                profiles: matching.iter().map(|p| p.name.clone()).collect(),
+            }),
+        }
+    }
+
+    /// Appends a `[[plugin]]` table for each of `plugins` to the manifest
+    /// in `repo`, leaving out what the defaults would give anyway.
+    pub fn add_plugins(repo: &Path, plugins: &[Plugin]) -> Result<()> {
+        let quote = |s: &str| toml::Value::String(s.to_string()).to_string();
+        let blocks: Vec<String> = plugins
+            .iter()
+            .map(|plugin| {
+                let mut block = format!("[[plugin]]\nurl = {}\n", quote(&plugin.url));
+                let name = plugin_name(&plugin.url);
+                if plugin.path != Path::new(BUNDLE_DIR).join(&name) {
+                    let path = plugin.path.to_string_lossy();
+                    match plugin.path.parent() == Some(Path::new(BUNDLE_DIR)) {
+                        true => block += &format!("name = {}\n", quote(&plugin.name)),
+                        false => block += &format!("path = {}\n", quote(&path)),
+                    }
+                }
+                match &plugin.pin {
+                    Pin::Default => {}
+                    Pin::Branch(branch) => block += &format!("branch = {}\n", quote(branch)),
+                    Pin::Tag(tag) => block += &format!("tag = {}\n", quote(tag)),
+                    Pin::Commit(commit) => block += &format!("commit = {}\n", quote(commit)),
+                }
+                block
+            })
+            .collect();
+        append(repo, &blocks.join("\n"))
+    }
+
+    /// Moves the plugin at `path` in the manifest in `repo` on to `tag`.
+    /// Only the old tag's value is rewritten, so its key and the rest of
+    /// the file survive as they are.
+    pub fn retag_plugin(repo: &Path, path: &Path, tag: &str) -> Result<()> {
+        let manifest = Manifest::load(repo)?;
+        let file = repo.join(MANIFEST);
+        let mut text = fs::read_to_string(&file).map_err(|e| Error::io(&file, e))?;
+        let raw: RawManifest = toml::from_str(&text).map_err(|e| Error::toml(&file, e))?;
+        let span = manifest
+            .plugins
+            .iter()
+            .position(|p| p.path == path)
+            .and_then(|index| raw.plugins[index].tag.as_ref())
+            .map(Spanned::span)
+            .ok_or_else(|| Error::NotPinnedToTag(path.to_path_buf()))?;
+        let quoted = toml::Value::String(tag.to_string());
+        text.replace_range(span, &quoted.to_string());
+        fs::write(&file, text).map_err(|e| Error::io(&file, e))
+    }
+
+    /// `url` with the best-matching rewrite applied, if any applies.
+    pub fn rewrite_url(&self, url: &str) -> Option<String> {
+        self.rewrites
+            .iter()
+            .filter(|r| url.starts_with(&r.from))
+            .max_by_key(|r| r.from.len())
+            .map(|r| format!("{}{}", r.to, &url[r.from.len()..]))
+    }
+
+    /// Whether a repository-relative path is inside some profile's overlay
+    /// directory, and so not part of the base configuration.
+    pub fn is_overlay(&self, rel: &Path) -> bool {
+        self.profiles
+            .iter()
+            .filter_map(|p| p.overlay.as_ref())
+            .any(|overlay| rel.starts_with(overlay))
+    }
+
+    /// Whether a repository-relative path is excluded by an ignore pattern.
+    pub fn is_ignored(&self, rel: &Path) -> bool {
+        if self.ignore.iter().any(|p| p.matches(rel)) {
+            return true;
+        }
+        self.enclosing(rel).any(|entry| {
+            let inner = rel.strip_prefix(&entry.path).unwrap_or(rel);
+            !inner.as_os_str().is_empty() && entry.ignore.iter().any(|p| p.matches(inner))
+        })
+    }
+}
+
+/// Appends `block` to the manifest in `repo`, after a blank line, creating
+/// the file if need be.
+fn append(repo: &Path, block: &str) -> Result<()> {
+    let path = repo.join(MANIFEST);
+    let mut text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if is_missing(&e) => String::new(),
+        Err(e) => return Err(Error::io(&path, e)),
+    };
+    if !text.is_empty() {
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        text.push('\n');
+    }
+    text.push_str(block);
+    fs::write(&path, text).map_err(|e| Error::io(&path, e))
+}
+
+fn is_commit_id(s: &str) -> bool {
+    (7..=40).contains(&s.len()) && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// The name a plugin gets from its URL: the last part, without `.git`.
+pub fn plugin_name(url: &str) -> String {
+    let last = url
+        .trim_end_matches('/')
+        .rsplit(['/', ':'])
+        .next()
+        .unwrap_or(url);
+    last.strip_suffix(".git").unwrap_or(last).to_string()
+}
+
+/// Parses and validates the manifest, turning byte offsets into lines and
+/// columns for error messages.
+struct Checker<'a> {
+    repo: &'a Path,
+    file: &'a Path,
+    text: &'a str,
+}
+
+impl Checker<'_> {
+    fn parse(&self) -> Result<Manifest> {
+        let raw: RawManifest = toml::from_str(self.text).map_err(|e| match e.span() {
+            Some(span) => self.error(span, e.message()),
+            None => Error::toml(self.file, e),
+        })?;
+
+        let mut seen: BTreeMap<PathBuf, Range<usize>> = BTreeMap::new();
+        let mut entries = Vec::new();
+        for entry in raw.entries {
+            let span = entry.path.span();
+            let path = self.relative(entry.path, "path")?;
+            if !self.repo.join(&path).exists() {
+                return Err(self.error(span, "no such file or directory in the repository"));
+            }
+            if let Some(first) = seen.get(&path) {
+                let (line, _) = self.line_col(first.start);
+                let message = format!("{} is already listed on line {line}", path.display());
+                return Err(self.error(span, &message));
+            }
+            seen.insert(path.clone(), span);
+
+            let dest = entry
+                .dest
+                .map(|dest| self.relative(dest, "dest"))
+                .transpose()?;
+            let os = entry
+                .os
+                .into_iter()
+                .map(|os| self.os(os))
+                .collect::<Result<_>>()?;
+            entries.push(ManifestEntry {
+                path,
+                dest,
+                strategy: entry.strategy,
+                os,
+                hosts: self.patterns(entry.hosts)?,
+                ignore: self.patterns(entry.ignore)?,
+            });
+        }
+
+        let mut profiles = Vec::new();
+        for (name, profile) in raw.profiles {
+            let overlay = match profile.overlay {
+                Some(overlay) => {
+                    let span = overlay.span();
+                    let overlay = self.relative(overlay, "overlay")?;
+                    if !self.repo.join(&overlay).is_dir() {
+                        return Err(self.error(span, "no such directory in the repository"));
+                    }
+                    Some(overlay)
+                }
+                None => None,
+            };
+            profiles.push(Profile {
+                name,
+                hosts: self.patterns(profile.hosts)?,
+                include: self.patterns(profile.include)?,
+                exclude: self.patterns(profile.exclude)?,
+                overlay,
+            });
+        }
+
+        let rewrites = match raw.bundles.rewrite {
+            Some(rewrites) => rewrites
+                .into_iter()
+                .map(|r| {
+                    if r.from.get_ref().is_empty() {
+                        return Err(self.error(r.from.span(), "from must not be empty"));
+                    }
+                    Ok(Rewrite::new(r.from.get_ref(), &r.to))
+                })
+                .collect::<Result<_>>()?,
+            None => Rewrite::defaults(),
+        };
+
+        let mut plugins: Vec<Plugin> = Vec::new();
+        let mut seen: BTreeMap<PathBuf, Range<usize>> = BTreeMap::new();
+        for raw in raw.plugins {
+            let span = raw.url.span();
+            let plugin = self.plugin(raw)?;
+            if let Some(first) = seen.get(&plugin.path) {
+                let (line, _) = self.line_col(first.start);
+                let message = format!(
+                    "{} is already cloned by the plugin on line {line}",
+                    plugin.path.display()
+                );
+                return Err(self.error(span, &message));
+            }
+            seen.insert(plugin.path.clone(), span);
+            plugins.push(plugin);
+        }
+
+        Ok(Manifest {
+            strategy: raw.strategy,
+            implicit: raw.implicit,
+            ignore: self.patterns(raw.ignore)?,
+            entries,
+            profiles,
+            rewrites,
+            plugins,
+        })
+    }
+
+    fn plugin(&self, raw: RawPlugin) -> Result<Plugin> {
+        let url = raw.url.into_inner();
+        let (name, path) = match (raw.name, raw.path) {
+            (Some(name), Some(path)) => {
+                let span = name.span().start..path.span().end;
+                return Err(self.error(span, "give a plugin either a name or a path, not both"));
+            }
+            (None, Some(path)) => {
+                let path = self.relative(path, "path")?;
+                let name = path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned();
+                (name, path)
+            }
+            (name, None) => {
+                let span = name.as_ref().map(Spanned::span);
+                let name = name.map_or_else(|| plugin_name(&url), Spanned::into_inner);
+                let ok = !name.is_empty()
+                    && Path::new(&name).components().count() == 1
+                    && !name.starts_with('.');
+                if !ok {
+                    let message =
+                        format!("{name:?} is not a valid plugin name; give one with `name`");
+                    return Err(self.error(span.unwrap_or(0..0), &message));
+                }
+                let path = Path::new(BUNDLE_DIR).join(&name);
+                (name, path)
+            }
+        };
+        let pins: Vec<(Range<usize>, Pin)> = [
+            raw.branch.map(|b| (b.span(), Pin::Branch(b.into_inner()))),
+            raw.tag.map(|t| (t.span(), Pin::Tag(t.into_inner()))),
+            raw.commit.map(|c| (c.span(), Pin::Commit(c.into_inner()))),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        let pin = match &pins[..] {
+            [] => Pin::Default,
+            [(span, Pin::Commit(commit))] if !is_commit_id(commit) => {
+                return Err(self.error(span.clone(), "commit must be 7 to 40 hexadecimal digits"));
+            }
+            [(_, pin)] => pin.clone(),
+            [_, (span, _), ..] => {
+                return Err(self.error(
+                    span.clone(),
+                    "pin a plugin with only one of branch, tag and commit",
+                ));
+            }
+        };
+        Ok(Plugin {
+            name,
+            path,
+            url,
+            pin,
+        })
+    }
+
+    /// A path that stays inside the directory it is relative to.
+    fn relative(&self, path: Spanned<PathBuf>, key: &str) -> Result<PathBuf> {
+        let span = path.span();
+        let path = path.into_inner();
+        let ok = path.components().next().is_some()
+            && path.components().all(|c| matches!(c, Component::Normal(_)));
+        if !ok {
+            let message = format!("{key} must be a relative path without `.` or `..`");
+            return Err(self.error(span, &message));
+        }
+        Ok(path)
+    }
+
+    fn os(&self, os: Spanned<String>) -> Result<String> {
+        if KNOWN_OS.contains(&os.get_ref().as_str()) {
+            return Ok(os.into_inner());
+        }
+        let message = format!(
+            "unknown operating system {:?}; expected one of {}",
+            os.get_ref(),
+            KNOWN_OS.join(", ")
+        );
+        Err(self.error(os.span(), &message))
+    }
+
+    fn patterns(&self, patterns: Vec<Spanned<String>>) -> Result<Vec<Pattern>> {
+        patterns
+            .into_iter()
+            .map(|p| {
+                Pattern::new(p.get_ref())
+                    .map_err(|e| self.error(p.span(), &format!("bad pattern: {}", e.kind())))
+            })
+            .collect()
+    }
+
+    fn error(&self, span: Range<usize>, message: &str) -> Error {
+        let (line, column) = self.line_col(span.start);
+        Error::Manifest {
+            path: self.file.to_path_buf(),
+            line,
+            column,
+            message: message.to_string(),
+        }
+    }
+
+    /// One-based line and column of a byte offset, counting columns in
+    /// characters.
+    fn line_col(&self, offset: usize) -> (usize, usize) {
+        let before = &self.text[..offset.min(self.text.len())];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        (line, before[line_start..].chars().count() + 1)
+    }
+}
@@ -0,0 +1,435 @@
+//! The runtimepath pathogen computes, without running Vim.
+//!
+//! This follows `.vim/autoload/pathogen.vim` step for step:
+//! [`Runtimepath::append_all_bundles`] is `pathogen#runtime_append_all_bundles`,
+//! [`Runtimepath::prepend_subdirectories`] is
+//! `pathogen#runtime_prepend_subdirectories`, and [`split`], [`join`],
+//! [`uniq`] and [`is_disabled`] are their namesakes. Directories are listed
+//! the way Vim's `glob()` lists them on Unix: sorted, without dot files,
+//! and with names ending in one of the default `'suffixes'` moved last.
+//!
+//! Vim would expand `~` and environment variables in the directories it is
+//! given; here they are taken as they are.
+//!
+//! [`resolve`] puts it together for the repository: the runtimepath Vim
+//! ends up with once `.vimrc` has run.
+//!
+//! [`Runtimepath::find`] and [`Runtimepath::complete`] are what `:Vedit`
+//! and its completion do with it: `pathogen#runtime_findfile` and
+//! `s:Findcomplete`.
+
+use std::collections::{BTreeSet, HashSet};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use globset::{Glob, GlobMatcher};
+
+use crate::error::Result;
+use crate::layout::Layout;
+use crate::vimrc::Vimrc;
+
+/// Where Vim looks for its own files unless `$VIM` says otherwise.
+const VIM: &str = "/usr/share/vim";
+
+/// Vim's default `'suffixes'`.
+const SUFFIXES: &[&str] = &[".bak", "~", ".o", ".h", ".info", ".swp", ".obj"];
+
+/// The `'runtimepath'` option, along with the state pathogen keeps between
+/// calls.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Runtimepath {
+    /// The option's value, escaped as Vim stores it.
+    option: String,
+    /// `g:pathogen_disabled`: names of bundles to leave out.
+    pub disabled: Vec<String>,
+    /// The names `append_all_bundles` has already been called with.
+    done_bundles: Vec<String>,
+}
+
+impl Runtimepath {
+    /// A runtimepath set to `option`, as in `:set runtimepath=...`.
+    pub fn new(option: &str) -> Self {
+        Runtimepath {
+            option: option.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Vim's default runtimepath on Unix, for a user whose vim files are
+    /// in `vimfiles` (normally `~/.vim`), with `$VIM` at `vim` and
+    /// `$VIMRUNTIME` at `vimruntime`.
+    pub fn vim_default(vimfiles: &Path, vim: &Path, vimruntime: &Path) -> Self {
+        let vimfiles = vimfiles.to_string_lossy();
+        let vim = vim.to_string_lossy();
+        let dirs = [
+            vimfiles.to_string(),
+            format!("{vim}/vimfiles"),
+            vimruntime.to_string_lossy().into_owned(),
+            format!("{vim}/vimfiles/after"),
+            format!("{vimfiles}/after"),
+        ];
+        Runtimepath::new(&join(&dirs))
+    }
+
+    /// The option's value.
+    pub fn option(&self) -> &str {
+        &self.option
+    }
+
+    /// The directories on the runtimepath, in order.
+    pub fn dirs(&self) -> Vec<String> {
+        split(&self.option)
+    }
+
+    /// `pathogen#infect`: bundles from the directory `source` if it is a
+    /// path, and otherwise from the directory of that name in every
+    /// runtimepath entry. Vim's `.vimrc` calls it with `"bundle"`.
+    pub fn infect(&mut self, source: &str) {
+        if source.contains(['\\', '/']) {
+            self.prepend_subdirectories(source);
+        } else {
+            self.append_all_bundles(source);
+        }
+    }
+
+    /// `pathogen#runtime_prepend_subdirectories`: puts every directory in
+    /// `path` at the front of the runtimepath and their `after`
+    /// directories at the back, dropping any entries already under `path`.
+    pub fn prepend_subdirectories(&mut self, path: &str) {
+        let before: Vec<String> = glob_directories(path, Pattern::Any, false)
+            .into_iter()
+            .filter(|dir| !is_disabled(dir, &self.disabled))
+            .collect();
+        let after: Vec<String> = glob_directories(path, Pattern::Any, true)
+            .into_iter()
+            .filter(|dir| !is_disabled(strip_after(dir), &self.disabled))
+            .collect();
+        let mut rtp = split(&self.option);
+        // `v:val[0:strlen(path)-1] !=# path`, which for an empty `path`
+        // compares the whole entry.
+        rtp.retain(|dir| match path.len() {
+            0 => !dir.is_empty(),
+            n => dir.as_bytes().get(..n).unwrap_or(dir.as_bytes()) != path.as_bytes(),
+        });
+        let mut list = before;
+        list.extend(rtp);
+        list.extend(after);
+        uniq(&mut list);
+        self.option = join(&list);
+    }
+
+    /// `pathogen#runtime_append_all_bundles`: puts the directories in
+    /// `<entry>/<name>` right after each runtimepath entry, and for an
+    /// `after` entry, their `after` directories right before it. Returns
+    /// false, changing nothing, if it was called with `name` before.
+    pub fn append_all_bundles(&mut self, name: &str) -> bool {
+        if self.done_bundles.iter().any(|done| done == name) {
+            return false;
+        }
+        self.done_bundles.push(name.to_string());
+        let mut list = Vec::new();
+        for dir in split(&self.option) {
+            if ends_with_after_word(&dir) {
+                let base = format!("{}{name}", &dir[..dir.len() - "after".len()]);
+                list.extend(
+                    glob_directories(&base, Pattern::NoTilde, true)
+                        .into_iter()
+                        .filter(|d| !is_disabled(strip_after(d), &self.disabled)),
+                );
+                list.push(dir);
+            } else {
+                let bundles = glob_directories(&format!("{dir}/{name}"), Pattern::NoTilde, false);
+                list.push(dir);
+                list.extend(
+                    bundles
+                        .into_iter()
+                        .filter(|d| !is_disabled(d, &self.disabled)),
+                );
+            }
+        }
+        uniq(&mut list);
+        self.option = join(&list);
+        true
+    }
+
+    /// Every copy of the runtime file `file`, such as `syntax/nginx.vim`,
+    /// in runtimepath order: the first is the one `:Vedit` opens and
+    /// `:runtime` sources, the rest are shadowed by it. Like `findfile()`,
+    /// this only finds files, and finds each one once however many entries
+    /// lead to it.
+    pub fn find(&self, file: &str) -> Vec<PathBuf> {
+        let mut seen = HashSet::new();
+        self.dirs()
+            .into_iter()
+            .map(|dir| Path::new(&dir).join(file))
+            .filter(|path| path.is_file())
+            .filter(|path| seen.insert(fs::canonicalize(path).unwrap_or_else(|_| path.clone())))
+            .collect()
+    }
+
+    /// What `:Vedit` completes `arg` to: every runtime-relative path that
+    /// starts with it, segment by segment, with a `/` after directories.
+    /// A leading `a/`, `d/`, `f/`, `i/`, `p/` or `s/` is short for
+    /// `autoload/`, `doc/`, `ftplugin/`, `indent/`, `plugin/` or `syntax/`.
+    pub fn complete(&self, arg: &str) -> Vec<String> {
+        let request = match arg.as_bytes() {
+            [c, b'/' | b'\\', ..] => match CHEATS.iter().find(|(k, _)| k == c) {
+                Some((_, dir)) => format!("{dir}{}", &arg[1..]),
+                None => arg.to_string(),
+            },
+            _ => arg.to_string(),
+        };
+        let pattern = format!("{}*", request.replace('/', "*/"));
+        let segments: Vec<&str> = pattern.split('/').collect();
+        let mut found = BTreeSet::new();
+        for dir in self.dirs() {
+            glob_relative(Path::new(&dir), "", &segments, &mut found);
+        }
+        found.into_iter().collect()
+    }
+}
+
+/// `s:Findcomplete`'s abbreviations.
+const CHEATS: [(u8, &str); 6] = [
+    (b'a', "autoload"),
+    (b'd', "doc"),
+    (b'f', "ftplugin"),
+    (b'i', "indent"),
+    (b'p', "plugin"),
+    (b's', "syntax"),
+];
+
+/// Adds to `found` the paths below `dir`, relative to it after `prefix`,
+/// whose segments match `segments` the way `glob()` matches them: without
+/// dot files unless the segment asks for them, and directories marked
+/// with a trailing `/`.
+fn glob_relative(dir: &Path, prefix: &str, segments: &[&str], found: &mut BTreeSet<String>) {
+    let Some((segment, rest)) = segments.split_first() else {
+        return;
+    };
+    let Some(matcher) = segment_matcher(segment) else {
+        return;
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+        if (name.starts_with('.') && !segment.starts_with('.')) || !matcher.is_match(&name) {
+            continue;
+        }
+        let path = entry.path();
+        let relative = format!("{prefix}{name}");
+        if !rest.is_empty() {
+            if path.is_dir() {
+                glob_relative(&path, &format!("{relative}/"), rest, found);
+            }
+        } else if path.is_dir() {
+            found.insert(format!("{relative}/"));
+        } else {
+            found.insert(relative);
+        }
+    }
+}
+
+fn segment_matcher(segment: &str) -> Option<GlobMatcher> {
+    Glob::new(segment).ok().map(|glob| glob.compile_matcher())
+}
+
+/// The runtimepath after `.vimrc` has called `pathogen#infect()`, with the
+/// bundles it disables left out and the repository's `.vim` standing in
+/// for `~/.vim`. `$VIM` and `$VIMRUNTIME` come from the environment if set;
+/// otherwise `$VIMRUNTIME` is the newest `vimNN` directory in `$VIM`.
+pub fn resolve(layout: &Layout) -> Result<Runtimepath> {
+    let repo = layout.repo();
+    let vimrc = Vimrc::load(repo)?;
+    let vim = env::var_os("VIM").map_or_else(|| PathBuf::from(VIM), PathBuf::from);
+    let vimruntime = match env::var_os("VIMRUNTIME") {
+        Some(dir) => PathBuf::from(dir),
+        None => newest_runtime(&vim),
+    };
+    let mut rtp = Runtimepath::vim_default(&repo.join(".vim"), &vim, &vimruntime);
+    rtp.disabled = vimrc.disabled().to_vec();
+    if let Some(source) = vimrc.infect_source() {
+        rtp.infect(source);
+    }
+    Ok(rtp)
+}
+
+/// `vim/vim91` over `vim/vim90`, or `vim/runtime` if there are none.
+fn newest_runtime(vim: &Path) -> PathBuf {
+    let version = |name: &str| -> Option<u32> { name.strip_prefix("vim")?.parse().ok() };
+    let newest = fs::read_dir(vim)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| version(name).is_some())
+        .max_by_key(|name| version(name));
+    vim.join(newest.as_deref().unwrap_or("runtime"))
+}
+
+impl fmt::Display for Runtimepath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.option)
+    }
+}
+
+/// `dir =~# '\<after$'`: `after` at the end, as a word of its own.
+fn ends_with_after_word(dir: &str) -> bool {
+    let Some(rest) = dir.strip_suffix("after") else {
+        return false;
+    };
+    // Vim's default 'iskeyword' is letters, digits, `_` and everything
+    // from 192 up.
+    !rest
+        .chars()
+        .next_back()
+        .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_' || c as u32 >= 192)
+}
+
+/// `v:val[0:-7]`: the path without its trailing `/after`.
+fn strip_after(dir: &str) -> &str {
+    &dir[..dir.len().saturating_sub("/after".len())]
+}
+
+/// `pathogen#split`: the entries of a comma-separated option, with `\,`
+/// and `\\` unescaped. Empty entries are kept, except before the first
+/// non-empty one and after a trailing comma.
+pub fn split(path: &str) -> Vec<String> {
+    // Every comma not escaped by an odd number of backslashes, as with
+    // `split(path, '\\\@<!\%(\\\\\)*\zs,')`.
+    let mut commas = Vec::new();
+    let mut backslashes = 0;
+    for (i, c) in path.char_indices() {
+        match c {
+            '\\' => backslashes += 1,
+            ',' if backslashes % 2 == 0 => commas.push(i),
+            _ => {}
+        }
+        if c != '\\' {
+            backslashes = 0;
+        }
+    }
+    // Vim's `split()` drops empty items until it has a non-empty one, and
+    // the one after a trailing comma.
+    let mut items = Vec::new();
+    let mut start = 0;
+    for comma in commas {
+        if comma > start || !items.is_empty() {
+            items.push(&path[start..comma]);
+        }
+        start = comma + 1;
+    }
+    if start < path.len() {
+        items.push(&path[start..]);
+    }
+    items.into_iter().map(unescape).collect()
+}
+
+/// `substitute(item, '\\\([\\,]\)', '\1', 'g')`.
+fn unescape(item: &str) -> String {
+    let mut out = String::with_capacity(item.len());
+    let mut chars = item.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some(&next @ ('\\' | ','))) => {
+                out.push(next);
+                chars.next();
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// `pathogen#join`: the entries joined with commas, with commas escaped,
+/// and backslashes before a backslash or a comma.
+pub fn join(list: &[String]) -> String {
+    join_escaping(list, "")
+}
+
+/// `pathogen#legacyjoin`: [`join`] that escapes spaces too, for options
+/// such as `'path'` and `'tags'`.
+pub fn legacy_join(list: &[String]) -> String {
+    join_escaping(list, " ")
+}
+
+fn join_escaping(list: &[String], space: &str) -> String {
+    let special = |c: char| c == ',' || space.contains(c);
+    let mut path = String::new();
+    for item in list {
+        path.push(',');
+        let mut chars = item.chars().peekable();
+        while let Some(c) = chars.next() {
+            let escape =
+                special(c) || (c == '\\' && chars.peek().is_some_and(|&n| n == '\\' || special(n)));
+            if escape {
+                path.push('\\');
+            }
+            path.push(c);
+        }
+    }
+    path.strip_prefix(',').unwrap_or(&path).to_string()
+}
+
+/// `pathogen#uniq`: drops every entry after the first of its kind.
+pub fn uniq(list: &mut Vec<String>) {
+    let mut seen = HashSet::new();
+    list.retain(|item| seen.insert(item.clone()));
+}
+
+/// `pathogen#is_disabled`: whether the bundle at `path` ends in a tilde or
+/// is named in `disabled`.
+pub fn is_disabled(path: &str, disabled: &[String]) -> bool {
+    if path.ends_with('~') {
+        return true;
+    }
+    let name = path.rsplit('/').next().unwrap_or(path);
+    disabled.iter().any(|d| d == name)
+}
+
+#[derive(Clone, Copy)]
+enum Pattern {
+    /// `*`
+    Any,
+    /// `*[^~]`
+    NoTilde,
+}
+
+/// `pathogen#glob_directories(parent/pattern)`, or with `after`,
+/// `pathogen#glob_directories(parent/pattern/after)`.
+fn glob_directories(parent: &str, pattern: Pattern, after: bool) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(parent) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| {
+            !name.starts_with('.')
+                && match pattern {
+                    Pattern::Any => true,
+                    Pattern::NoTilde => !name.ends_with('~'),
+                }
+        })
+        .collect();
+    // Vim sorts on the whole path with separators first, which for names
+    // under one directory, with or without `/after`, is their byte order.
+    names.sort();
+    let (mut paths, suffixed): (Vec<String>, Vec<String>) = names
+        .into_iter()
+        .map(|name| match after {
+            false => format!("{parent}/{name}"),
+            true => format!("{parent}/{name}/after"),
+        })
+        .filter(|path| Path::new(path).is_dir())
+        // `glob()` moves names ending in one of 'suffixes' last.
+        .partition(|path| !SUFFIXES.iter().any(|s| path.ends_with(s)));
+    paths.extend(suffixed);
+    paths
+}
@@ -0,0 +1,287 @@
+//! Deciding what an install will do before anything is touched.
+//!
+//! Planning only reads the filesystem. The resulting [`Plan`] is turned into
+//! a flat list of [`Op`]s that a [`Transaction`](crate::transaction) applies
+//! and, if anything goes wrong, undoes.
+//!
+//! A plan can be printed as a diff-like table for people or serialized as
+//! JSON for scripts, and a saved plan can be applied later as long as the
+//! home directory has not changed in the meantime.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::discover::{discover, Entry};
+use crate::error::{Error, Result};
+use crate::fsutil::{blocking_parent, is_missing, FileKind};
+use crate::installed::Installed;
+use crate::layout::Layout;
+use crate::strategy::{inspect, Found, Strategy};
+use crate::template::{render_file, Values};
+use crate::transaction::Op;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum Action {
+    /// Nothing is there yet; install the file.
+    Create,
+    /// A file or foreign symlink is there; back it up, then install.
+    Replace { existing: FileKind },
+    /// Something we installed earlier is out of date or was installed with
+    /// another strategy; swap it for a fresh one without a backup.
+    Update,
+    /// The destination already is exactly what we would install.
+    Skip,
+    /// Something is in the way that cannot be backed up.
+    Refuse(Refusal),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Refusal {
+    /// The destination is a directory or something else we cannot back up.
+    Exists(FileKind),
+    /// A parent of the destination exists but is not a directory.
+    ParentNotDir(PathBuf),
+    /// A copy we installed has been edited since; overwriting it would
+    /// lose those edits.
+    LocallyModified,
+    /// A template cannot be rendered, usually because a value it uses is
+    /// not set.
+    Unrenderable { line: usize, message: String },
+}
+
+impl fmt::Display for Refusal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Refusal::Exists(kind) => write!(f, "a {kind} already exists"),
+            Refusal::ParentNotDir(p) => write!(f, "{} is not a directory", p.display()),
+            Refusal::LocallyModified => f.write_str("it has local edits"),
+            Refusal::Unrenderable { line, message } => {
+                write!(f, "the template cannot be rendered: line {line}: {message}")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Item {
+    #[serde(flatten)]
+    pub entry: Entry,
+    #[serde(flatten)]
+    pub action: Action,
+    /// Directories below home that must be created first, outermost first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub create_dirs: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Plan {
+    pub repo: PathBuf,
+    pub home: PathBuf,
+    pub items: Vec<Item>,
+}
+
+impl Plan {
+    /// Checks that this plan was made for `layout` and that planning again
+    /// now, with `values`, would produce exactly the same thing.
+    pub fn validate(&self, layout: &Layout, values: &Values) -> Result<()> {
+        if self.repo != layout.repo() || self.home != layout.home() {
+            return Err(Error::PlanMismatch {
+                repo: self.repo.clone(),
+                home: self.home.clone(),
+            });
+        }
+        let current = plan_with(layout, values)?;
+        let mut changed: Vec<PathBuf> = self
+            .items
+            .iter()
+            .filter(|item| !current.items.contains(item))
+            .chain(
+                current
+                    .items
+                    .iter()
+                    .filter(|item| !self.items.contains(item)),
+            )
+            .map(|item| item.entry.path.clone())
+            .collect();
+        changed.sort();
+        changed.dedup();
+        if changed.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::StalePlan(changed))
+        }
+    }
+
+    /// The filesystem operations that carry the plan out, in order.
+    pub fn operations(&self, layout: &Layout) -> Vec<Op> {
+        let mut ops = Vec::new();
+        for item in &self.items {
+            let path = &item.entry.path;
+            ops.extend(
+                item.create_dirs
+                    .iter()
+                    .map(|dir| Op::MkDir { path: dir.clone() }),
+            );
+            match item.action {
+                Action::Create => {}
+                Action::Replace { .. } => ops.push(Op::Backup { path: path.clone() }),
+                Action::Update => ops.push(Op::Discard { path: path.clone() }),
+                Action::Skip | Action::Refuse(_) => continue,
+            }
+            ops.push(Op::Install {
+                path: path.clone(),
+                source: layout.source(&item.entry.source),
+                strategy: item.entry.strategy,
+            });
+        }
+        ops
+    }
+}
+
+/// Works out what installing every discovered dotfile would do.
+pub fn plan(layout: &Layout) -> Result<Plan> {
+    plan_with(layout, &Values::load(layout)?)
+}
+
+/// [`plan`], rendering templates with `values`.
+pub fn plan_with(layout: &Layout, values: &Values) -> Result<Plan> {
+    let mut plan = Plan {
+        repo: layout.repo().to_path_buf(),
+        home: layout.home().to_path_buf(),
+        items: Vec::new(),
+    };
+    let installed = Installed::load(layout)?;
+    let mut scheduled = BTreeSet::new();
+    for entry in discover(layout)? {
+        let action = action_for(layout, &entry, &installed, values)?;
+        let create_dirs = match action {
+            Action::Create => missing_parents(layout, &entry.path, &mut scheduled)?,
+            _ => Vec::new(),
+        };
+        plan.items.push(Item {
+            entry,
+            action,
+            create_dirs,
+        });
+    }
+    Ok(plan)
+}
+
+fn action_for(
+    layout: &Layout,
+    entry: &Entry,
+    installed: &Installed,
+    values: &Values,
+) -> Result<Action> {
+    let source = layout.source(&entry.source);
+    let dest = layout.dest(&entry.path);
+    let recorded = installed.files.get(&entry.path);
+    if entry.strategy == Strategy::Template {
+        // Catch missing values now rather than half-way through the
+        // install, and hold back only the templates that need them.
+        match render_file(&source, values) {
+            Err(Error::Template { line, message, .. }) => {
+                return Ok(Action::Refuse(Refusal::Unrenderable { line, message }))
+            }
+            rendered => drop(rendered?),
+        }
+    }
+
+    Ok(
+        match inspect(&dest, &source, entry.strategy, recorded, values)? {
+            Found::Missing => match blocking_parent(layout.home(), &dest)? {
+                Some(blocker) => Action::Refuse(Refusal::ParentNotDir(blocker)),
+                None => Action::Create,
+            },
+            Found::Current => Action::Skip,
+            Found::Stale => Action::Update,
+            Found::Modified => Action::Refuse(Refusal::LocallyModified),
+            Found::Foreign(existing @ (FileKind::File | FileKind::Symlink)) => {
+                Action::Replace { existing }
+            }
+            Found::Foreign(kind) => Action::Refuse(Refusal::Exists(kind)),
+        },
+    )
+}
+
+/// The ancestors of `rel` that do not exist yet and that no earlier item
+/// has already scheduled for creation.
+fn missing_parents(
+    layout: &Layout,
+    rel: &Path,
+    scheduled: &mut BTreeSet<PathBuf>,
+) -> Result<Vec<PathBuf>> {
+    let mut missing = Vec::new();
+    let Some(parent) = rel.parent() else {
+        return Ok(missing);
+    };
+    let mut current = PathBuf::new();
+    for component in parent.components() {
+        current.push(component);
+        if scheduled.contains(&current) {
+            continue;
+        }
+        let full = layout.dest(&current);
+        match fs::symlink_metadata(&full) {
+            Ok(_) => {}
+            Err(e) if is_missing(&e) => {
+                scheduled.insert(current.clone());
+                missing.push(current.clone());
+            }
+            Err(e) => return Err(Error::io(&full, e)),
+        }
+    }
+    Ok(missing)
+}
+
+/// Renders the plan as a diff-like table, one operation per line:
+///
+/// ```text
+/// + mkdir    .vim/after
+/// + create   .vim/after/syntax/html.vim
+/// + create   .gitconfig (copy)
+/// + create   .local/bin/tmux-session (from bin/tmux-session)
+/// > backup   .vimrc (file)
+/// ~ replace  .vimrc
+/// ~ update   .dircolors (copy)
+///   skip     .tmux.conf (up to date)
+/// ! refuse   .gitconfig (a directory already exists)
+/// ```
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for item in &self.items {
+            for dir in &item.create_dirs {
+                writeln!(f, "+ mkdir    {}", dir.display())?;
+            }
+            let path = item.entry.path.display();
+            let mut notes = Vec::new();
+            if item.entry.strategy != Strategy::Symlink {
+                notes.push(item.entry.strategy.to_string());
+            }
+            if item.entry.source != item.entry.path {
+                notes.push(format!("from {}", item.entry.source.display()));
+            }
+            let how = match notes.is_empty() {
+                true => String::new(),
+                false => format!(" ({})", notes.join(", ")),
+            };
+            match &item.action {
+                Action::Create => writeln!(f, "+ create   {path}{how}")?,
+                Action::Replace { existing } => {
+                    writeln!(f, "> backup   {path} ({existing})")?;
+                    writeln!(f, "~ replace  {path}{how}")?;
+                }
+                Action::Update => writeln!(f, "~ update   {path}{how}")?,
+                Action::Skip => writeln!(f, "  skip     {path} (up to date)")?,
+                Action::Refuse(why) => writeln!(f, "! refuse   {path} ({why})")?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,312 @@
+//! A plugin manager for the vim bundles, in place of git submodules.
+//!
+//! Plugins are listed as `[[plugin]]` tables in the
+//! [manifest](crate::manifest), each with its URL and, optionally, the
+//! branch, tag or commit to check out. [`install`] clones whatever is
+//! missing and moves each checkout onto its pin, several plugins at a time.
+//! A branch pin leaves the checkout on that branch, not on a detached
+//! `HEAD`.
+//!
+//! [`migrate`] turns the submodules in `.gitmodules` into plugins.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use git2::build::{CheckoutBuilder, RepoBuilder};
+use git2::{BranchType, Oid, Repository};
+
+use crate::bundles::{git, recorded};
+use crate::error::{Error, Result};
+use crate::fsutil::{is_empty_dir, is_missing};
+use crate::gitmodules::{GitModules, GITMODULES};
+use crate::layout::Layout;
+use crate::lockfile::Lockfile;
+use crate::manifest::{Manifest, Pin, Plugin};
+
+/// How many plugins are cloned or fetched at once unless told otherwise.
+pub const DEFAULT_JOBS: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// Cloned, as there was nothing there.
+    Cloned,
+    /// Moved onto its pin.
+    CheckedOut,
+    /// Already on its pin.
+    Current,
+}
+
+/// Clones or checks out every plugin in the manifest, `jobs` at a time.
+/// A plugin that fails does not stop the others; its error is returned in
+/// its place.
+pub fn install(layout: &Layout, jobs: usize) -> Result<Vec<(Plugin, Result<Outcome>)>> {
+    let repo = layout.repo();
+    let plugins = Manifest::load(repo)?.plugins;
+    let outcomes = parallel(&plugins, jobs, |plugin| install_one(repo, plugin));
+    Ok(plugins.into_iter().zip(outcomes).collect())
+}
+
+fn install_one(repo: &Path, plugin: &Plugin) -> Result<Outcome> {
+    let dir = repo.join(&plugin.path);
+    let (checkout, cloned) = if is_empty_dir(&dir)? {
+        let checkout = RepoBuilder::new()
+            .clone(&plugin.url, &dir)
+            .map_err(|e| Error::git2(&dir, e))?;
+        (checkout, true)
+    } else {
+        let checkout = Repository::open(&dir).map_err(|e| Error::git2(&dir, e))?;
+        (checkout, false)
+    };
+    let moved = Checkout {
+        repo: &checkout,
+        dir: &dir,
+        url: &plugin.url,
+    }
+    .pin(&plugin.pin)?;
+    Ok(match (cloned, moved) {
+        (true, _) => Outcome::Cloned,
+        (false, true) => Outcome::CheckedOut,
+        (false, false) => Outcome::Current,
+    })
+}
+
+/// A plugin's checkout.
+pub(crate) struct Checkout<'a> {
+    pub repo: &'a Repository,
+    pub dir: &'a Path,
+    pub url: &'a str,
+}
+
+impl Checkout<'_> {
+    fn err(&self, e: git2::Error) -> Error {
+        Error::git2(self.dir, e)
+    }
+
+    /// Puts the checkout on `pin`, fetching only if it does not have what
+    /// the pin names. A checkout that follows the default branch stays
+    /// where it is. Returns whether anything changed.
+    fn pin(&self, pin: &Pin) -> Result<bool> {
+        match pin {
+            Pin::Default => Ok(false),
+            Pin::Branch(branch) => {
+                let local = format!("refs/heads/{branch}");
+                if self
+                    .repo
+                    .head()
+                    .ok()
+                    .and_then(|h| h.name().ok().map(str::to_string))
+                    == Some(local)
+                {
+                    return Ok(false);
+                }
+                if self.repo.find_branch(branch, BranchType::Local).is_err() {
+                    let remote = format!("refs/remotes/origin/{branch}");
+                    if self.repo.find_reference(&remote).is_err() {
+                        self.fetch(&[&format!("+refs/heads/{branch}:{remote}")])?;
+                    }
+                    let commit = self
+                        .repo
+                        .find_reference(&remote)
+                        .and_then(|r| r.peel_to_commit())
+                        .map_err(|e| self.err(e))?;
+                    let mut created = self
+                        .repo
+                        .branch(branch, &commit, false)
+                        .map_err(|e| self.err(e))?;
+                    created
+                        .set_upstream(Some(&format!("origin/{branch}")))
+                        .map_err(|e| self.err(e))?;
+                }
+                self.switch(&format!("refs/heads/{branch}"))?;
+                Ok(true)
+            }
+            Pin::Tag(tag) => {
+                let name = format!("refs/tags/{tag}");
+                if self.repo.find_reference(&name).is_err() {
+                    self.fetch(&[&format!("+{name}:{name}")])?;
+                }
+                let commit = self
+                    .repo
+                    .find_reference(&name)
+                    .and_then(|r| r.peel_to_commit())
+                    .map_err(|e| self.err(e))?;
+                self.detach(commit.id())
+            }
+            Pin::Commit(commit) => {
+                let found = |repo: &Repository| {
+                    repo.revparse_single(commit)
+                        .and_then(|o| o.peel_to_commit())
+                        .map(|c| c.id())
+                };
+                let id = match found(self.repo) {
+                    Ok(id) => id,
+                    Err(_) => {
+                        self.fetch(&["+refs/heads/*:refs/remotes/origin/*"])?;
+                        found(self.repo).map_err(|e| self.err(e))?
+                    }
+                };
+                self.detach(id)
+            }
+        }
+    }
+
+    /// Fetches `refspecs` from the plugin's URL.
+    pub(crate) fn fetch(&self, refspecs: &[&str]) -> Result<()> {
+        let mut remote = self
+            .repo
+            .remote_anonymous(self.url)
+            .map_err(|e| self.err(e))?;
+        remote.fetch(refspecs, None, None).map_err(|e| self.err(e))
+    }
+
+    /// Checks out `id` on a detached `HEAD`, unless it is there already.
+    pub(crate) fn detach(&self, id: Oid) -> Result<bool> {
+        if self.repo.head().ok().and_then(|h| h.target()) == Some(id) {
+            return Ok(false);
+        }
+        let object = self.repo.find_object(id, None).map_err(|e| self.err(e))?;
+        self.repo
+            .checkout_tree(&object, Some(CheckoutBuilder::new().safe()))
+            .map_err(|e| self.err(e))?;
+        self.repo.set_head_detached(id).map_err(|e| self.err(e))?;
+        Ok(true)
+    }
+
+    /// Checks out the branch `reference` and puts `HEAD` on it.
+    pub(crate) fn switch(&self, reference: &str) -> Result<()> {
+        let object = self
+            .repo
+            .revparse_single(reference)
+            .map_err(|e| self.err(e))?;
+        self.repo
+            .checkout_tree(&object, Some(CheckoutBuilder::new().safe()))
+            .map_err(|e| self.err(e))?;
+        self.repo.set_head(reference).map_err(|e| self.err(e))
+    }
+}
+
+/// Runs `f` on every item with at most `jobs` threads, returning the
+/// results in the order of `items`.
+pub(crate) fn parallel<T: Sync, R: Send>(
+    items: &[T],
+    jobs: usize,
+    f: impl Fn(&T) -> R + Sync,
+) -> Vec<R> {
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new(items.iter().map(|_| None).collect());
+    thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, items.len().max(1)) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(i) else { break };
+                let result = f(item);
+                results.lock().unwrap()[i] = Some(result);
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|r| r.expect("every item was run"))
+        .collect()
+}
+
+/// Lists every submodule in `.gitmodules` as a plugin in the manifest,
+/// then removes `.gitmodules` and stops the repository tracking the
+/// submodules. Checkouts are left where they are, and added to
+/// `.gitignore` so the repository does not pick them up as new files.
+///
+/// Each plugin is pinned to the commit in `bundles.lock` or recorded for
+/// the submodule if there is one, and otherwise follows the submodule's
+/// `branch`, if it has one. URLs get the manifest's rewrites. Submodules
+/// at a path some plugin already has are dropped.
+pub fn migrate(layout: &Layout) -> Result<Vec<Plugin>> {
+    let repo = layout.repo();
+    let manifest = Manifest::load(repo)?;
+    let lockfile = Lockfile::load(repo)?;
+    let modules = GitModules::load(repo)?;
+
+    let mut plugins = Vec::new();
+    let mut untrack = Vec::new();
+    for module in &modules.modules {
+        let path = PathBuf::from(module.require(repo, "path")?);
+        let url = module.require(repo, "url")?;
+        let gitlink = recorded(repo, &path)?;
+        if gitlink.is_some() {
+            untrack.push(path.clone());
+        }
+        if manifest.plugins.iter().any(|p| p.path == path) {
+            continue;
+        }
+        let locked = lockfile.as_ref().and_then(|l| l.get(&path));
+        let pin = match (locked, gitlink, module.get("branch")) {
+            (Some(locked), _, _) => Pin::Commit(locked.commit.clone()),
+            (None, Some(commit), _) => Pin::Commit(commit),
+            (None, None, Some(branch)) => Pin::Branch(branch.to_string()),
+            (None, None, None) => Pin::Default,
+        };
+        plugins.push(Plugin {
+            name: path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+            url: manifest.rewrite_url(url).unwrap_or_else(|| url.to_string()),
+            path,
+            pin,
+        });
+    }
+
+    if !plugins.is_empty() {
+        Manifest::add_plugins(repo, &plugins)?;
+    }
+    let ignored: Vec<PathBuf> = modules
+        .modules
+        .iter()
+        .filter_map(|m| m.path().map(Path::to_path_buf))
+        .collect();
+    ignore(repo, &ignored)?;
+    for path in &untrack {
+        git(
+            repo,
+            &["rm", "--cached", "--quiet", "--", &path.to_string_lossy()],
+        )?;
+    }
+    let gitmodules = repo.join(GITMODULES);
+    match fs::remove_file(&gitmodules) {
+        Ok(()) => {}
+        Err(e) if is_missing(&e) => {}
+        Err(e) => return Err(Error::io(&gitmodules, e)),
+    }
+    Ok(plugins)
+}
+
+/// Adds `/path/` to `.gitignore` for each of `paths` it does not have.
+fn ignore(repo: &Path, paths: &[PathBuf]) -> Result<()> {
+    let path = repo.join(".gitignore");
+    let mut text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if is_missing(&e) => String::new(),
+        Err(e) => return Err(Error::io(&path, e)),
+    };
+    let original = text.len();
+    for ignored in paths {
+        let line = format!("/{}/", ignored.display());
+        if text.lines().any(|l| l == line) {
+            continue;
+        }
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        text.push_str(&line);
+        text.push('\n');
+    }
+    if text.len() == original {
+        return Ok(());
+    }
+    fs::write(&path, text).map_err(|e| Error::io(&path, e))
+}
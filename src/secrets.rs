@@ -0,0 +1,188 @@
+//! A local, encrypted store for values that must never be committed.
+//!
+//! Templates refer to a secret as `{{ secret://github.token }}`. The store
+//! lives at `~/.config/dotfiles/secrets.enc` and holds every secret as one
+//! JSON object encrypted with ChaCha20-Poly1305, under a key derived from a
+//! passphrase with Argon2id. The file is laid out as
+//!
+//! ```text
+//! dotfiles-secrets-v1\n | salt (16 bytes) | nonce (12 bytes) | ciphertext
+//! ```
+//!
+//! and is rewritten with a fresh salt and nonce on every change.
+//!
+//! The passphrase is taken from `DOTFILES_PASSPHRASE` if it is set, and
+//! asked for on the terminal otherwise.
+
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{IsTerminal, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+use crate::error::{Error, Result};
+use crate::fsutil::is_missing;
+use crate::layout::Layout;
+
+/// The store, relative to home.
+pub const SECRETS: &str = ".config/dotfiles/secrets.enc";
+
+/// The environment variable the passphrase is read from.
+pub const PASSPHRASE_VAR: &str = "DOTFILES_PASSPHRASE";
+
+const MAGIC: &[u8] = b"dotfiles-secrets-v1\n";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Decrypted secrets, by name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Secrets(BTreeMap<String, String>);
+
+impl Secrets {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+
+    /// Adds or replaces a secret. Names follow the same rules as template
+    /// variable names.
+    pub fn set(&mut self, name: &str, value: String) -> Result<()> {
+        if !is_valid_name(name) {
+            return Err(Error::BadSecretName(name.to_string()));
+        }
+        self.0.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.0.remove(name).is_some()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+}
+
+/// Letters, digits, `_` and `-`, in dot-separated parts.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.split('.').all(|part| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretStore {
+    path: PathBuf,
+}
+
+impl SecretStore {
+    pub fn new(layout: &Layout) -> Self {
+        SecretStore {
+            path: layout.home().join(SECRETS),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    /// Decrypts the store. A store that does not exist yet is empty.
+    pub fn open(&self, passphrase: &str) -> Result<Secrets> {
+        let data = match fs::read(&self.path) {
+            Ok(data) => data,
+            Err(e) if is_missing(&e) => return Ok(Secrets::default()),
+            Err(e) => return Err(Error::io(&self.path, e)),
+        };
+        let locked = || Error::SecretsLocked(self.path.clone());
+        let rest = data.strip_prefix(MAGIC).ok_or_else(locked)?;
+        if rest.len() < SALT_LEN + NONCE_LEN {
+            return Err(locked());
+        }
+        let (salt, rest) = rest.split_at(SALT_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        let cipher = cipher(passphrase, salt)?;
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| locked())?;
+        let secrets = serde_json::from_slice(&plaintext).map_err(|e| Error::json(&self.path, e))?;
+        Ok(Secrets(secrets))
+    }
+
+    /// Encrypts `secrets` into the store, readable by the owner only.
+    pub fn save(&self, secrets: &Secrets, passphrase: &str) -> Result<()> {
+        let mut salt = [0; SALT_LEN];
+        let mut nonce = [0; NONCE_LEN];
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut nonce);
+        let plaintext = serde_json::to_vec(&secrets.0).map_err(|e| Error::json(&self.path, e))?;
+        let ciphertext = cipher(passphrase, &salt)?
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+            .map_err(|_| Error::SecretsLocked(self.path.clone()))?;
+
+        let mut data = MAGIC.to_vec();
+        data.extend_from_slice(&salt);
+        data.extend_from_slice(&nonce);
+        data.extend_from_slice(&ciphertext);
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| Error::io(dir, e))?;
+        }
+        write_private(&self.path, &data)
+    }
+}
+
+fn cipher(passphrase: &str, salt: &[u8]) -> Result<ChaCha20Poly1305> {
+    let mut key = [0; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| Error::Kdf(e.to_string()))?;
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+}
+
+/// Writes `data` to `path` with mode 0600, via a temporary file so that a
+/// reader never sees half of it.
+pub fn write_private(path: &Path, data: &[u8]) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".dotfiles-tmp");
+    let tmp = PathBuf::from(tmp);
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&tmp)
+        .map_err(|e| Error::io(&tmp, e))?;
+    file.write_all(data).map_err(|e| Error::io(&tmp, e))?;
+    fs::rename(&tmp, path).map_err(|e| Error::io(path, e))
+}
+
+/// The passphrase for the store, from the environment or the terminal.
+/// With `confirm`, a typed passphrase has to be entered twice.
+pub fn passphrase(store: &SecretStore, confirm: bool) -> Result<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_VAR) {
+        return Ok(passphrase);
+    }
+    if !std::io::stdin().is_terminal() {
+        return Err(Error::NoPassphrase);
+    }
+    let prompt = format!("Passphrase for {}: ", store.path.display());
+    let passphrase = rpassword::prompt_password(prompt).map_err(|e| Error::io("<tty>", e))?;
+    if confirm {
+        let again = rpassword::prompt_password("Again: ").map_err(|e| Error::io("<tty>", e))?;
+        if again != passphrase {
+            return Err(Error::PassphraseMismatch);
+        }
+    }
+    Ok(passphrase)
+}
@@ -0,0 +1,177 @@
+//! Comparing the repository with what is actually in the home directory.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use similar::TextDiff;
+
+use crate::discover::discover;
+use crate::error::{Error, Result};
+use crate::fsutil::{is_missing, FileKind};
+use crate::installed::{Installed, InstalledFile};
+use crate::layout::Layout;
+use crate::strategy::{inspect, Found, Strategy};
+use crate::template::{render_file, Rendered, Values};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum State {
+    /// Installed and identical to the repository.
+    Linked,
+    /// Nothing at the destination.
+    Missing,
+    /// A copy whose content differs from the repository.
+    Diverged {
+        /// Whether the copy was edited after it was installed, rather than
+        /// the repository having moved on.
+        edited: bool,
+        /// Unified diff from the repository file to the home file.
+        diff: String,
+    },
+    /// Something the tool did not put there.
+    Foreign(FileKind),
+    /// A symlink whose target does not exist.
+    BrokenLink(PathBuf),
+}
+
+impl State {
+    pub fn is_clean(&self) -> bool {
+        *self == State::Linked
+    }
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            State::Linked => "linked",
+            State::Missing => "missing",
+            State::Diverged { .. } => "diverged",
+            State::Foreign(_) => "foreign",
+            State::BrokenLink(_) => "broken",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileStatus {
+    pub path: PathBuf,
+    pub strategy: Strategy,
+    pub state: State,
+}
+
+/// Checks every file in the repository, plus anything the install record
+/// still lists that has since been removed from the repository.
+pub fn status(layout: &Layout) -> Result<Vec<FileStatus>> {
+    let installed = Installed::load(layout)?;
+    let values = Values::load(layout)?;
+    let mut managed: BTreeMap<PathBuf, (PathBuf, Strategy)> = installed
+        .files
+        .iter()
+        .map(|(path, file)| (path.clone(), (file.source.clone(), file.strategy)))
+        .collect();
+    for entry in discover(layout)? {
+        let source = layout.source(&entry.source);
+        managed.insert(entry.path, (source, entry.strategy));
+    }
+
+    managed
+        .into_iter()
+        .map(|(path, (source, strategy))| {
+            let recorded = installed.files.get(&path);
+            let state = check(&layout.dest(&path), &source, strategy, recorded, &values)?;
+            Ok(FileStatus {
+                path,
+                strategy,
+                state,
+            })
+        })
+        .collect()
+}
+
+fn check(
+    dest: &Path,
+    source: &Path,
+    strategy: Strategy,
+    recorded: Option<&InstalledFile>,
+    values: &Values,
+) -> Result<State> {
+    let meta = fs::symlink_metadata(dest).ok();
+    if meta.as_ref().is_some_and(|m| m.file_type().is_symlink()) && fs::metadata(dest).is_err() {
+        let target = fs::read_link(dest).map_err(|e| Error::io(dest, e))?;
+        return Ok(State::BrokenLink(target));
+    }
+    let expected = || match strategy {
+        Strategy::Template if source.exists() => render_file(source, values),
+        _ => read_or_empty(source).map(|bytes| Rendered {
+            bytes,
+            secret: false,
+        }),
+    };
+    Ok(match inspect(dest, source, strategy, recorded, values)? {
+        Found::Missing => State::Missing,
+        Found::Current => State::Linked,
+        Found::Modified => State::Diverged {
+            edited: true,
+            diff: diff_rendered(source, &expected()?, dest)?,
+        },
+        Found::Stale if meta.is_some_and(|m| m.is_file()) => {
+            let expected = expected()?;
+            if expected.bytes == read_or_empty(dest)? {
+                // The right content, just not installed the way asked for.
+                State::Linked
+            } else {
+                State::Diverged {
+                    edited: false,
+                    diff: diff_rendered(source, &expected, dest)?,
+                }
+            }
+        }
+        // A link to the right file, just not the strategy asked for.
+        Found::Stale => State::Linked,
+        Found::Foreign(kind) => State::Foreign(kind),
+    })
+}
+
+/// A unified diff from the repository's version of a file to the home
+/// directory's. A file that does not exist diffs as empty.
+pub fn diff(source: &Path, dest: &Path) -> Result<String> {
+    diff_bytes(source, &read_or_empty(source)?, dest)
+}
+
+/// Like [`diff`], but against a rendered template, which is not shown if a
+/// secret went into it.
+fn diff_rendered(source: &Path, expected: &Rendered, dest: &Path) -> Result<String> {
+    if expected.secret {
+        return Ok(format!(
+            "Rendered {} and {} differ (not shown: it contains secrets)\n",
+            source.display(),
+            dest.display()
+        ));
+    }
+    diff_bytes(source, &expected.bytes, dest)
+}
+
+/// Like [`diff`], but with the repository side already read or rendered.
+fn diff_bytes(source: &Path, old: &[u8], dest: &Path) -> Result<String> {
+    let new = read_or_empty(dest)?;
+    let (Ok(old), Ok(new)) = (std::str::from_utf8(old), String::from_utf8(new)) else {
+        return Ok(format!(
+            "Binary files {} and {} differ\n",
+            source.display(),
+            dest.display()
+        ));
+    };
+    Ok(TextDiff::from_lines(old, &new)
+        .unified_diff()
+        .header(&source.display().to_string(), &dest.display().to_string())
+        .to_string())
+}
+
+fn read_or_empty(path: &Path) -> Result<Vec<u8>> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(bytes),
+        Err(e) if is_missing(&e) => Ok(Vec::new()),
+        Err(e) => Err(Error::io(path, e)),
+    }
+}
@@ -0,0 +1,183 @@
+//! The ways a file can be put into the home directory, and telling whether
+//! what is there now is something we put there.
+//!
+//! Symlinks and hardlinks identify themselves: a symlink points at the
+//! repository and a hardlink shares the repository file's inode. A copy has
+//! no such link back, so the install record keeps a hash of the content as
+//! it was installed. A copy whose content no longer matches that hash has
+//! been edited locally and must not be overwritten. Rendered templates are
+//! tracked the same way.
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io;
+use std::os::unix::fs::{symlink, MetadataExt};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::{Error, Result};
+use crate::fsutil::{is_missing, FileKind};
+use crate::installed::InstalledFile;
+use crate::secrets::write_private;
+use crate::template::{render_file, Values};
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Strategy {
+    #[default]
+    Symlink,
+    Copy,
+    Hardlink,
+    /// A copy rendered through the [template engine](crate::template).
+    Template,
+}
+
+impl Strategy {
+    /// Whether the install record needs a content hash to recognise the
+    /// installed file later.
+    pub fn is_hashed(self) -> bool {
+        !matches!(self, Strategy::Symlink)
+    }
+
+    /// Puts `source` at `dest`, which must not exist yet. `values` are only
+    /// needed for templates.
+    ///
+    /// Copies are written to a temporary file first so that `dest` never
+    /// holds a partial copy.
+    pub fn place(self, source: &Path, dest: &Path, values: &Values) -> Result<()> {
+        match self {
+            Strategy::Symlink => symlink(source, dest).map_err(|e| Error::io(dest, e)),
+            Strategy::Hardlink => fs::hard_link(source, dest).map_err(|e| Error::io(dest, e)),
+            Strategy::Copy => write_new(dest, |tmp| {
+                fs::copy(source, tmp)
+                    .map(drop)
+                    .map_err(|e| Error::io(tmp, e))
+            }),
+            Strategy::Template => {
+                let rendered = render_file(source, values)?;
+                write_new(dest, |tmp| match rendered.secret {
+                    true => write_private(tmp, &rendered.bytes),
+                    false => fs::write(tmp, &rendered.bytes).map_err(|e| Error::io(tmp, e)),
+                })
+            }
+        }
+    }
+
+    /// The hash `dest` would have if this strategy put `source` there, for
+    /// the strategies that produce a file of their own. `None` if the source
+    /// has been removed from the repository since.
+    fn expected_hash(self, source: &Path, values: &Values) -> Result<Option<String>> {
+        if fs::symlink_metadata(source).is_err() {
+            return Ok(None);
+        }
+        match self {
+            Strategy::Copy => hash_file(source).map(Some),
+            Strategy::Template => Ok(Some(hash_bytes(&render_file(source, values)?.bytes))),
+            Strategy::Symlink | Strategy::Hardlink => Ok(None),
+        }
+    }
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Strategy::Symlink => "symlink",
+            Strategy::Copy => "copy",
+            Strategy::Hardlink => "hardlink",
+            Strategy::Template => "template",
+        })
+    }
+}
+
+/// What is at a destination, relative to what a strategy would put there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Found {
+    Missing,
+    /// Exactly what the strategy would produce.
+    Current,
+    /// Something we installed that is out of date or was installed with a
+    /// different strategy, and can be replaced without a backup.
+    Stale,
+    /// A copy we installed that has been edited since.
+    Modified,
+    /// Anything else.
+    Foreign(FileKind),
+}
+
+/// Classifies whatever is at `dest`. `recorded` is the install record's
+/// entry for it, if there is one.
+pub fn inspect(
+    dest: &Path,
+    source: &Path,
+    strategy: Strategy,
+    recorded: Option<&InstalledFile>,
+    values: &Values,
+) -> Result<Found> {
+    let meta = match fs::symlink_metadata(dest) {
+        Ok(meta) => meta,
+        Err(e) if is_missing(&e) => return Ok(Found::Missing),
+        Err(e) => return Err(Error::io(dest, e)),
+    };
+    match FileKind::of(&meta) {
+        FileKind::Symlink => {
+            let target = fs::read_link(dest).map_err(|e| Error::io(dest, e))?;
+            Ok(match (target == source, strategy) {
+                (true, Strategy::Symlink) => Found::Current,
+                (true, _) => Found::Stale,
+                (false, _) => Found::Foreign(FileKind::Symlink),
+            })
+        }
+        FileKind::File => {
+            if same_inode(&meta, source) {
+                return Ok(match strategy {
+                    Strategy::Hardlink => Found::Current,
+                    _ => Found::Stale,
+                });
+            }
+            let hash = hash_file(dest)?;
+            if strategy.expected_hash(source, values)?.as_ref() == Some(&hash) {
+                return Ok(Found::Current);
+            }
+            Ok(match recorded.and_then(|r| r.hash.as_deref()) {
+                Some(recorded) if recorded == hash => Found::Stale,
+                Some(_) => Found::Modified,
+                None => Found::Foreign(FileKind::File),
+            })
+        }
+        kind => Ok(Found::Foreign(kind)),
+    }
+}
+
+fn same_inode(meta: &fs::Metadata, other: &Path) -> bool {
+    fs::metadata(other).is_ok_and(|o| o.dev() == meta.dev() && o.ino() == meta.ino())
+}
+
+/// The hex SHA-256 of a file's content.
+pub fn hash_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path).map_err(|e| Error::io(path, e))?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher).map_err(|e| Error::io(path, e))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+pub fn hash_bytes(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Creates `dest` by having `write` fill a temporary file beside it, which
+/// is then renamed into place.
+fn write_new(dest: &Path, write: impl FnOnce(&Path) -> Result<()>) -> Result<()> {
+    if fs::symlink_metadata(dest).is_ok() {
+        let e = io::Error::from(io::ErrorKind::AlreadyExists);
+        return Err(Error::io(dest, e));
+    }
+    let mut tmp = dest.as_os_str().to_owned();
+    tmp.push(".dotfiles-tmp");
+    let tmp = PathBuf::from(tmp);
+    write(&tmp)?;
+    fs::rename(&tmp, dest).map_err(|e| Error::io(dest, e))
+}
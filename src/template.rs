@@ -0,0 +1,249 @@
+//! Rendering files installed with the `template` strategy.
+//!
+//! A template is the file as it should be installed, with `{{ name }}` tags
+//! where per-user values go:
+//!
+//! ```text
+//! [user]
+//!   name = {{ user.name }}
+//!   email = {{ user.email }}
+//! ```
+//!
+//! Values come from `~/.config/dotfiles/values.toml`, which lives on each
+//! machine and is never committed. Dotted names look into its tables. A
+//! literal `{{` is written as `{{ "{{" }}`.
+//!
+//! `{{ secret://name }}` is replaced by a secret from the
+//! [encrypted store](crate::secrets), which is only unlocked once a
+//! template asks for one. Files with secrets in them are installed
+//! readable by their owner only.
+
+use std::cell::OnceCell;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::error::{Error, Result};
+use crate::fsutil::is_missing;
+use crate::layout::Layout;
+use crate::secrets::{is_valid_name, passphrase, SecretStore, Secrets};
+
+/// The values file, relative to home.
+pub const VALUES: &str = ".config/dotfiles/values.toml";
+
+/// The variables templates are rendered with.
+///
+/// Load them once per command and pass them around: clones share the
+/// secret store, so it is unlocked at most once however many times the
+/// templates are rendered.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Values {
+    table: toml::Table,
+    secrets: Option<Rc<LazySecrets>>,
+}
+
+type Passphrase = Box<dyn Fn(&SecretStore) -> Result<String>>;
+
+/// The secret store, unlocked the first time a secret is looked up.
+struct LazySecrets {
+    store: Option<SecretStore>,
+    /// Where the passphrase comes from, if not [`passphrase`].
+    passphrase: Option<Passphrase>,
+    unlocked: OnceCell<Result<Secrets, String>>,
+}
+
+impl LazySecrets {
+    fn get(&self) -> Result<&Secrets, String> {
+        let unlocked = self.unlocked.get_or_init(|| {
+            let store = self.store.as_ref().ok_or("there is no secret store")?;
+            if !store.exists() {
+                return Err(format!("{} does not exist", store.path().display()));
+            }
+            match &self.passphrase {
+                Some(ask) => ask(store),
+                None => passphrase(store, false),
+            }
+            .and_then(|p| store.open(&p))
+            .map_err(|e| e.to_string())
+        });
+        unlocked.as_ref().map_err(String::clone)
+    }
+}
+
+impl fmt::Debug for LazySecrets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazySecrets")
+            .field("store", &self.store)
+            .field("unlocked", &self.unlocked.get().map(Result::is_ok))
+            .finish()
+    }
+}
+
+impl PartialEq for LazySecrets {
+    fn eq(&self, other: &Self) -> bool {
+        self.store == other.store && self.unlocked == other.unlocked
+    }
+}
+
+impl Values {
+    pub fn new(table: toml::Table) -> Self {
+        Values {
+            table,
+            secrets: None,
+        }
+    }
+
+    /// Uses `secrets` for `secret://` placeholders instead of the store.
+    pub fn with_secrets(mut self, secrets: Secrets) -> Self {
+        self.secrets = Some(Rc::new(LazySecrets {
+            store: None,
+            passphrase: None,
+            unlocked: OnceCell::from(Ok(secrets)),
+        }));
+        self
+    }
+
+    /// Gets the store's passphrase from `ask` instead of the environment
+    /// or the terminal.
+    pub fn with_passphrase(
+        mut self,
+        ask: impl Fn(&SecretStore) -> Result<String> + 'static,
+    ) -> Self {
+        let store = self.secrets.as_ref().and_then(|s| s.store.clone());
+        self.secrets = Some(Rc::new(LazySecrets {
+            store,
+            passphrase: Some(Box::new(ask)),
+            unlocked: OnceCell::new(),
+        }));
+        self
+    }
+
+    /// Reads the values file for `layout`'s home, which may not exist, and
+    /// gets ready to unlock its secret store should a template need it.
+    pub fn load(layout: &Layout) -> Result<Self> {
+        let path = values_path(layout);
+        let mut values = match fs::read_to_string(&path) {
+            Ok(text) => toml::from_str(&text)
+                .map(Values::new)
+                .map_err(|e| Error::toml(&path, e))?,
+            Err(e) if is_missing(&e) => Values::default(),
+            Err(e) => return Err(Error::io(&path, e)),
+        };
+        values.secrets = Some(Rc::new(LazySecrets {
+            store: Some(SecretStore::new(layout)),
+            passphrase: None,
+            unlocked: OnceCell::new(),
+        }));
+        Ok(values)
+    }
+
+    fn secret(&self, name: &str) -> Result<String, String> {
+        let secrets = match &self.secrets {
+            Some(lazy) => lazy.get()?,
+            None => return Err("there is no secret store".to_string()),
+        };
+        secrets
+            .get(name)
+            .map(str::to_string)
+            .ok_or_else(|| format!("secret {name} is not in the secret store"))
+    }
+
+    /// Looks up a dotted name.
+    fn get(&self, name: &str) -> Result<String, String> {
+        let mut table = &self.table;
+        let mut parts = name.split('.').peekable();
+        while let Some(part) = parts.next() {
+            let value = table
+                .get(part)
+                .ok_or_else(|| format!("{name} is not set in ~/{VALUES}"))?;
+            match value {
+                toml::Value::Table(inner) if parts.peek().is_some() => table = inner,
+                toml::Value::Table(_) => return Err(format!("{name} is a table, not a value")),
+                _ if parts.peek().is_some() => {
+                    return Err(format!("{name}: {part} is not a table"));
+                }
+                toml::Value::String(s) => return Ok(s.clone()),
+                toml::Value::Integer(i) => return Ok(i.to_string()),
+                toml::Value::Float(f) => return Ok(f.to_string()),
+                toml::Value::Boolean(b) => return Ok(b.to_string()),
+                toml::Value::Datetime(d) => return Ok(d.to_string()),
+                toml::Value::Array(_) => return Err(format!("{name} is a list, not a value")),
+            }
+        }
+        Err(format!("{name} is not a valid name"))
+    }
+}
+
+pub fn values_path(layout: &Layout) -> PathBuf {
+    layout.home().join(VALUES)
+}
+
+/// A rendered template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rendered {
+    pub bytes: Vec<u8>,
+    /// Whether any secret went into it.
+    pub secret: bool,
+}
+
+/// Renders the template at `path`.
+pub fn render_file(path: &Path, values: &Values) -> Result<Rendered> {
+    let text = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+    let mut secret = false;
+    render_with(&text, values, &mut secret)
+        .map(|text| Rendered {
+            bytes: text.into_bytes(),
+            secret,
+        })
+        .map_err(|(line, message)| Error::Template {
+            path: path.to_path_buf(),
+            line,
+            message,
+        })
+}
+
+/// Renders `text`, or returns the one-based line of the first problem and
+/// what it is.
+pub fn render(text: &str, values: &Values) -> Result<String, (usize, String)> {
+    render_with(text, values, &mut false)
+}
+
+fn render_with(text: &str, values: &Values, secret: &mut bool) -> Result<String, (usize, String)> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut line = 1;
+    while let Some(start) = rest.find("{{") {
+        let (before, tag) = rest.split_at(start);
+        out.push_str(before);
+        line += before.matches('\n').count();
+        let end = tag
+            .find("}}")
+            .filter(|&end| !tag[..end].contains('\n'))
+            .ok_or_else(|| (line, "`{{` is not closed on the same line".to_string()))?;
+        let expr = tag[2..end].trim();
+        *secret |= expr.starts_with(SECRET_SCHEME);
+        out.push_str(&evaluate(expr, values).map_err(|message| (line, message))?);
+        rest = &tag[end + 2..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+const SECRET_SCHEME: &str = "secret://";
+
+fn evaluate(expr: &str, values: &Values) -> Result<String, String> {
+    if let Some(literal) = expr.strip_prefix('"').and_then(|e| e.strip_suffix('"')) {
+        return Ok(literal.to_string());
+    }
+    if let Some(name) = expr.strip_prefix(SECRET_SCHEME) {
+        if !is_valid_name(name) {
+            return Err(format!("`{name}` is not a secret name"));
+        }
+        return values.secret(name);
+    }
+    if !is_valid_name(expr) {
+        return Err(format!("`{expr}` is not a variable name"));
+    }
+    values.get(expr)
+}
@@ -0,0 +1,411 @@
+//! Applying a plan as a journaled, all-or-nothing transaction.
+//!
+//! Before the first operation runs, the full list of operations is written to
+//! `journal.json` in the state directory, and each one is marked done as soon
+//! as it succeeds. If an operation fails, every completed one is undone in
+//! reverse order and the journal is removed. If the process dies instead, the
+//! journal stays behind so the install can later be resumed or rolled back.
+//!
+//! Every operation is safe to repeat: one that was interrupted half-way is
+//! simply run again on resume.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::backup::{Backup, BackupStore};
+use crate::error::{Error, Result};
+use crate::fsutil::{is_missing, read_json, write_json};
+use crate::installed::{Installed, InstalledFile};
+use crate::layout::Layout;
+use crate::strategy::{hash_file, inspect, Found, Strategy};
+use crate::template::Values;
+
+const JOURNAL: &str = "journal.json";
+const DISCARDED: &str = "discarded";
+
+/// One reversible change to the home directory. Paths are relative to home.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum Op {
+    MkDir {
+        path: PathBuf,
+    },
+    Backup {
+        path: PathBuf,
+    },
+    /// Moves something we installed earlier out of the way. It is kept in
+    /// the state directory until the transaction finishes.
+    Discard {
+        path: PathBuf,
+    },
+    Install {
+        path: PathBuf,
+        source: PathBuf,
+        strategy: Strategy,
+    },
+}
+
+impl Op {
+    fn describe(&self) -> String {
+        match self {
+            Op::MkDir { path } => format!("creating {}", path.display()),
+            Op::Backup { path } => format!("backing up {}", path.display()),
+            Op::Discard { path } => format!("removing old {}", path.display()),
+            Op::Install { path, strategy, .. } => {
+                format!("installing {} as a {strategy}", path.display())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Step {
+    #[serde(flatten)]
+    op: Op,
+    done: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Journal {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    backup: Option<String>,
+    steps: Vec<Step>,
+}
+
+pub struct Transaction {
+    layout: Layout,
+    path: PathBuf,
+    journal: Journal,
+    backup: Option<Backup>,
+    /// Only needed to install templates, so only loaded once one is.
+    values: Option<Values>,
+}
+
+impl Transaction {
+    /// Records `ops` in a new journal without running any of them.
+    /// Templates are installed with `values`, which should be the ones the
+    /// operations were planned with.
+    ///
+    /// Fails if a previous transaction was interrupted and is still pending.
+    pub fn begin(layout: &Layout, ops: Vec<Op>, values: Values) -> Result<Self> {
+        let path = journal_path(layout);
+        if path.exists() {
+            return Err(Error::JournalPending);
+        }
+        // Everything that can fail happens before the journal is written,
+        // since a journal left behind blocks every later install.
+        let dir = layout.state_dir();
+        fs::create_dir_all(&dir).map_err(|e| Error::io(&dir, e))?;
+        let backup = if ops.iter().any(|op| matches!(op, Op::Backup { .. })) {
+            Some(BackupStore::new(layout).create()?)
+        } else {
+            None
+        };
+        let journal = Journal {
+            backup: backup.as_ref().map(|b| b.id().to_string()),
+            steps: ops.into_iter().map(|op| Step { op, done: false }).collect(),
+        };
+        if let Err(e) = write_json(&path, &journal) {
+            if let Some(backup) = backup {
+                backup.discard_if_empty()?;
+            }
+            return Err(e);
+        }
+        Ok(Transaction {
+            layout: layout.clone(),
+            path,
+            journal,
+            backup,
+            values: Some(values),
+        })
+    }
+
+    /// Picks up the journal left behind by an interrupted transaction.
+    pub fn load(layout: &Layout) -> Result<Self> {
+        let path = journal_path(layout);
+        if !path.exists() {
+            return Err(Error::NoJournal);
+        }
+        let journal: Journal = read_json(&path)?;
+        let backup = match &journal.backup {
+            Some(id) => Some(BackupStore::new(layout).open(id)?),
+            None => None,
+        };
+        Ok(Transaction {
+            layout: layout.clone(),
+            path,
+            journal,
+            backup,
+            values: None,
+        })
+    }
+
+    /// Whether an interrupted transaction is waiting to be resumed or
+    /// rolled back.
+    pub fn is_pending(layout: &Layout) -> bool {
+        journal_path(layout).exists()
+    }
+
+    /// The backup displaced files go into, if any operation needs one.
+    pub fn backup_id(&self) -> Option<&str> {
+        self.journal.backup.as_deref()
+    }
+
+    pub fn completed(&self) -> usize {
+        self.journal.steps.iter().filter(|s| s.done).count()
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.journal.steps.len() - self.completed()
+    }
+
+    /// Runs the next pending operation, returning `false` once none are
+    /// left. A failed operation is left pending.
+    pub fn step(&mut self) -> Result<bool> {
+        let Some(index) = self.journal.steps.iter().position(|s| !s.done) else {
+            return Ok(false);
+        };
+        let op = self.journal.steps[index].op.clone();
+        self.apply(&op).map_err(|e| Error::Step {
+            step: op.describe(),
+            source: Box::new(e),
+        })?;
+        self.journal.steps[index].done = true;
+        write_json(&self.path, &self.journal)?;
+        Ok(true)
+    }
+
+    /// Runs every pending operation. If one fails, everything done so far is
+    /// rolled back and the original error is returned.
+    pub fn commit(mut self) -> Result<Option<String>> {
+        let failure = loop {
+            match self.step() {
+                Ok(true) => {}
+                Ok(false) => break None,
+                Err(e) => break Some(e),
+            }
+        };
+        let Some(failure) = failure else {
+            return self.finish();
+        };
+        let rolled_back = self.completed();
+        match self.rollback() {
+            Ok(()) => Err(Error::TransactionFailed {
+                rolled_back,
+                source: Box::new(failure),
+            }),
+            Err(rollback) => Err(Error::RollbackFailed {
+                source: Box::new(failure),
+                rollback: Box::new(rollback),
+            }),
+        }
+    }
+
+    /// Undoes every completed operation, newest first, and discards the
+    /// journal. If an undo fails the journal is kept so it can be retried.
+    pub fn rollback(mut self) -> Result<()> {
+        while let Some(index) = self.journal.steps.iter().rposition(|s| s.done) {
+            let op = self.journal.steps[index].op.clone();
+            self.undo(&op).map_err(|e| Error::Step {
+                step: format!("undoing {}", op.describe()),
+                source: Box::new(e),
+            })?;
+            self.journal.steps[index].done = false;
+            write_json(&self.path, &self.journal)?;
+        }
+        if let Some(backup) = self.backup.take() {
+            backup.discard_if_empty()?;
+        }
+        self.clear_discarded()?;
+        self.remove_journal()
+    }
+
+    fn finish(mut self) -> Result<Option<String>> {
+        self.record()?;
+        self.clear_discarded()?;
+        let id = match self.backup.take() {
+            Some(backup) if backup.is_empty() => {
+                backup.discard_if_empty()?;
+                None
+            }
+            Some(backup) => Some(backup.id().to_string()),
+            None => None,
+        };
+        self.remove_journal()?;
+        Ok(id)
+    }
+
+    /// Adds everything this transaction created to the install record.
+    fn record(&self) -> Result<()> {
+        let mut installed = Installed::load(&self.layout)?;
+        let backed_up: Vec<&Path> = self
+            .journal
+            .steps
+            .iter()
+            .filter_map(|step| match &step.op {
+                Op::Backup { path } => Some(path.as_path()),
+                _ => None,
+            })
+            .collect();
+        for step in &self.journal.steps {
+            match &step.op {
+                Op::MkDir { path } => installed.add_dir(path),
+                Op::Install {
+                    path,
+                    source,
+                    strategy,
+                } => {
+                    let backup = backed_up
+                        .contains(&path.as_path())
+                        .then(|| self.journal.backup.clone())
+                        .flatten();
+                    let hash = match strategy.is_hashed() {
+                        true => Some(hash_file(&self.layout.dest(path))?),
+                        false => None,
+                    };
+                    let file = InstalledFile {
+                        source: source.clone(),
+                        strategy: *strategy,
+                        hash,
+                        backup,
+                    };
+                    installed.add(path, file);
+                }
+                Op::Backup { .. } | Op::Discard { .. } => {}
+            }
+        }
+        installed.save(&self.layout)
+    }
+
+    fn discarded(&self, rel: &Path) -> PathBuf {
+        self.layout.state_dir().join(DISCARDED).join(rel)
+    }
+
+    fn clear_discarded(&self) -> Result<()> {
+        let dir = self.layout.state_dir().join(DISCARDED);
+        match fs::remove_dir_all(&dir) {
+            Err(e) if !is_missing(&e) => Err(Error::io(&dir, e)),
+            _ => Ok(()),
+        }
+    }
+
+    fn remove_journal(&self) -> Result<()> {
+        fs::remove_file(&self.path).map_err(|e| Error::io(&self.path, e))
+    }
+
+    fn apply(&mut self, op: &Op) -> Result<()> {
+        let home = self.layout.home().to_path_buf();
+        match op {
+            Op::MkDir { path: rel } => {
+                let dir = home.join(rel);
+                match fs::create_dir(&dir) {
+                    Err(e) if e.kind() == io::ErrorKind::AlreadyExists && dir.is_dir() => Ok(()),
+                    other => other.map_err(|e| Error::io(&dir, e)),
+                }
+            }
+            Op::Backup { path: rel } => self.backup()?.take(&home, rel),
+            Op::Discard { path: rel } => {
+                let parked = self.discarded(rel);
+                move_if_present(&home.join(rel), &parked)
+            }
+            Op::Install {
+                path,
+                source,
+                strategy,
+            } => {
+                let dest = home.join(path);
+                let none = Values::default();
+                let values = match strategy {
+                    Strategy::Template => self.values()?,
+                    _ => &none,
+                };
+                if is_installed(&dest, source, *strategy, values) {
+                    return Ok(());
+                }
+                strategy.place(source, &dest, values)
+            }
+        }
+    }
+
+    fn undo(&mut self, op: &Op) -> Result<()> {
+        let home = self.layout.home().to_path_buf();
+        match op {
+            Op::MkDir { path: rel } => {
+                let dir = home.join(rel);
+                match fs::remove_dir(&dir) {
+                    Ok(()) => Ok(()),
+                    Err(e) if is_missing(&e) => Ok(()),
+                    // Something else has been put there since; keep it.
+                    Err(e) if e.kind() == io::ErrorKind::DirectoryNotEmpty => Ok(()),
+                    Err(e) => Err(Error::io(&dir, e)),
+                }
+            }
+            Op::Backup { path: rel } => self.backup()?.put_back(&home, rel).map(drop),
+            Op::Discard { path: rel } => {
+                let parked = self.discarded(rel);
+                move_if_present(&parked, &home.join(rel))
+            }
+            Op::Install {
+                path,
+                source,
+                strategy,
+            } => {
+                let dest = home.join(path);
+                // Telling a rendered template from an edited one means
+                // rendering it again, which rolling back must not depend
+                // on. The step completed, so the file there is the one it
+                // rendered.
+                let ours = match strategy {
+                    Strategy::Template => fs::symlink_metadata(&dest).is_ok_and(|m| m.is_file()),
+                    _ => is_installed(&dest, source, *strategy, &Values::default()),
+                };
+                if ours {
+                    fs::remove_file(&dest).map_err(|e| Error::io(&dest, e))?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn values(&mut self) -> Result<&Values> {
+        let values = match self.values.take() {
+            Some(values) => values,
+            None => Values::load(&self.layout)?,
+        };
+        Ok(self.values.insert(values))
+    }
+
+    fn backup(&mut self) -> Result<&mut Backup> {
+        self.backup
+            .as_mut()
+            .ok_or_else(|| Error::JournalWithoutBackup(self.path.clone()))
+    }
+}
+
+fn journal_path(layout: &Layout) -> PathBuf {
+    layout.state_dir().join(JOURNAL)
+}
+
+/// Whether `dest` already is exactly what installing `source` with
+/// `strategy` would produce.
+fn is_installed(dest: &Path, source: &Path, strategy: Strategy, values: &Values) -> bool {
+    matches!(
+        inspect(dest, source, strategy, None, values),
+        Ok(Found::Current)
+    )
+}
+
+/// Renames `from` to `to` unless `from` has already gone, which is the case
+/// when an interrupted move is repeated.
+fn move_if_present(from: &Path, to: &Path) -> Result<()> {
+    if fs::symlink_metadata(from).is_err() {
+        return Ok(());
+    }
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).map_err(|e| Error::io(parent, e))?;
+    }
+    fs::rename(from, to).map_err(|e| Error::io(from, e))
+}
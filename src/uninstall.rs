@@ -0,0 +1,180 @@
+//! Handing a home directory back: removing our links and restoring what they
+//! replaced.
+//!
+//! Only links that still point into the repository, and copies whose content
+//! is still what was installed, are removed. Anything that has been edited or
+//! replaced since the install is left exactly as it is, along with its
+//! backup, and reported so the user can sort it out.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::backup::{Backup, BackupStore};
+use crate::error::{Error, Result};
+use crate::fsutil::{is_missing, FileKind};
+use crate::installed::{Installed, InstalledFile};
+use crate::layout::Layout;
+use crate::strategy::{inspect, Found};
+use crate::template::Values;
+use crate::transaction::Transaction;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// Our file was removed; nothing had been there before it.
+    Removed,
+    /// Our file was removed and the original put back from this backup.
+    Restored(String),
+    /// Our file was already gone. Any original is put back regardless.
+    Missing { restored: Option<String> },
+    /// The path no longer holds what we installed, so it was not touched.
+    LeftAlone(Modified),
+}
+
+/// How a managed path has changed since it was installed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Modified {
+    /// Replaced by something other than a symlink.
+    Replaced(FileKind),
+    /// Re-pointed somewhere outside the repository.
+    Relinked(PathBuf),
+    /// A copy whose content has been edited.
+    Edited,
+}
+
+impl fmt::Display for Modified {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Modified::Replaced(kind) => write!(f, "it has been replaced by a {kind}"),
+            Modified::Relinked(target) => write!(f, "it now points at {}", target.display()),
+            Modified::Edited => f.write_str("it has local edits"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub results: Vec<(PathBuf, Outcome)>,
+    /// Backups still holding originals that could not be put back.
+    pub kept_backups: Vec<String>,
+}
+
+/// Removes everything the install record says was installed.
+pub fn uninstall(layout: &Layout) -> Result<Report> {
+    if Transaction::is_pending(layout) {
+        return Err(Error::JournalPending);
+    }
+    let mut installed = Installed::load(layout)?;
+    let values = Values::load(layout)?;
+    let store = BackupStore::new(layout);
+    let mut backups: Vec<Backup> = Vec::new();
+    let mut report = Report::default();
+    let mut kept = BTreeSet::new();
+
+    for (path, file) in &installed.files {
+        let dest = layout.dest(path);
+        let outcome = match check(layout, &dest, file, &values)? {
+            Check::Ours => {
+                fs::remove_file(&dest).map_err(|e| Error::io(&dest, e))?;
+                match restore(layout, &store, &mut backups, path, &file.backup)? {
+                    Some(id) => Outcome::Restored(id),
+                    None => Outcome::Removed,
+                }
+            }
+            Check::Missing => Outcome::Missing {
+                restored: restore(layout, &store, &mut backups, path, &file.backup)?,
+            },
+            Check::Changed(why) => {
+                kept.extend(file.backup.clone());
+                Outcome::LeftAlone(why)
+            }
+        };
+        report.results.push((path.clone(), outcome));
+    }
+
+    for backup in backups {
+        if backup.is_empty() {
+            backup.discard_if_empty()?;
+        } else {
+            kept.insert(backup.id().to_string());
+        }
+    }
+    report.kept_backups = kept.into_iter().collect();
+
+    // Deepest first, so children go before their parents.
+    for dir in installed.dirs.iter().rev() {
+        remove_empty_dir(&layout.dest(dir))?;
+    }
+
+    installed.files.clear();
+    installed.dirs.clear();
+    installed.save(layout)?;
+    Ok(report)
+}
+
+enum Check {
+    Ours,
+    Missing,
+    Changed(Modified),
+}
+
+fn check(layout: &Layout, dest: &Path, file: &InstalledFile, values: &Values) -> Result<Check> {
+    let meta = match fs::symlink_metadata(dest) {
+        Ok(meta) => meta,
+        Err(e) if is_missing(&e) => return Ok(Check::Missing),
+        Err(e) => return Err(Error::io(dest, e)),
+    };
+    Ok(match FileKind::of(&meta) {
+        FileKind::Symlink => {
+            let target = fs::read_link(dest).map_err(|e| Error::io(dest, e))?;
+            if target.starts_with(layout.repo()) {
+                Check::Ours
+            } else {
+                Check::Changed(Modified::Relinked(target))
+            }
+        }
+        FileKind::File if file.strategy.is_hashed() => {
+            match inspect(dest, &file.source, file.strategy, Some(file), values)? {
+                Found::Current | Found::Stale => Check::Ours,
+                Found::Modified => Check::Changed(Modified::Edited),
+                _ => Check::Changed(Modified::Replaced(FileKind::File)),
+            }
+        }
+        kind => Check::Changed(Modified::Replaced(kind)),
+    })
+}
+
+/// Puts `path` back from its backup, if it has one, returning the backup id.
+fn restore(
+    layout: &Layout,
+    store: &BackupStore,
+    backups: &mut Vec<Backup>,
+    path: &Path,
+    id: &Option<String>,
+) -> Result<Option<String>> {
+    let Some(id) = id else { return Ok(None) };
+    let index = match backups.iter().position(|b| b.id() == id) {
+        Some(index) => index,
+        None => match store.open(id) {
+            Ok(backup) => {
+                backups.push(backup);
+                backups.len() - 1
+            }
+            // Restored or deleted by hand since; nothing to put back.
+            Err(Error::BackupNotFound(_)) => return Ok(None),
+            Err(e) => return Err(e),
+        },
+    };
+    let restored = backups[index].put_back(layout.home(), path)?;
+    Ok(restored.then(|| id.clone()))
+}
+
+fn remove_empty_dir(dir: &Path) -> Result<()> {
+    match fs::remove_dir(dir) {
+        Ok(()) => Ok(()),
+        Err(e) if is_missing(&e) || e.kind() == io::ErrorKind::DirectoryNotEmpty => Ok(()),
+        Err(e) => Err(Error::io(dir, e)),
+    }
+}
@@ -0,0 +1,274 @@
+//! Moving the vim bundles on to newer upstream commits.
+//!
+//! Bundles are the submodules in `.gitmodules` and the plugins in the
+//! manifest. [`check`] fetches every checked-out bundle from its URL,
+//! several at a time, and lists the commits each would pull in. Nothing is
+//! checked out until [`apply`] is given the updates that were accepted; it
+//! moves just those bundles and records them in `bundles.lock`, leaving
+//! every other locked bundle as it was.
+//!
+//! A bundle follows the `branch` set for it in `.gitmodules` or the
+//! manifest, or else the upstream's default branch, and is compared with
+//! its locked commit, or with what is checked out if it is not locked. A
+//! plugin pinned to a tag is only offered a newer release tag, and
+//! accepting it moves the manifest's `tag` on. A plugin pinned to a commit
+//! stays where it is until its pin is changed by hand.
+
+use std::path::{Path, PathBuf};
+
+use git2::{Oid, Repository, Sort};
+
+use crate::bundles::{git, recorded};
+use crate::error::{Error, Result};
+use crate::layout::Layout;
+use crate::lockfile::{self, declared, Lockfile};
+use crate::manifest::{Manifest, Pin};
+use crate::plugins::{parallel, Checkout};
+
+/// A commit an update would pull in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Commit {
+    pub id: String,
+    pub subject: String,
+    /// Marked as breaking, the Conventional Commits way: `feat!:` in the
+    /// subject or a `BREAKING CHANGE` note in the message.
+    pub breaking: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Update {
+    /// Relative to the repository root.
+    pub path: PathBuf,
+    /// With the manifest's rewrites applied.
+    pub url: String,
+    pub from: String,
+    pub to: String,
+    /// Reachable from `to` but not from `from`, newest first.
+    pub commits: Vec<Commit>,
+    /// Whether `to` descends from `from`. If not, upstream rewrote its
+    /// history and updating drops commits.
+    pub fast_forward: bool,
+    /// The tag `to` is, for a plugin pinned to a tag.
+    pub tag: Option<String>,
+}
+
+impl Update {
+    /// How many of the commits are marked as breaking.
+    pub fn breaking(&self) -> usize {
+        self.commits.iter().filter(|c| c.breaking).count()
+    }
+}
+
+struct Target {
+    path: PathBuf,
+    url: String,
+    branch: Option<String>,
+    pin: Option<Pin>,
+    locked: Option<String>,
+}
+
+/// Fetches every checked-out bundle, `jobs` at a time, and returns what
+/// updating each would pull in, or `None` if it is up to date. Bundles
+/// that are not checked out and plugins pinned to a commit are left out. A
+/// bundle that fails does not stop the others; its error is returned in
+/// its place.
+pub fn check(layout: &Layout, jobs: usize) -> Result<Vec<(PathBuf, Result<Option<Update>>)>> {
+    let repo = layout.repo();
+    let manifest = Manifest::load(repo)?;
+    let lockfile = Lockfile::load(repo)?;
+    let mut targets = Vec::new();
+    for bundle in declared(repo, &manifest)? {
+        if !repo.join(&bundle.path).join(".git").exists()
+            || matches!(bundle.pin, Some(Pin::Commit(_)))
+        {
+            continue;
+        }
+        targets.push(Target {
+            url: bundle.url,
+            branch: bundle.branch,
+            pin: bundle.pin,
+            locked: lockfile
+                .as_ref()
+                .and_then(|l| l.get(&bundle.path))
+                .map(|b| b.commit.clone()),
+            path: bundle.path,
+        });
+    }
+    let updates = parallel(&targets, jobs, |target| check_one(repo, target));
+    Ok(targets.into_iter().map(|t| t.path).zip(updates).collect())
+}
+
+fn check_one(repo: &Path, target: &Target) -> Result<Option<Update>> {
+    let dir = repo.join(&target.path);
+    let err = |e| Error::git2(&dir, e);
+    let checkout = Repository::open(&dir).map_err(err)?;
+    let mut remote = checkout.remote_anonymous(&target.url).map_err(err)?;
+    let (to, tag) = match &target.pin {
+        Some(Pin::Tag(current)) => {
+            remote
+                .fetch(&["+refs/tags/*:refs/tags/*"], None, None)
+                .map_err(err)?;
+            let Some(tag) = newer_tag(&checkout, current).map_err(err)? else {
+                return Ok(None);
+            };
+            let to = checkout
+                .revparse_single(&format!("refs/tags/{tag}"))
+                .and_then(|tag| tag.peel_to_commit())
+                .map_err(err)?;
+            (to.id(), Some(tag))
+        }
+        _ => {
+            remote
+                .fetch(&["+refs/heads/*:refs/remotes/origin/*"], None, None)
+                .map_err(err)?;
+            let branch = match &target.branch {
+                Some(branch) => branch.clone(),
+                None => {
+                    let default = remote.default_branch().map_err(err)?;
+                    let default = default.as_str().unwrap_or_default();
+                    default.trim_start_matches("refs/heads/").to_string()
+                }
+            };
+            let to = checkout
+                .refname_to_id(&format!("refs/remotes/origin/{branch}"))
+                .map_err(err)?;
+            (to, None)
+        }
+    };
+    let head = checkout
+        .head()
+        .map_err(err)?
+        .peel_to_commit()
+        .map_err(err)?;
+    // A locked commit the checkout does not have is compared as its head.
+    let from = target
+        .locked
+        .as_deref()
+        .and_then(|c| Oid::from_str(c).ok())
+        .filter(|&id| checkout.find_commit(id).is_ok())
+        .unwrap_or(head.id());
+    if from == to {
+        return Ok(None);
+    }
+
+    let mut walk = checkout.revwalk().map_err(err)?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)
+        .map_err(err)?;
+    walk.push(to).map_err(err)?;
+    walk.hide(from).map_err(err)?;
+    let mut commits = Vec::new();
+    for id in walk {
+        let commit = checkout.find_commit(id.map_err(err)?).map_err(err)?;
+        let message = String::from_utf8_lossy(commit.message_bytes());
+        commits.push(Commit {
+            id: commit.id().to_string(),
+            subject: message.lines().next().unwrap_or_default().to_string(),
+            breaking: is_breaking(&message),
+        });
+    }
+    Ok(Some(Update {
+        path: target.path.clone(),
+        url: target.url.clone(),
+        from: from.to_string(),
+        to: to.to_string(),
+        commits,
+        fast_forward: checkout.graph_descendant_of(to, from).map_err(err)?,
+        tag,
+    }))
+}
+
+/// The newest release tag in `checkout` that is a later version than
+/// `current`, if there is one. Release tags are dotted numbers with an
+/// optional `v` in front, so pre-releases such as `v2.0-rc1` are never
+/// offered, and nothing is if `current` is not a release tag itself.
+fn newer_tag(checkout: &Repository, current: &str) -> Result<Option<String>, git2::Error> {
+    let Some(current) = version(current) else {
+        return Ok(None);
+    };
+    let tags = checkout.tag_names(None)?;
+    Ok(tags
+        .iter()
+        .filter_map(|tag| tag.ok().flatten())
+        .filter_map(|tag| Some((version(tag)?, tag)))
+        .filter(|(version, _)| *version > current)
+        .max()
+        .map(|(_, tag)| tag.to_string()))
+}
+
+/// `v1.2.3` or `1.2.3` as `[1, 2, 3]`.
+fn version(tag: &str) -> Option<Vec<u64>> {
+    tag.strip_prefix(['v', 'V'])
+        .unwrap_or(tag)
+        .split('.')
+        .map(|part| part.parse().ok())
+        .collect()
+}
+
+/// `type!:` or `type(scope)!:` at the start of the subject, or a
+/// `BREAKING CHANGE:` footer.
+fn is_breaking(message: &str) -> bool {
+    let subject = message.lines().next().unwrap_or_default();
+    let bang = subject
+        .split_once(':')
+        .is_some_and(|(kind, _)| kind.ends_with('!') && !kind.contains(char::is_whitespace));
+    bang || message
+        .lines()
+        .any(|l| l.starts_with("BREAKING CHANGE:") || l.starts_with("BREAKING-CHANGE:"))
+}
+
+/// Checks out each of `updates` and records those bundles in
+/// `bundles.lock`. Where the repository records a commit for a submodule,
+/// the new one is staged, ready to commit. A plugin that follows a branch
+/// has its local branch moved on, and one pinned to a tag has the new tag
+/// written to the manifest. Plugins pinned to a commit are left alone.
+pub fn apply(layout: &Layout, updates: &[Update]) -> Result<Lockfile> {
+    let repo = layout.repo();
+    let bundles = declared(repo, &Manifest::load(repo)?)?;
+    let mut paths = Vec::new();
+    for update in updates {
+        let dir = repo.join(&update.path);
+        let err = |e| Error::git2(&dir, e);
+        let checkout = Repository::open(&dir).map_err(err)?;
+        let to = Oid::from_str(&update.to).map_err(err)?;
+        let checkout = Checkout {
+            repo: &checkout,
+            dir: &dir,
+            url: &update.url,
+        };
+        let pin = bundles
+            .iter()
+            .find(|b| b.path == update.path)
+            .and_then(|b| b.pin.as_ref());
+        match pin {
+            Some(Pin::Branch(branch)) => {
+                // The branch may well be `HEAD`, so the files are checked
+                // out before it is moved.
+                checkout.detach(to)?;
+                let reference = format!("refs/heads/{branch}");
+                checkout
+                    .repo
+                    .reference(&reference, to, true, "bundles update")
+                    .map_err(err)?;
+                checkout.repo.set_head(&reference).map_err(err)?;
+            }
+            Some(Pin::Tag(_)) => {
+                checkout.detach(to)?;
+                if let Some(tag) = &update.tag {
+                    Manifest::retag_plugin(repo, &update.path, tag)?;
+                }
+            }
+            Some(Pin::Commit(_)) => continue,
+            Some(Pin::Default) => {
+                checkout.detach(to)?;
+            }
+            None => {
+                checkout.detach(to)?;
+                if recorded(repo, &update.path)?.is_some() {
+                    git(repo, &["add", "--", &update.path.to_string_lossy()])?;
+                }
+            }
+        }
+        paths.push(update.path.clone());
+    }
+    lockfile::relock(layout, &paths)
+}
@@ -0,0 +1,198 @@
+//! Installing vim bundles without a network.
+//!
+//! The cache is a bare git repository. Since git stores objects by their
+//! hash it is content-addressed already: each vendored commit is kept
+//! alive by a `refs/pins/<commit>` ref, bundles that share history share
+//! objects, and vendoring the same commit twice does nothing.
+//!
+//! Which commit a bundle should be at comes from `bundles.lock` if it has
+//! the bundle, and otherwise from the commit the repository records for
+//! the submodule.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::bundles::{git, head, is_dirty, recorded, try_git};
+use crate::error::{Error, Result};
+use crate::fsutil::is_empty_dir;
+use crate::gitmodules::GitModules;
+use crate::layout::Layout;
+use crate::lockfile::Lockfile;
+use crate::manifest::Manifest;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// Checked out into an empty or missing directory.
+    Installed,
+    /// Moved from another commit.
+    Updated,
+    /// Already at the pinned commit.
+    Current,
+}
+
+/// A submodule and the commit it is pinned to.
+struct Pin {
+    /// Relative to the repository root.
+    path: PathBuf,
+    /// With the manifest's rewrites applied.
+    url: String,
+    commit: Option<String>,
+}
+
+fn pins(repo: &Path) -> Result<Vec<Pin>> {
+    let manifest = Manifest::load(repo)?;
+    let lockfile = Lockfile::load(repo)?;
+    let mut pins = Vec::new();
+    for module in &GitModules::load(repo)?.modules {
+        let path = PathBuf::from(module.require(repo, "path")?);
+        let url = module.require(repo, "url")?;
+        let locked = lockfile.as_ref().and_then(|l| l.get(&path));
+        let commit = match locked {
+            Some(locked) => Some(locked.commit.clone()),
+            None => recorded(repo, &path)?,
+        };
+        pins.push(Pin {
+            url: manifest.rewrite_url(url).unwrap_or_else(|| url.to_string()),
+            path,
+            commit,
+        });
+    }
+    Ok(pins)
+}
+
+/// Copies every bundle's pinned commit into the cache at `cache`, creating
+/// it if need be. A bundle is taken from its checkout when that has the
+/// commit, and fetched from its URL otherwise. A checked-out bundle with
+/// no pin is vendored at whatever it has checked out.
+///
+/// Returns each bundle's path and the commit vendored for it.
+pub fn vendor(layout: &Layout, cache: &Path) -> Result<Vec<(PathBuf, String)>> {
+    let repo = layout.repo();
+    let cache = &absolute(cache)?;
+    if fs::symlink_metadata(cache.join("HEAD")).is_err() {
+        fs::create_dir_all(cache).map_err(|e| Error::io(cache, e))?;
+        git(cache, &["init", "--quiet", "--bare"])?;
+    }
+    let mut vendored = Vec::new();
+    for pin in pins(repo)? {
+        let dir = repo.join(&pin.path);
+        let checkout = head(&dir)?;
+        let Some(commit) = pin.commit.or(checkout) else {
+            return Err(Error::NoPin(pin.path));
+        };
+        if !has_commit(cache, &commit)? {
+            let source = if has_commit(&dir, &commit)? {
+                dir.to_string_lossy().into_owned()
+            } else {
+                pin.url
+            };
+            git(cache, &["fetch", "--quiet", "--no-tags", &source, &commit])?;
+        }
+        git(cache, &["update-ref", &pin_ref(&commit), &commit])?;
+        vendored.push((pin.path, commit));
+    }
+    Ok(vendored)
+}
+
+/// Checks out every bundle at its pinned commit. Commits are taken from
+/// `cache` if one is given and it has them; unless `offline`, anything
+/// else is fetched from the bundle's URL.
+///
+/// A bundle with local changes, or a directory in the way that is not a
+/// checkout, is refused before anything is changed.
+pub fn install(
+    layout: &Layout,
+    cache: Option<&Path>,
+    offline: bool,
+) -> Result<Vec<(PathBuf, Outcome)>> {
+    let repo = layout.repo();
+    let cache = cache.map(absolute).transpose()?;
+    let cache = cache.as_deref();
+    let mut planned = Vec::new();
+    for pin in pins(repo)? {
+        let Some(commit) = pin.commit.clone() else {
+            return Err(Error::NoPin(pin.path));
+        };
+        let dir = repo.join(&pin.path);
+        let outcome = match head(&dir)? {
+            Some(head) if head == commit => Outcome::Current,
+            Some(_) => {
+                if is_dirty(&dir)? {
+                    return Err(Error::BundleInTheWay {
+                        path: pin.path,
+                        reason: "it has uncommitted changes".to_string(),
+                    });
+                }
+                Outcome::Updated
+            }
+            None if is_empty_dir(&dir)? => Outcome::Installed,
+            None => {
+                return Err(Error::BundleInTheWay {
+                    path: pin.path,
+                    reason: "it is not empty and not a git checkout".to_string(),
+                })
+            }
+        };
+        let cached = match cache {
+            Some(cache) => has_commit(cache, &commit)?,
+            None => false,
+        };
+        if outcome != Outcome::Current && !cached && offline {
+            return Err(Error::NotInCache {
+                path: pin.path,
+                commit,
+            });
+        }
+        planned.push((pin, commit, outcome, cached));
+    }
+
+    let mut results = Vec::new();
+    for (pin, commit, outcome, cached) in planned {
+        let dir = repo.join(&pin.path);
+        if outcome == Outcome::Installed {
+            fs::create_dir_all(&dir).map_err(|e| Error::io(&dir, e))?;
+            git(&dir, &["init", "--quiet"])?;
+            git(&dir, &["remote", "add", "origin", &pin.url])?;
+        }
+        if outcome != Outcome::Current && !has_commit(&dir, &commit)? {
+            match cache.filter(|_| cached) {
+                Some(cache) => {
+                    let cache = cache.to_string_lossy();
+                    git(
+                        &dir,
+                        &["fetch", "--quiet", "--no-tags", &cache, &pin_ref(&commit)],
+                    )?
+                }
+                None => git(&dir, &["fetch", "--quiet", "--no-tags", &pin.url, &commit])?,
+            };
+        }
+        if outcome != Outcome::Current {
+            git(&dir, &["checkout", "--quiet", "--detach", &commit])?;
+        }
+        results.push((pin.path, outcome));
+    }
+    Ok(results)
+}
+
+/// Git runs in each bundle's directory, so a relative cache path would
+/// mean something else there.
+fn absolute(path: &Path) -> Result<PathBuf> {
+    std::path::absolute(path).map_err(|e| Error::io(path, e))
+}
+
+fn pin_ref(commit: &str) -> String {
+    format!("refs/pins/{commit}")
+}
+
+/// Whether the repository at `dir`, bare or not, has `commit`. Anything
+/// else, including a directory inside some other repository, does not.
+fn has_commit(dir: &Path, commit: &str) -> Result<bool> {
+    let is_repo = ["HEAD", ".git"]
+        .iter()
+        .any(|name| fs::symlink_metadata(dir.join(name)).is_ok());
+    if !is_repo {
+        return Ok(false);
+    }
+    let object = format!("{commit}^{{commit}}");
+    Ok(try_git(dir, &["cat-file", "-e", &object])?.is_some())
+}
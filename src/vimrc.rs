@@ -0,0 +1,194 @@
+//! The parts of `.vimrc` that decide which bundles pathogen loads.
+//!
+//! `g:pathogen_disabled` is read from, and written back to, a single
+//! `let g:pathogen_disabled = [...]` line of quoted names. If there is no
+//! such line, one is added just before the call to `pathogen#infect()`,
+//! since pathogen only sees what was set before it runs. Anything else
+//! that touches the variable is refused rather than guessed at.
+
+use std::fs;
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::fsutil::is_missing;
+
+pub const VIMRC: &str = ".vimrc";
+
+const DISABLED: &str = "g:pathogen_disabled";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Vimrc {
+    lines: Vec<String>,
+    trailing_newline: bool,
+    /// The `let g:pathogen_disabled` line and its names.
+    disabled: Option<(usize, Vec<String>)>,
+    /// The line calling `pathogen#infect()`, and its argument.
+    infect: Option<(usize, Option<String>)>,
+}
+
+impl Vimrc {
+    /// Reads the `.vimrc` in `repo`. A missing one is empty.
+    pub fn load(repo: &Path) -> Result<Self> {
+        let path = repo.join(VIMRC);
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if is_missing(&e) => String::new(),
+            Err(e) => return Err(Error::io(&path, e)),
+        };
+        Vimrc::parse(&text).map_err(|(line, message)| Error::Vimrc {
+            path,
+            line: line + 1,
+            message,
+        })
+    }
+
+    pub fn save(&self, repo: &Path) -> Result<()> {
+        let path = repo.join(VIMRC);
+        fs::write(&path, self.to_string()).map_err(|e| Error::io(&path, e))
+    }
+
+    /// Parses `.vimrc` text, failing with a zero-based line number.
+    pub fn parse(text: &str) -> std::result::Result<Self, (usize, String)> {
+        let mut vimrc = Vimrc {
+            lines: text.lines().map(str::to_string).collect(),
+            trailing_newline: text.is_empty() || text.ends_with('\n'),
+            ..Default::default()
+        };
+        for (i, line) in vimrc.lines.iter().enumerate() {
+            let code = line.trim_start();
+            if code.starts_with('"') {
+                continue;
+            }
+            if code.contains(DISABLED) {
+                if vimrc.disabled.is_some() {
+                    return Err((i, format!("{DISABLED} is set more than once")));
+                }
+                let (names, _) = parse_let(code).ok_or_else(|| {
+                    (
+                        i,
+                        format!(
+                            "set {DISABLED} with one `let {DISABLED} = [...]` line of quoted names"
+                        ),
+                    )
+                })?;
+                vimrc.disabled = Some((i, names));
+            }
+            if vimrc.infect.is_none() {
+                if let Some((_, call)) = code.split_once("pathogen#infect(") {
+                    let argument = call
+                        .split_once(')')
+                        .and_then(|(arg, _)| unquote(arg.trim()));
+                    vimrc.infect = Some((i, argument));
+                }
+            }
+        }
+        Ok(vimrc)
+    }
+
+    /// The bundle names in `g:pathogen_disabled`.
+    pub fn disabled(&self) -> &[String] {
+        self.disabled.as_ref().map_or(&[], |(_, names)| names)
+    }
+
+    /// What `pathogen#infect()` is called with, `bundle` if nothing, or
+    /// `None` if it is not called at all.
+    pub fn infect_source(&self) -> Option<&str> {
+        let (_, argument) = self.infect.as_ref()?;
+        Some(argument.as_deref().unwrap_or("bundle"))
+    }
+
+    /// Sets `g:pathogen_disabled` to `names`, on its existing line or a
+    /// new one before the call to `pathogen#infect()`, or at the end if
+    /// there is none. A comment after the existing list is kept.
+    pub fn set_disabled(&mut self, names: &[String]) {
+        let list: Vec<String> = names
+            .iter()
+            .map(|n| format!("'{}'", n.replace('\'', "''")))
+            .collect();
+        let statement = format!("let {DISABLED} = [{}]", list.join(", "));
+        let index = match (&self.disabled, &self.infect) {
+            (Some((index, _)), _) => *index,
+            (None, Some((index, _))) => {
+                let index = *index;
+                let infect = &self.lines[index];
+                let indent = infect[..infect.len() - infect.trim_start().len()].to_string();
+                self.lines.insert(index, indent);
+                if let Some((infect, _)) = &mut self.infect {
+                    *infect += 1;
+                }
+                index
+            }
+            (None, None) => {
+                self.lines.push(String::new());
+                self.lines.len() - 1
+            }
+        };
+        let line = &self.lines[index];
+        let indent = &line[..line.len() - line.trim_start().len()];
+        let after = parse_let(line.trim_start()).map_or("", |(_, after)| after);
+        self.lines[index] = format!("{indent}{statement}{after}");
+        self.disabled = Some((index, names.to_vec()));
+    }
+}
+
+impl std::fmt::Display for Vimrc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.lines.join("\n"))?;
+        if self.trailing_newline && !self.lines.is_empty() {
+            f.write_str("\n")?;
+        }
+        Ok(())
+    }
+}
+
+/// The names in `let g:pathogen_disabled = ['a', "b"]`, with nothing but
+/// a comment after it, and what follows the list.
+fn parse_let(code: &str) -> Option<(Vec<String>, &str)> {
+    let rest = code.strip_prefix("let")?.trim_start();
+    let rest = rest.strip_prefix(DISABLED)?.trim_start();
+    let mut rest = rest.strip_prefix('=')?.trim_start().strip_prefix('[')?;
+    let mut names = Vec::new();
+    loop {
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix(']') {
+            let comment = after.trim_start();
+            return (comment.is_empty() || comment.starts_with('"')).then_some((names, after));
+        }
+        let (name, after) = quoted(rest)?;
+        names.push(name);
+        rest = after.trim_start();
+        rest = rest.strip_prefix(',').unwrap_or(rest);
+    }
+}
+
+/// The whole of `text` as one quoted string.
+fn unquote(text: &str) -> Option<String> {
+    match quoted(text)? {
+        (value, "") => Some(value),
+        _ => None,
+    }
+}
+
+/// A single- or double-quoted string at the start of `text`, and what
+/// follows it.
+fn quoted(text: &str) -> Option<(String, &str)> {
+    let mut chars = text.char_indices();
+    let (_, quote) = chars.next().filter(|(_, c)| *c == '\'' || *c == '"')?;
+    let mut value = String::new();
+    while let Some((i, c)) = chars.next() {
+        match (quote, c) {
+            ('\'', '\'') => {
+                if text[i + 1..].starts_with('\'') {
+                    chars.next();
+                    value.push('\'');
+                } else {
+                    return Some((value, &text[i + 1..]));
+                }
+            }
+            ('"', '"') => return Some((value, &text[i + 1..])),
+            ('"', '\\') => value.push(chars.next()?.1),
+            _ => value.push(c),
+        }
+    }
+    None
+}
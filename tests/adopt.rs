@@ -0,0 +1,170 @@
+mod common;
+
+use std::fs;
+use std::os::unix::fs::{symlink, MetadataExt, PermissionsExt};
+use std::path::Path;
+
+use common::{write, Fixture};
+use dotfiles::adopt::{adopt, Outcome};
+use dotfiles::install::install;
+use dotfiles::installed::Installed;
+use dotfiles::manifest::Manifest;
+use dotfiles::Error;
+
+#[test]
+fn adopts_a_new_file() {
+    let fx = Fixture::new();
+    write(&fx.home.join(".inputrc"), "set editing-mode vi\n");
+    let layout = fx.layout();
+
+    let report = adopt(&layout, &fx.home_path(".inputrc")).unwrap();
+
+    assert_eq!(report.results, [(".inputrc".into(), Outcome::Added)]);
+    assert!(report.tracked);
+    assert!(report.backup.is_some());
+    assert_eq!(
+        fs::read_to_string(fx.repo.join(".inputrc")).unwrap(),
+        "set editing-mode vi\n"
+    );
+    assert_eq!(
+        fs::read_link(fx.home.join(".inputrc")).unwrap(),
+        fx.repo_path(".inputrc")
+    );
+    let manifest = Manifest::load(&fx.repo).unwrap();
+    assert_eq!(manifest.entries[0].path, Path::new(".inputrc"));
+    assert!(Installed::load(&layout)
+        .unwrap()
+        .files
+        .contains_key(Path::new(".inputrc")));
+}
+
+#[test]
+fn adopts_a_directory_and_keeps_existing_manifest_text() {
+    let fx = Fixture::new();
+    write(
+        &fx.repo.join("dotfiles.toml"),
+        "# hand-written\nstrategy = \"symlink\"\n",
+    );
+    let layout = fx.layout();
+    install(&layout).unwrap();
+    write(
+        &fx.home.join(".vim/after/ftplugin/go.vim"),
+        "setlocal noet\n",
+    );
+
+    let report = adopt(&layout, Path::new(".vim/after")).unwrap();
+
+    assert_eq!(
+        report.results,
+        [
+            (".vim/after/ftplugin/go.vim".into(), Outcome::Added),
+            (".vim/after/syntax/html.vim".into(), Outcome::AlreadyManaged),
+        ]
+    );
+    assert_eq!(
+        fs::read_to_string(fx.repo.join("dotfiles.toml")).unwrap(),
+        "# hand-written\nstrategy = \"symlink\"\n\n[[entry]]\npath = \".vim/after\"\n"
+    );
+    assert!(fx.repo.join(".vim/after/ftplugin/go.vim").is_file());
+    assert!(
+        fs::symlink_metadata(fx.home.join(".vim/after/ftplugin/go.vim"))
+            .unwrap()
+            .file_type()
+            .is_symlink()
+    );
+}
+
+#[test]
+fn pulls_edits_to_a_copy_back_into_the_repository() {
+    let fx = Fixture::new();
+    write(
+        &fx.repo.join("dotfiles.toml"),
+        "[[entry]]\npath = \".tmux.conf\"\nstrategy = \"copy\"\n",
+    );
+    let layout = fx.layout();
+    install(&layout).unwrap();
+    write(&fx.home.join(".tmux.conf"), "set -g prefix C-b\n");
+
+    let report = adopt(&layout, Path::new(".tmux.conf")).unwrap();
+
+    assert_eq!(report.results, [(".tmux.conf".into(), Outcome::Updated)]);
+    assert!(!report.tracked);
+    // An edited copy is ours, so there is nothing to back up.
+    assert_eq!(report.backup, None);
+    assert_eq!(
+        fs::read_to_string(fx.repo.join(".tmux.conf")).unwrap(),
+        "set -g prefix C-b\n"
+    );
+    let record = &Installed::load(&layout).unwrap().files[Path::new(".tmux.conf")];
+    assert!(record.hash.is_some());
+    assert!(!fs::symlink_metadata(fx.home.join(".tmux.conf"))
+        .unwrap()
+        .file_type()
+        .is_symlink());
+}
+
+#[test]
+fn refuses_foreign_symlinks_without_changing_anything() {
+    let fx = Fixture::new();
+    write(&fx.home.join(".config/a"), "a\n");
+    symlink("/etc/hostname", fx.home.join(".config/b")).unwrap();
+    let layout = fx.layout();
+
+    let err = adopt(&layout, Path::new(".config")).unwrap_err();
+
+    assert!(matches!(err, Error::CannotAdopt { .. }), "{err}");
+    assert!(!fx.repo.join(".config").exists());
+    assert!(!fx.repo.join("dotfiles.toml").exists());
+}
+
+#[test]
+fn refuses_paths_outside_home_and_non_dotfiles() {
+    let fx = Fixture::new();
+    write(&fx.home.join("notes.txt"), "hi\n");
+    let layout = fx.layout();
+
+    for path in [fx.repo_path(".vimrc"), fx.home_path("notes.txt")] {
+        let err = adopt(&layout, &path).unwrap_err();
+        assert!(matches!(err, Error::CannotAdopt { .. }), "{err}");
+    }
+}
+
+#[test]
+fn puts_the_repository_back_when_the_install_fails() {
+    let fx = Fixture::new();
+    write(&fx.repo.join("dotfiles.toml"), "# hand-written\n");
+    // An executable file in the repository has to stay executable.
+    let html = fx.repo_path(".vim/after/syntax/html.vim");
+    fs::set_permissions(&html, fs::Permissions::from_mode(0o755)).unwrap();
+    write(
+        &fx.home.join(".vim/after/syntax/html.vim"),
+        "syn case match\n",
+    );
+    write(
+        &fx.home.join(".vim/after/ftplugin/go.vim"),
+        "setlocal noet\n",
+    );
+    // The transaction cannot write its journal where a file is in the way.
+    write(&fx.home.join(".local/state/dotfiles"), "");
+
+    assert!(adopt(&fx.layout(), Path::new(".vim/after")).is_err());
+
+    assert_eq!(
+        fs::read_to_string(fx.repo.join("dotfiles.toml")).unwrap(),
+        "# hand-written\n"
+    );
+    assert_eq!(
+        fs::read_to_string(fx.repo_path(".vim/after/syntax/html.vim")).unwrap(),
+        "syn case ignore\n"
+    );
+    assert_eq!(fs::metadata(&html).unwrap().mode() & 0o777, 0o755);
+    assert!(!fx.repo_path(".vim/after/ftplugin").exists());
+    assert_eq!(
+        fs::read_to_string(fx.home.join(".vim/after/syntax/html.vim")).unwrap(),
+        "syn case match\n"
+    );
+    assert_eq!(
+        fs::read_to_string(fx.home.join(".vim/after/ftplugin/go.vim")).unwrap(),
+        "setlocal noet\n"
+    );
+}
@@ -0,0 +1,143 @@
+mod common;
+
+use std::fs::{self, File};
+use std::os::unix::fs::{symlink, MetadataExt, PermissionsExt};
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
+
+use common::{write, Fixture};
+use dotfiles::backup::{BackupKind, BackupStore};
+use dotfiles::install::install;
+use dotfiles::installed::Installed;
+use dotfiles::uninstall::uninstall;
+use dotfiles::Error;
+
+#[test]
+fn restore_puts_originals_back_exactly() {
+    let fx = Fixture::new();
+    let vimrc = fx.home.join(".vimrc");
+    write(&vimrc, "hand-edited\n");
+    fs::set_permissions(&vimrc, fs::Permissions::from_mode(0o600)).unwrap();
+    let mtime = UNIX_EPOCH + Duration::from_secs(1_400_000_000);
+    File::open(&vimrc).unwrap().set_modified(mtime).unwrap();
+    symlink("/somewhere/else/tmux.conf", fx.home.join(".tmux.conf")).unwrap();
+
+    let layout = fx.layout();
+    let id = install(&layout)
+        .unwrap()
+        .backup
+        .expect("a backup was taken");
+
+    let store = BackupStore::new(&layout);
+    let manifest = store.load(&id).unwrap();
+    assert_eq!(manifest.entries.len(), 2);
+    let tmux = manifest
+        .entries
+        .iter()
+        .find(|e| e.path.ends_with(".tmux.conf"))
+        .unwrap();
+    assert_eq!(tmux.kind, BackupKind::Symlink);
+
+    let restored = store.restore(&layout, &id).unwrap();
+    assert_eq!(restored.len(), 2);
+
+    let meta = fs::symlink_metadata(&vimrc).unwrap();
+    assert!(meta.is_file());
+    assert_eq!(meta.mode() & 0o777, 0o600);
+    assert_eq!(meta.modified().unwrap(), mtime);
+    assert_eq!(fs::read_to_string(&vimrc).unwrap(), "hand-edited\n");
+    assert_eq!(
+        fs::read_link(fx.home.join(".tmux.conf")).unwrap(),
+        std::path::Path::new("/somewhere/else/tmux.conf")
+    );
+    assert!(store.list().unwrap().is_empty());
+}
+
+#[test]
+fn restore_refuses_to_clobber_unmanaged_files() {
+    let fx = Fixture::new();
+    write(&fx.home.join(".vimrc"), "original\n");
+    let layout = fx.layout();
+    let id = install(&layout).unwrap().backup.unwrap();
+
+    fs::remove_file(fx.home.join(".vimrc")).unwrap();
+    write(&fx.home.join(".vimrc"), "written since\n");
+
+    let err = BackupStore::new(&layout).restore(&layout, &id).unwrap_err();
+    assert!(matches!(err, Error::RestoreBlocked(_)));
+    assert_eq!(
+        fs::read_to_string(fx.home.join(".vimrc")).unwrap(),
+        "written since\n"
+    );
+}
+
+#[test]
+fn restore_replaces_copies_and_rendered_templates() {
+    let fx = Fixture::new();
+    write(
+        &fx.repo.join(".gitconfig"),
+        "[user]\n  name = {{ user.name }}\n",
+    );
+    write(
+        &fx.repo.join("dotfiles.toml"),
+        "[[entry]]\npath = \".tmux.conf\"\nstrategy = \"copy\"\n\n\
+         [[entry]]\npath = \".gitconfig\"\nstrategy = \"template\"\n",
+    );
+    write(
+        &fx.home.join(".config/dotfiles/values.toml"),
+        "[user]\nname = \"Someone\"\n",
+    );
+    write(&fx.home.join(".tmux.conf"), "original tmux\n");
+    write(&fx.home.join(".gitconfig"), "original git\n");
+    let layout = fx.layout();
+    let id = install(&layout).unwrap().backup.unwrap();
+    assert_eq!(
+        fs::read_to_string(fx.home.join(".gitconfig")).unwrap(),
+        "[user]\n  name = Someone\n"
+    );
+
+    let mut restored = BackupStore::new(&layout).restore(&layout, &id).unwrap();
+    restored.sort();
+    assert_eq!(restored, [Path::new(".gitconfig"), Path::new(".tmux.conf")]);
+    assert_eq!(
+        fs::read_to_string(fx.home.join(".tmux.conf")).unwrap(),
+        "original tmux\n"
+    );
+    assert_eq!(
+        fs::read_to_string(fx.home.join(".gitconfig")).unwrap(),
+        "original git\n"
+    );
+}
+
+#[test]
+fn restore_forgets_the_restored_files() {
+    let fx = Fixture::new();
+    write(&fx.home.join(".vimrc"), "original\n");
+    let layout = fx.layout();
+    let id = install(&layout).unwrap().backup.unwrap();
+
+    BackupStore::new(&layout).restore(&layout, &id).unwrap();
+
+    let installed = Installed::load(&layout).unwrap();
+    assert!(!installed.files.contains_key(Path::new(".vimrc")));
+    assert!(installed.files.contains_key(Path::new(".tmux.conf")));
+    let report = uninstall(&layout).unwrap();
+    assert!(report
+        .results
+        .iter()
+        .all(|(path, _)| path != Path::new(".vimrc")));
+    assert_eq!(
+        fs::read_to_string(fx.home.join(".vimrc")).unwrap(),
+        "original\n"
+    );
+}
+
+#[test]
+fn unknown_backup_ids_are_reported() {
+    let fx = Fixture::new();
+    let layout = fx.layout();
+    let err = BackupStore::new(&layout)
+        .restore(&layout, "19700101T000000Z")
+        .unwrap_err();
+    assert!(matches!(err, Error::BackupNotFound(_)));
+}
@@ -0,0 +1,240 @@
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+
+use common::{git, git_repo, with_bundles, write, Fixture};
+use dotfiles::bundles::{disable, enable, fix_urls, status, Mechanism, State};
+use dotfiles::gitmodules::GitModules;
+use dotfiles::pathogen::resolve;
+use dotfiles::Error;
+
+const GITMODULES: &str = "\
+[submodule \".vim/bundle/vim-fugitive\"]
+    path = .vim/bundle/vim-fugitive
+    url = git://github.com/tpope/vim-fugitive.git
+# themes
+[submodule \".vim/bundle/vim-tomorrow-theme\"]
+\tpath = .vim/bundle/vim-tomorrow-theme
+\turl=git://github.com/chriskempson/vim-tomorrow-theme.git ; pinned
+";
+
+#[test]
+fn parses_and_edits_gitmodules_in_place() {
+    let mut modules = GitModules::parse(GITMODULES).unwrap();
+    assert_eq!(modules.to_string(), GITMODULES);
+    let names: Vec<_> = modules.modules.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(
+        names,
+        [".vim/bundle/vim-fugitive", ".vim/bundle/vim-tomorrow-theme"]
+    );
+    let theme = &modules.modules[1];
+    assert_eq!(
+        theme.url(),
+        Some("git://github.com/chriskempson/vim-tomorrow-theme.git")
+    );
+
+    let line = theme.fields.iter().find(|f| f.key == "url").unwrap().line;
+    modules.set_value(
+        line,
+        "https://github.com/chriskempson/vim-tomorrow-theme.git",
+    );
+    assert_eq!(
+        modules.to_string(),
+        GITMODULES.replace(
+            "\turl=git://github.com/chriskempson/vim-tomorrow-theme.git ; pinned",
+            "\turl=https://github.com/chriskempson/vim-tomorrow-theme.git ; pinned"
+        )
+    );
+
+    let mut modules = GitModules::parse("[submodule \"a\"]\n\turl = foo # mirror\n").unwrap();
+    let line = modules.modules[0].fields[0].line;
+    modules.set_value(line, "bar");
+    assert_eq!(
+        modules.to_string(),
+        "[submodule \"a\"]\n\turl = bar # mirror\n"
+    );
+    assert_eq!(modules.modules[0].url(), Some("bar"));
+
+    let err = GitModules::parse("path = x\n").unwrap_err();
+    assert_eq!(err, (1, "key outside of any section".to_string()));
+}
+
+#[test]
+fn reports_each_submodule_checkout() {
+    let fx = Fixture::new();
+    let upstream = fx.repo.parent().unwrap().join("upstream");
+    git_repo(&upstream, &[("plugin/a.vim", "\" a\n")]);
+    git_repo(&fx.repo, &[]);
+    let url = upstream.to_str().unwrap();
+    for name in ["clean", "dirty", "moved", "missing", "empty"] {
+        let path = format!(".vim/bundle/{name}");
+        git(&fx.repo, &["submodule", "add", "-q", url, &path]);
+    }
+    git(&fx.repo, &["commit", "-q", "-m", "bundles"]);
+
+    write(
+        &fx.repo_path(".vim/bundle/dirty/plugin/a.vim"),
+        "\" edited\n",
+    );
+    let moved = fx.repo_path(".vim/bundle/moved");
+    write(&moved.join("plugin/b.vim"), "\" b\n");
+    git(&moved, &["add", "-A"]);
+    git(&moved, &["commit", "-q", "-m", "more"]);
+    fs::remove_dir_all(fx.repo_path(".vim/bundle/missing")).unwrap();
+    fs::remove_dir_all(fx.repo_path(".vim/bundle/empty")).unwrap();
+    fs::create_dir(fx.repo_path(".vim/bundle/empty")).unwrap();
+
+    let report = status(&fx.layout()).unwrap();
+    let states: Vec<_> = report
+        .bundles
+        .iter()
+        .map(|b| (b.path.to_str().unwrap(), b.state.to_string(), b.dirty))
+        .collect();
+    assert_eq!(
+        states,
+        [
+            (".vim/bundle/clean", "ok".to_string(), false),
+            (".vim/bundle/dirty", "ok".to_string(), true),
+            (".vim/bundle/moved", "moved".to_string(), false),
+            (".vim/bundle/missing", "missing".to_string(), false),
+            (".vim/bundle/empty", "uninitialized".to_string(), false),
+        ]
+    );
+    let State::Moved { recorded, head } = &report.bundles[2].state else {
+        unreachable!()
+    };
+    assert_eq!(*head, git(&moved, &["rev-parse", "HEAD"]));
+    assert_ne!(recorded, head);
+    assert_eq!(report.vendored, [PathBuf::from(".vim/bundle/nginx")]);
+    assert!(!report.is_clean());
+}
+
+#[test]
+fn checkout_without_recorded_commit_is_unrecorded() {
+    let fx = Fixture::new();
+    write(
+        &fx.repo_path(".gitmodules"),
+        "[submodule \"x\"]\n\tpath = .vim/bundle/x\n\turl = file:///nowhere\n",
+    );
+    git_repo(
+        &fx.repo_path(".vim/bundle/x"),
+        &[("plugin/x.vim", "\" x\n")],
+    );
+
+    let report = status(&fx.layout()).unwrap();
+    assert!(matches!(report.bundles[0].state, State::Unrecorded { .. }));
+    assert_eq!(report.vendored, [PathBuf::from(".vim/bundle/nginx")]);
+}
+
+#[test]
+fn fix_urls_rewrites_dead_protocols_in_place() {
+    let fx = Fixture::new();
+    let text = format!(
+        "{GITMODULES}[submodule \"jst\"]\n\tpath = .vim/bundle/vim-jst\n\turl = git@github.com:briancollins/vim-jst.git\n"
+    );
+    write(&fx.repo_path(".gitmodules"), &text);
+
+    let fix = fix_urls(&fx.layout(), true).unwrap();
+    assert_eq!(fix.changes.len(), 3);
+    assert!(fix
+        .diff
+        .contains("+\turl=https://github.com/chriskempson/vim-tomorrow-theme.git ; pinned\n"));
+    assert_eq!(
+        fs::read_to_string(fx.repo_path(".gitmodules")).unwrap(),
+        text
+    );
+
+    fix_urls(&fx.layout(), false).unwrap();
+    let expected = text
+        .replace("git://github.com/", "https://github.com/")
+        .replace("git@github.com:", "https://github.com/");
+    assert_eq!(
+        fs::read_to_string(fx.repo_path(".gitmodules")).unwrap(),
+        expected
+    );
+    assert!(fix_urls(&fx.layout(), false).unwrap().changes.is_empty());
+}
+
+#[test]
+fn fix_urls_follows_manifest_rules() {
+    let fx = Fixture::new();
+    write(&fx.repo_path(".gitmodules"), GITMODULES);
+    write(
+        &fx.repo_path("dotfiles.toml"),
+        "[[bundles.rewrite]]\n\
+         from = \"git://\"\n\
+         to = \"https://mirror.example/\"\n\
+         \n\
+         [[bundles.rewrite]]\n\
+         from = \"git://github.com/tpope/\"\n\
+         to = \"https://github.com/tpope/\"\n",
+    );
+
+    let fix = fix_urls(&fx.layout(), false).unwrap();
+    let urls: Vec<_> = fix.changes.iter().map(|c| c.to.as_str()).collect();
+    assert_eq!(
+        urls,
+        [
+            "https://github.com/tpope/vim-fugitive.git",
+            "https://mirror.example/github.com/chriskempson/vim-tomorrow-theme.git",
+        ]
+    );
+}
+
+#[test]
+fn disable_and_enable_use_one_mechanism_at_a_time() {
+    let fx = Fixture::new();
+    write(
+        &fx.repo_path(".vimrc"),
+        "set nocompatible\n  call pathogen#infect()\n",
+    );
+    let nginx = fx.repo_path(".vim/bundle/nginx");
+    let loaded = |fx: &Fixture| {
+        let dirs = resolve(&fx.layout()).unwrap().dirs();
+        dirs.contains(&nginx.display().to_string())
+    };
+    assert!(loaded(&fx));
+
+    assert!(disable(&fx.layout(), "nginx", Mechanism::Listed).unwrap());
+    assert_eq!(
+        fs::read_to_string(fx.repo_path(".vimrc")).unwrap(),
+        "set nocompatible\n  let g:pathogen_disabled = ['nginx']\n  call pathogen#infect()\n"
+    );
+    assert!(!loaded(&fx));
+    assert!(!disable(&fx.layout(), "nginx", Mechanism::Listed).unwrap());
+
+    assert!(disable(&fx.layout(), "nginx", Mechanism::Renamed).unwrap());
+    assert!(fx.repo_path(".vim/bundle/nginx~").is_dir());
+    assert!(fs::read_to_string(fx.repo_path(".vimrc"))
+        .unwrap()
+        .contains("let g:pathogen_disabled = []\n"));
+    assert!(!loaded(&fx));
+
+    assert!(enable(&fx.layout(), "nginx~").unwrap());
+    assert!(nginx.is_dir());
+    assert!(loaded(&fx));
+    assert!(!enable(&fx.layout(), "nginx").unwrap());
+}
+
+#[test]
+fn disable_refuses_what_it_cannot_do_safely() {
+    let fx = Fixture::new();
+    with_bundles(&fx, &["a"]);
+    assert!(matches!(
+        disable(&fx.layout(), "a", Mechanism::Listed),
+        Err(Error::NoPathogen(_))
+    ));
+
+    write(&fx.repo_path(".vimrc"), "execute pathogen#infect()\n");
+    assert!(matches!(
+        disable(&fx.layout(), "a", Mechanism::Renamed),
+        Err(Error::CannotRename { .. })
+    ));
+    assert!(matches!(
+        disable(&fx.layout(), "missing", Mechanism::Listed),
+        Err(Error::UnknownBundle(_))
+    ));
+    assert!(disable(&fx.layout(), "a", Mechanism::Listed).unwrap());
+    assert!(fx.repo_path(".vim/bundle/a").is_dir());
+}
@@ -0,0 +1,119 @@
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use tempfile::TempDir;
+
+use dotfiles::Layout;
+
+/// A throwaway repository and home directory side by side.
+pub struct Fixture {
+    _tmp: TempDir,
+    pub repo: PathBuf,
+    pub home: PathBuf,
+}
+
+impl Fixture {
+    /// A repository shaped like the real one, with an empty home.
+    pub fn new() -> Self {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo = tmp.path().join("repo");
+        let home = tmp.path().join("home");
+        fs::create_dir_all(&home).unwrap();
+
+        write(&repo.join(".vimrc"), "set nocompatible\n");
+        write(&repo.join(".tmux.conf"), "set -g prefix C-a\n");
+        write(&repo.join(".gitconfig"), "[user]\n  name = Someone\n");
+        write(&repo.join(".dircolors"), "TERM xterm\n");
+        write(&repo.join(".vim/autoload/pathogen.vim"), "\" pathogen\n");
+        write(
+            &repo.join(".vim/after/syntax/html.vim"),
+            "syn case ignore\n",
+        );
+        write(
+            &repo.join(".vim/bundle/nginx/syntax/nginx.vim"),
+            "\" nginx\n",
+        );
+        write(&repo.join(".gitmodules"), "");
+        write(&repo.join(".gitignore"), "target/\n");
+        write(&repo.join("README.md"), "dotfiles!\n");
+
+        Fixture {
+            _tmp: tmp,
+            repo,
+            home,
+        }
+    }
+
+    pub fn layout(&self) -> Layout {
+        Layout::new(&self.repo, &self.home).unwrap()
+    }
+
+    pub fn home_path(&self, rel: &str) -> PathBuf {
+        self.layout().home().join(rel)
+    }
+
+    pub fn repo_path(&self, rel: &str) -> PathBuf {
+        self.layout().repo().join(rel)
+    }
+}
+
+pub fn write(path: &Path, contents: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+}
+
+/// Runs git in `dir` with a fixed identity, panicking if it fails, and
+/// returns its standard output.
+pub fn git(dir: &Path, args: &[&str]) -> String {
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args([
+            "-c",
+            "user.name=Test",
+            "-c",
+            "user.email=test@example.com",
+            "-c",
+            "protocol.file.allow=always",
+            "-c",
+            "init.defaultBranch=main",
+        ])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "git {args:?} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap().trim().to_string()
+}
+
+/// Creates a git repository at `dir` holding `files`, committed.
+pub fn git_repo(dir: &Path, files: &[(&str, &str)]) {
+    for (path, contents) in files {
+        write(&dir.join(path), contents);
+    }
+    fs::create_dir_all(dir).unwrap();
+    git(dir, &["init", "-q"]);
+    git(dir, &["add", "-A"]);
+    git(dir, &["commit", "-q", "-m", "initial"]);
+}
+
+/// Makes the fixture's repository a git repository with each of `names`
+/// added as a submodule under `.vim/bundle`, all cloned from one upstream
+/// repository, and returns the upstream's path.
+pub fn with_bundles(fx: &Fixture, names: &[&str]) -> PathBuf {
+    let upstream = fx.repo.parent().unwrap().join("upstream");
+    git_repo(&upstream, &[("plugin/a.vim", "\" a\n")]);
+    git_repo(&fx.repo, &[]);
+    let url = format!("file://{}", upstream.display());
+    for name in names {
+        let path = format!(".vim/bundle/{name}");
+        git(&fx.repo, &["submodule", "add", "-q", &url, &path]);
+    }
+    git(&fx.repo, &["commit", "-q", "-m", "bundles"]);
+    upstream
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use std::path::{Path, PathBuf};
+
+use common::{write, Fixture};
+use dotfiles::conflicts::{conflicts, defined_commands, Overlap};
+
+#[test]
+fn finds_overlapping_bundles_in_load_order() {
+    let fx = Fixture::new();
+    write(
+        &fx.repo_path(".gitmodules"),
+        "[submodule \"vim-surround\"]\n\
+         \tpath = .vim/bundle/vim-surround\n\
+         \turl = git://github.com/tpope/vim-surround.git\n\
+         [submodule \"surround\"]\n\
+         \tpath = .vim/bundle/surround\n\
+         \turl = git://github.com/vim-scripts/surround.vim.git\n\
+         [submodule \"fugitive\"]\n\
+         \tpath = .vim/bundle/fugitive\n\
+         \turl = git@github.com:tpope/vim-fugitive.git\n\
+         [submodule \"vim-fugitive\"]\n\
+         \tpath = .vim/bundle/vim-fugitive\n\
+         \turl = https://github.com/tpope/vim-fugitive\n\
+         [submodule \"vim-matchit\"]\n\
+         \tpath = .vim/bundle/vim-matchit\n\
+         \turl = git://github.com/edsono/vim-matchit.git\n",
+    );
+    write(&fx.repo_path(".vimrc"), "execute pathogen#infect()\n");
+    let script = "command! -nargs=1 Surround call s:go()\ncom -buffer Local echo\n";
+    write(
+        &fx.repo_path(".vim/bundle/surround/plugin/surround.vim"),
+        script,
+    );
+    write(
+        &fx.repo_path(".vim/bundle/vim-surround/plugin/surround.vim"),
+        script,
+    );
+
+    let found: Vec<_> = conflicts(&fx.layout())
+        .unwrap()
+        .into_iter()
+        .map(|c| (c.overlap, c.bundles))
+        .collect();
+    let paths = |names: &[&str]| -> Vec<PathBuf> {
+        names
+            .iter()
+            .map(|n| Path::new(".vim/bundle").join(n))
+            .collect()
+    };
+    assert_eq!(
+        found,
+        [
+            (
+                Overlap::SameUpstream("github.com/tpope/vim-fugitive".into()),
+                paths(&["fugitive", "vim-fugitive"]),
+            ),
+            (
+                Overlap::SameName("surround".into()),
+                paths(&["surround", "vim-surround"]),
+            ),
+            (
+                Overlap::SameFile("plugin/surround.vim".into()),
+                paths(&["surround", "vim-surround"]),
+            ),
+            (
+                Overlap::SameCommand("Surround".into()),
+                paths(&["surround", "vim-surround"]),
+            ),
+            (Overlap::ShippedWithVim("matchit"), paths(&["vim-matchit"])),
+        ]
+    );
+}
+
+#[test]
+fn reports_the_first_bundle_as_winner() {
+    let fx = Fixture::new();
+    write(&fx.repo_path(".vimrc"), "execute pathogen#infect()\n");
+    write(&fx.repo_path(".vim/bundle/nginx/plugin/x.vim"), "\n");
+    write(&fx.repo_path(".vim/bundle/Nginx/plugin/x.vim"), "\n");
+
+    let found = conflicts(&fx.layout()).unwrap();
+    assert_eq!(found.len(), 2);
+    assert_eq!(
+        found[1].to_string(),
+        ".vim/bundle/Nginx, .vim/bundle/nginx all provide plugin/x.vim; \
+         .vim/bundle/Nginx loads first"
+    );
+}
+
+#[test]
+fn leaves_out_bundles_pathogen_disables() {
+    let fx = Fixture::new();
+    write(
+        &fx.repo_path(".vimrc"),
+        "let g:pathogen_disabled = ['disabled']\nexecute pathogen#infect()\n",
+    );
+    write(
+        &fx.repo_path(".gitmodules"),
+        "[submodule \"disabled\"]\n\
+         \tpath = .vim/bundle/disabled\n\
+         \turl = https://github.com/tpope/vim-surround\n\
+         [submodule \"surround\"]\n\
+         \tpath = .vim/bundle/surround\n\
+         \turl = https://github.com/tpope/vim-surround\n",
+    );
+    for bundle in ["disabled", "old~", "nginx", "vim-nginx"] {
+        write(
+            &fx.repo_path(&format!(".vim/bundle/{bundle}/plugin/x.vim")),
+            "\n",
+        );
+    }
+
+    let found = conflicts(&fx.layout()).unwrap();
+    let shown: Vec<_> = found.iter().map(|c| c.to_string()).collect();
+    assert_eq!(
+        shown,
+        [
+            ".vim/bundle/nginx, .vim/bundle/vim-nginx are all called nginx; \
+             .vim/bundle/nginx loads first",
+            ".vim/bundle/nginx, .vim/bundle/vim-nginx all provide plugin/x.vim; \
+             .vim/bundle/nginx loads first",
+        ]
+    );
+}
+
+#[test]
+fn parses_command_definitions() {
+    let script = "\
+        \" command! Commented\n\
+        :command! -nargs=* -complete=file Gedit call s:edit()\n\
+        comm Short echo\n\
+        command -buffer Local echo\n\
+        compiler gcc\n\
+        command! lowercase echo\n";
+    assert_eq!(defined_commands(script), ["Gedit", "Short"]);
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+
+use common::{git, with_bundles, write, Fixture};
+use dotfiles::bundles::status;
+use dotfiles::helptags::{generate, helptags};
+use dotfiles::Error;
+
+// The expected tags files are what `:helptags` in Vim 9.0 writes for the
+// same doc directories.
+
+#[test]
+fn generate_matches_vims_tags_files() {
+    let fx = Fixture::new();
+    let doc = fx.repo_path(".vim/bundle/a/doc");
+    write(
+        &doc.join("a.txt"),
+        "*a.txt*  Plugin — for tests\n\
+         \n\
+         *a-intro* *a/path* *a\\star* *no space*\n\
+         Example: >\n\
+         \t*not-a-tag*\n\
+         <\n\
+         |a-intro| and *a-end*\n",
+    );
+    write(&doc.join("b.txt"), "*b.txt*\t*b* — b\n");
+    write(&doc.join("a.frx"), "*a.txt*  Greffon\n*a-intro*\n");
+
+    let tags_files = generate(&doc).unwrap();
+    let names: Vec<_> = tags_files.iter().map(|t| t.name.as_str()).collect();
+    // Languages come in the order their first help file sorts.
+    assert_eq!(names, ["tags-fr", "tags"]);
+    assert_eq!(
+        String::from_utf8(tags_files[1].contents.clone()).unwrap(),
+        "!_TAG_FILE_ENCODING\tutf-8\t//\n\
+         a-end\ta.txt\t/*a-end*\n\
+         a-intro\ta.txt\t/*a-intro*\n\
+         a.txt\ta.txt\t/*a.txt*\n\
+         a/path\ta.txt\t/*a\\/path*\n\
+         a\\star\ta.txt\t/*a\\\\star*\n\
+         b\tb.txt\t/*b*\n\
+         b.txt\tb.txt\t/*b.txt*\n"
+    );
+    assert_eq!(
+        String::from_utf8(tags_files[0].contents.clone()).unwrap(),
+        "a-intro\ta.frx\t/*a-intro*\n\
+         a.txt\ta.frx\t/*a.txt*\n"
+    );
+
+    // A UTF-8 help file next to a plain one is refused, not half-tagged.
+    write(&doc.join("c.txt"), "*c.txt*\n");
+    let err = generate(&doc).unwrap_err();
+    assert!(matches!(err, Error::HelpEncoding(path) if path == doc.join("c.txt")));
+}
+
+#[test]
+fn helptags_writes_each_bundle_and_reports_duplicates() {
+    let fx = Fixture::new();
+    with_bundles(&fx, &["a", "b"]);
+    write(&fx.repo_path(".vimrc"), "execute pathogen#infect()\n");
+    write(
+        &fx.repo_path(".vim/bundle/a/doc/a.txt"),
+        "*a.txt* *shared*\n",
+    );
+    write(
+        &fx.repo_path(".vim/bundle/b/doc/b.txt"),
+        "*b.txt* *shared*\n",
+    );
+    // An empty doc directory is left alone, as pathogen#helptags() does.
+    fs::create_dir_all(fx.repo_path(".vim/bundle/nginx/doc")).unwrap();
+
+    let report = helptags(&fx.layout()).unwrap();
+    assert_eq!(
+        report.written,
+        [
+            PathBuf::from(".vim/bundle/a/doc/tags"),
+            PathBuf::from(".vim/bundle/b/doc/tags"),
+        ]
+    );
+    assert!(report.failed.is_empty());
+    let duplicates: Vec<_> = report.duplicates.iter().map(|d| d.to_string()).collect();
+    assert_eq!(
+        duplicates,
+        ["*shared* is in .vim/bundle/a/doc/a.txt, .vim/bundle/b/doc/b.txt"]
+    );
+    assert!(!fx.repo_path(".vim/bundle/nginx/doc/tags").exists());
+
+    // Generated tags do not make a submodule dirty, its help files do.
+    let dirty: Vec<_> = status(&fx.layout())
+        .unwrap()
+        .bundles
+        .iter()
+        .map(|b| b.dirty)
+        .collect();
+    assert_eq!(dirty, [true, true]);
+    for name in ["a", "b"] {
+        let bundle = fx.repo_path(&format!(".vim/bundle/{name}"));
+        git(&bundle, &["add", "doc/*.txt"]);
+        git(&bundle, &["commit", "-q", "-m", "docs"]);
+    }
+    let report = status(&fx.layout()).unwrap();
+    assert!(report.bundles.iter().all(|b| !b.dirty));
+}
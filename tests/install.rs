@@ -0,0 +1,109 @@
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use common::{write, Fixture};
+use dotfiles::discover::discover;
+use dotfiles::fsutil::FileKind;
+use dotfiles::install::{install, Outcome, Refusal};
+
+fn outcome_of<'a>(report: &'a dotfiles::install::Report, path: &str) -> &'a Outcome {
+    &report
+        .results
+        .iter()
+        .find(|(e, _)| e.path == Path::new(path))
+        .unwrap_or_else(|| panic!("{path} not in report"))
+        .1
+}
+
+#[test]
+fn discovers_dotfiles_and_vim_tree_but_not_repo_metadata() {
+    let fx = Fixture::new();
+    write(
+        &fx.repo.join(".vim/bundle/nginx/.git"),
+        "gitdir: ../../.git/modules/nginx\n",
+    );
+
+    let paths: Vec<PathBuf> = discover(&fx.layout())
+        .unwrap()
+        .into_iter()
+        .map(|e| e.path)
+        .collect();
+    let expected: Vec<PathBuf> = [
+        ".dircolors",
+        ".gitconfig",
+        ".tmux.conf",
+        ".vim/after/syntax/html.vim",
+        ".vim/autoload/pathogen.vim",
+        ".vim/bundle/nginx/syntax/nginx.vim",
+        ".vimrc",
+    ]
+    .iter()
+    .map(PathBuf::from)
+    .collect();
+    assert_eq!(paths, expected);
+}
+
+#[test]
+fn links_everything_into_an_empty_home() {
+    let fx = Fixture::new();
+    let report = install(&fx.layout()).unwrap();
+
+    assert!(report.results.iter().all(|(_, o)| *o == Outcome::Linked));
+    assert_eq!(
+        fs::read_link(fx.home_path(".vim/after/syntax/html.vim")).unwrap(),
+        fx.repo_path(".vim/after/syntax/html.vim")
+    );
+    assert!(fs::symlink_metadata(fx.home_path(".vim")).unwrap().is_dir());
+}
+
+#[test]
+fn second_install_skips_existing_links() {
+    let fx = Fixture::new();
+    install(&fx.layout()).unwrap();
+    let report = install(&fx.layout()).unwrap();
+
+    assert!(report
+        .results
+        .iter()
+        .all(|(_, o)| *o == Outcome::AlreadyLinked));
+}
+
+#[test]
+fn backs_up_foreign_files_but_refuses_directories() {
+    let fx = Fixture::new();
+    write(&fx.home.join(".vimrc"), "my own vimrc\n");
+    fs::create_dir(fx.home.join(".tmux.conf")).unwrap();
+
+    let report = install(&fx.layout()).unwrap();
+
+    assert_eq!(outcome_of(&report, ".vimrc"), &Outcome::Replaced);
+    assert_eq!(
+        outcome_of(&report, ".tmux.conf"),
+        &Outcome::Refused(Refusal::Exists(FileKind::Dir))
+    );
+    assert_eq!(outcome_of(&report, ".gitconfig"), &Outcome::Linked);
+    assert!(report.backup.is_some());
+    assert_eq!(
+        fs::read_link(fx.home.join(".vimrc")).unwrap(),
+        fx.repo_path(".vimrc")
+    );
+}
+
+#[test]
+fn refuses_when_a_parent_is_a_file() {
+    let fx = Fixture::new();
+    write(&fx.home.join(".vim/after"), "not a directory\n");
+
+    let report = install(&fx.layout()).unwrap();
+
+    assert_eq!(
+        outcome_of(&report, ".vim/after/syntax/html.vim"),
+        &Outcome::Refused(Refusal::ParentNotDir(fx.home_path(".vim/after")))
+    );
+    assert_eq!(
+        outcome_of(&report, ".vim/autoload/pathogen.vim"),
+        &Outcome::Linked
+    );
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use std::path::{Path, PathBuf};
+
+use common::{write, Fixture};
+use dotfiles::lint::{lint, lint_script, Rule};
+
+fn found(path: &str, text: &str) -> Vec<(usize, usize, Rule)> {
+    lint_script(Path::new(path), text)
+        .into_iter()
+        .map(|d| (d.line, d.col, d.rule))
+        .collect()
+}
+
+#[test]
+fn reports_each_rule_where_vim_would_see_it() {
+    let script = "\
+\" set tabstop=2
+set ts=2 sw=2 \" | set et
+setlocal et | set sts=2
+if 1 | se ai | endif
+syn match x 'a|b' | set sw=4
+";
+    assert_eq!(
+        found(".vim/after/syntax/javascript.vim", script),
+        [
+            (2, 1, Rule::GlobalSet),
+            (3, 15, Rule::GlobalSet),
+            (4, 8, Rule::GlobalSet),
+            (5, 21, Rule::GlobalSet),
+        ]
+    );
+    assert_eq!(found(".vimrc", script), []);
+
+    let vimrc = "\
+augroup strip
+  autocmd!
+  autocmd BufWritePre * call s:Strip()
+augroup END
+autocmd strip BufRead * set ft=x | echo 'x'
+au! BufRead,BufNewFile *.json
+  \\ set filetype=json
+function! s:Strip() abort \" {{{
+endfunction
+fun <SID>Go(a, b) range \"abort
+endfun
+function GetFileEncoding
+let &l:tabstop = 2 | let &t_Co = 256
+let &g:tabstopp = 2
+";
+    assert_eq!(
+        found(".vimrc", vimrc),
+        [
+            (6, 1, Rule::AutocmdOutsideAugroup),
+            (10, 1, Rule::MissingAbort),
+            (14, 5, Rule::UnknownOption),
+        ]
+    );
+    let messages: Vec<_> = lint_script(Path::new(".vimrc"), vimrc)
+        .iter()
+        .map(|d| d.to_string())
+        .collect();
+    assert_eq!(
+        messages[1],
+        ".vimrc:10:1: missing-abort: function <SID>Go() has no `abort`"
+    );
+    assert_eq!(
+        messages[2],
+        ".vimrc:14:5: unknown-option: there is no option called `tabstopp`"
+    );
+}
+
+#[test]
+fn lints_the_repositorys_own_scripts_only() {
+    let fx = Fixture::new();
+    write(
+        &fx.repo_path(".vimrc"),
+        "set nocompatible\nau BufRead *.md\n      \\ setlocal spell\n",
+    );
+    write(&fx.repo_path(".vim/ftplugin/go.vim"), "set noexpandtab\n");
+    write(
+        &fx.repo_path(".vim/plugin/mine.vim"),
+        "function Mine()\nendfunction\n",
+    );
+    write(
+        &fx.repo_path(".vim/bundle/nginx/ftplugin/nginx.vim"),
+        "set et\n",
+    );
+    write(
+        &fx.repo_path(".vim/autoload/pathogen.vim"),
+        "function! pathogen#x()\n",
+    );
+
+    let diagnostics: Vec<_> = lint(&fx.layout())
+        .unwrap()
+        .into_iter()
+        .map(|d| (d.path, d.line, d.rule))
+        .collect();
+    assert_eq!(
+        diagnostics,
+        [
+            (PathBuf::from(".vimrc"), 2, Rule::AutocmdOutsideAugroup),
+            (PathBuf::from(".vim/ftplugin/go.vim"), 1, Rule::GlobalSet),
+            (PathBuf::from(".vim/plugin/mine.vim"), 1, Rule::MissingAbort),
+        ]
+    );
+}
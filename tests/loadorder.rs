@@ -0,0 +1,133 @@
+mod common;
+
+use std::path::Path;
+
+use common::{write, Fixture};
+use dotfiles::loadorder::{analyze, load_order, Section};
+use dotfiles::pathogen::Runtimepath;
+
+fn headings(sections: &[Section]) -> Vec<String> {
+    sections.iter().map(Section::heading).collect()
+}
+
+fn relative(root: &Path, section: &Section) -> Vec<String> {
+    section
+        .files
+        .iter()
+        .map(|f| f.path.strip_prefix(root).unwrap().display().to_string())
+        .collect()
+}
+
+// The orders are the ones Vim 9.0 sources the same files in.
+
+#[test]
+fn orders_scripts_and_finds_overrides_and_extensions() {
+    let fx = Fixture::new();
+    let root = &fx.home;
+    for file in [
+        "a/plugin/x.vim",
+        "a/plugin/x/z.vim",
+        "a/plugin/x-y/q.vim",
+        "a/plugin/.hidden.vim",
+        "b/plugin/a.vim",
+        "a/syntax/html.vim",
+        "a/syntax/html/x.vim",
+        "b/syntax/html.vim",
+        "a/after/syntax/html.vim",
+        "a/ftplugin/html_z.vim",
+        "a/ftplugin/html/a.vim",
+        "a/ftplugin/html.vim",
+        "a/autoload/util.vim",
+        "b/autoload/util.vim",
+        "b/autoload/b/deep.vim",
+    ] {
+        write(&root.join(file), "");
+    }
+    let rtp = Runtimepath::new(&format!("{0}/a,{0}/b,{0}/a/after", root.display()));
+
+    let sections = analyze(&rtp);
+    assert_eq!(
+        headings(&sections),
+        [
+            "plugin/",
+            "ftplugin/",
+            "syntax/",
+            "after/syntax/",
+            "autoload/"
+        ]
+    );
+    assert_eq!(
+        relative(root, &sections[0]),
+        [
+            "a/plugin/x/z.vim",
+            "a/plugin/x-y/q.vim",
+            "a/plugin/x.vim",
+            "b/plugin/a.vim"
+        ]
+    );
+    assert_eq!(
+        relative(root, &sections[1]),
+        [
+            "a/ftplugin/html.vim",
+            "a/ftplugin/html_z.vim",
+            "a/ftplugin/html/a.vim"
+        ]
+    );
+    assert!(sections[1].files.iter().all(|f| f.name == "html"));
+
+    let syntax = &sections[2].files;
+    let main = root.join("a/syntax/html.vim");
+    assert_eq!(
+        relative(root, &sections[2]),
+        [
+            "a/syntax/html.vim",
+            "a/syntax/html/x.vim",
+            "b/syntax/html.vim"
+        ]
+    );
+    assert_eq!(syntax[1].extends.as_ref(), Some(&main));
+    assert_eq!(syntax[2].overridden_by.as_ref(), Some(&main));
+    assert_eq!(syntax[0].overrides, [root.join("b/syntax/html.vim")]);
+    assert_eq!(
+        syntax[0].extended_by,
+        [
+            root.join("a/syntax/html/x.vim"),
+            root.join("a/after/syntax/html.vim")
+        ]
+    );
+    assert_eq!(sections[3].files[0].extends.as_ref(), Some(&main));
+
+    let autoload = &sections[4].files;
+    assert_eq!(
+        relative(root, &sections[4]),
+        [
+            "a/autoload/util.vim",
+            "b/autoload/b/deep.vim",
+            "b/autoload/util.vim"
+        ]
+    );
+    assert_eq!(
+        autoload[2].overridden_by,
+        Some(root.join("a/autoload/util.vim"))
+    );
+    assert_eq!(autoload[1].overridden_by, None);
+}
+
+#[test]
+fn after_syntax_in_the_repository_extends_the_bundles() {
+    let fx = Fixture::new();
+    write(&fx.repo_path(".vimrc"), "execute pathogen#infect()\n");
+    write(&fx.repo_path(".vim/bundle/html5/syntax/html.vim"), "");
+
+    let sections = load_order(&fx.layout()).unwrap();
+    let after = sections
+        .iter()
+        .find(|s| s.heading() == "after/syntax/")
+        .unwrap();
+    let html = &after.files[0];
+    assert_eq!(html.path, fx.repo_path(".vim/after/syntax/html.vim"));
+    assert_eq!(
+        html.extends,
+        Some(fx.repo_path(".vim/bundle/html5/syntax/html.vim"))
+    );
+}
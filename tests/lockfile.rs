@@ -0,0 +1,80 @@
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+
+use common::{git, with_bundles, write, Fixture};
+use dotfiles::lockfile::{lock, verify, Lockfile, Mismatch};
+
+#[test]
+fn lock_records_url_commit_and_tree() {
+    let fx = Fixture::new();
+    let upstream = with_bundles(&fx, &["a"]);
+
+    let lockfile = lock(&fx.layout()).unwrap();
+    assert_eq!(Lockfile::load(&fx.repo).unwrap(), Some(lockfile.clone()));
+    let [bundle] = &lockfile.bundles[..] else {
+        panic!("{lockfile:?}")
+    };
+    assert_eq!(bundle.path, PathBuf::from(".vim/bundle/a"));
+    assert_eq!(bundle.url, format!("file://{}", upstream.display()));
+    assert_eq!(bundle.commit, git(&upstream, &["rev-parse", "HEAD"]));
+    assert!(bundle.tree.as_ref().unwrap().starts_with("sha256:"));
+
+    assert_eq!(verify(&fx.layout()).unwrap(), []);
+}
+
+#[test]
+fn verify_reports_each_kind_of_difference() {
+    let fx = Fixture::new();
+    with_bundles(&fx, &["edited", "moved", "gone", "untracked"]);
+    lock(&fx.layout()).unwrap();
+
+    write(
+        &fx.repo_path(".vim/bundle/edited/plugin/a.vim"),
+        "\" edited\n",
+    );
+    let moved = fx.repo_path(".vim/bundle/moved");
+    write(&moved.join("plugin/b.vim"), "\" b\n");
+    git(&moved, &["add", "-A"]);
+    git(&moved, &["commit", "-q", "-m", "more"]);
+    let head = git(&moved, &["rev-parse", "HEAD"]);
+    fs::remove_dir_all(fx.repo_path(".vim/bundle/gone")).unwrap();
+    // Untracked files, such as generated help tags, do not count.
+    write(
+        &fx.repo_path(".vim/bundle/untracked/doc/tags"),
+        "a\ta.txt\t/*a*\n",
+    );
+
+    let mismatches = verify(&fx.layout()).unwrap();
+    assert_eq!(mismatches.len(), 3, "{mismatches:?}");
+    assert_eq!(mismatches[0], (".vim/bundle/edited".into(), Mismatch::Tree));
+    assert!(
+        matches!(&mismatches[1], (p, Mismatch::Commit { head: h, .. }) if p.ends_with("moved") && *h == head)
+    );
+    assert_eq!(
+        mismatches[2],
+        (".vim/bundle/gone".into(), Mismatch::NotCheckedOut)
+    );
+    assert!(!mismatches[2].1.is_failure());
+}
+
+#[test]
+fn lock_records_the_commit_of_bundles_that_are_not_checked_out() {
+    let fx = Fixture::new();
+    let upstream = with_bundles(&fx, &["a", "b"]);
+    fs::remove_dir_all(fx.repo_path(".vim/bundle/a")).unwrap();
+
+    let lockfile = lock(&fx.layout()).unwrap();
+    let [a, b] = &lockfile.bundles[..] else {
+        panic!("{lockfile:?}")
+    };
+    assert_eq!(a.commit, git(&upstream, &["rev-parse", "HEAD"]));
+    assert_eq!(a.tree, None);
+    assert!(b.tree.is_some());
+    assert_eq!(Lockfile::load(&fx.repo).unwrap(), Some(lockfile.clone()));
+    assert_eq!(
+        verify(&fx.layout()).unwrap(),
+        [(".vim/bundle/a".into(), Mismatch::NotCheckedOut)]
+    );
+}
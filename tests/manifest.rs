@@ -0,0 +1,228 @@
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use common::{write, Fixture};
+use dotfiles::discover::discover;
+use dotfiles::host::Host;
+use dotfiles::install::install;
+use dotfiles::manifest::Manifest;
+use dotfiles::Error;
+
+fn host(os: &str, name: &str) -> Host {
+    Host {
+        os: os.into(),
+        name: name.into(),
+    }
+}
+
+fn discovered(fx: &Fixture, host: Host) -> Vec<(PathBuf, PathBuf)> {
+    discover(&fx.layout().with_host(host))
+        .unwrap()
+        .into_iter()
+        .map(|e| (e.path, e.source))
+        .collect()
+}
+
+fn load_error(text: &str) -> String {
+    let fx = Fixture::new();
+    write(&fx.repo.join("dotfiles.toml"), text);
+    let err = Manifest::load(&fx.repo).unwrap_err();
+    assert!(matches!(err, Error::Manifest { .. }), "{err}");
+    let prefix = format!("{}:", fx.repo.join("dotfiles.toml").display());
+    err.to_string().strip_prefix(&prefix).unwrap().to_string()
+}
+
+#[test]
+fn entries_pick_destinations_hosts_and_ignores() {
+    let fx = Fixture::new();
+    write(&fx.repo.join("bin/tmux-session"), "#!/bin/sh\n");
+    write(&fx.repo.join("bin/tmux-session.orig"), "#!/bin/sh\n");
+    write(
+        &fx.repo.join(".vim/.netrwhist"),
+        "let g:netrw_dirhistmax = 10\n",
+    );
+    write(
+        &fx.repo.join("dotfiles.toml"),
+        r#"
+implicit = false
+ignore = [".netrwhist"]
+
+[[entry]]
+path = ".vim"
+
+[[entry]]
+path = ".vim/bundle"
+os = ["macos"]
+
+[[entry]]
+path = "bin"
+dest = ".local/bin"
+hosts = ["web-*"]
+ignore = ["*.orig"]
+"#,
+    );
+
+    let pairs = |list: &[(&str, &str)]| -> Vec<(PathBuf, PathBuf)> {
+        list.iter().map(|(d, s)| (d.into(), s.into())).collect()
+    };
+    assert_eq!(
+        discovered(&fx, host("linux", "web-1")),
+        pairs(&[
+            (".local/bin/tmux-session", "bin/tmux-session"),
+            (".vim/after/syntax/html.vim", ".vim/after/syntax/html.vim"),
+            (".vim/autoload/pathogen.vim", ".vim/autoload/pathogen.vim"),
+        ])
+    );
+    assert_eq!(
+        discovered(&fx, host("macos", "laptop")),
+        pairs(&[
+            (".vim/after/syntax/html.vim", ".vim/after/syntax/html.vim"),
+            (".vim/autoload/pathogen.vim", ".vim/autoload/pathogen.vim"),
+            (
+                ".vim/bundle/nginx/syntax/nginx.vim",
+                ".vim/bundle/nginx/syntax/nginx.vim"
+            ),
+        ])
+    );
+}
+
+#[test]
+fn installs_to_the_entry_destination() {
+    let fx = Fixture::new();
+    write(&fx.repo.join("bin/tmux-session"), "#!/bin/sh\n");
+    write(
+        &fx.repo.join("dotfiles.toml"),
+        "[[entry]]\npath = \"bin\"\ndest = \".local/bin\"\n",
+    );
+
+    install(&fx.layout()).unwrap();
+
+    assert_eq!(
+        fs::read_link(fx.home.join(".local/bin/tmux-session")).unwrap(),
+        fx.repo_path("bin/tmux-session")
+    );
+    // Implicit discovery still picks up the root dot-files.
+    assert!(fx.home.join(".vimrc").exists());
+}
+
+#[test]
+fn two_sources_for_one_destination_are_rejected() {
+    let fx = Fixture::new();
+    write(&fx.repo.join("tmux/.tmux.conf"), "set -g prefix C-b\n");
+    write(
+        &fx.repo.join("dotfiles.toml"),
+        "[[entry]]\npath = \"tmux\"\ndest = \".\"\n",
+    );
+    // `.` is not a valid destination, let alone a duplicate one.
+    assert!(matches!(
+        Manifest::load(&fx.repo),
+        Err(Error::Manifest { line: 3, .. })
+    ));
+
+    write(
+        &fx.repo.join("dotfiles.toml"),
+        "[[entry]]\npath = \"tmux/.tmux.conf\"\ndest = \".tmux.conf\"\n",
+    );
+    let err = discover(&fx.layout()).unwrap_err();
+    assert!(
+        matches!(err, Error::DuplicateDest { ref dest, .. } if dest == Path::new(".tmux.conf"))
+    );
+}
+
+#[test]
+fn errors_point_at_the_offending_line() {
+    assert_eq!(
+        load_error("[[entry]]\npath = \".vimrc\"\nstrategy = \"copy\"\nsymlink = true\n"),
+        "4:1: unknown field `symlink`, expected one of `path`, `dest`, `strategy`, `os`, `hosts`, `ignore`"
+    );
+    assert_eq!(
+        load_error("[[entry]]\npath = \".vimrc\"\nstrategy = \"rsync\"\n"),
+        "3:12: unknown variant `rsync`, expected one of `symlink`, `copy`, `hardlink`, `template`"
+    );
+    assert_eq!(
+        load_error("\n[[entry]]\npath = \"../.vimrc\"\n"),
+        "3:8: path must be a relative path without `.` or `..`"
+    );
+    assert_eq!(
+        load_error("[[entry]]\npath = \".zshrc\"\n"),
+        "2:8: no such file or directory in the repository"
+    );
+    assert_eq!(
+        load_error("[[entry]]\npath = \".vimrc\"\nos = [\"linux\", \"plan9\"]\n"),
+        "3:16: unknown operating system \"plan9\"; expected one of linux, macos, freebsd, \
+         openbsd, netbsd, dragonfly, solaris, illumos, android, windows"
+    );
+    assert_eq!(
+        load_error("[[entry]]\npath = \".vim\"\n\n[[entry]]\npath = \".vim\"\n"),
+        "5:8: .vim is already listed on line 2"
+    );
+    assert!(load_error("ignore = [\"[z-a\"]\n").starts_with("1:11: bad pattern: "));
+}
+
+#[test]
+fn plugins_get_names_paths_and_pins() {
+    let fx = Fixture::new();
+    write(
+        &fx.repo.join("dotfiles.toml"),
+        r#"
+[[plugin]]
+url = "https://github.com/tpope/vim-fugitive.git"
+tag = "v3.7"
+
+[[plugin]]
+url = "git@github.com:vim-scripts/surround.vim.git"
+name = "surround"
+commit = "abc1234"
+
+[[plugin]]
+url = "https://github.com/tpope/vim-pathogen"
+path = ".vim/vim-pathogen"
+branch = "master"
+"#,
+    );
+    let plugins: Vec<_> = Manifest::load(&fx.repo)
+        .unwrap()
+        .plugins
+        .into_iter()
+        .map(|p| (p.name, p.path, p.pin.to_string()))
+        .collect();
+    assert_eq!(
+        plugins,
+        [
+            (
+                "vim-fugitive".to_string(),
+                PathBuf::from(".vim/bundle/vim-fugitive"),
+                "tag v3.7".to_string()
+            ),
+            (
+                "surround".to_string(),
+                PathBuf::from(".vim/bundle/surround"),
+                "commit abc1234".to_string()
+            ),
+            (
+                "vim-pathogen".to_string(),
+                PathBuf::from(".vim/vim-pathogen"),
+                "branch master".to_string()
+            ),
+        ]
+    );
+
+    assert_eq!(
+        load_error("[[plugin]]\nurl = \"x\"\nbranch = \"main\"\ntag = \"v1\"\n"),
+        "4:7: pin a plugin with only one of branch, tag and commit"
+    );
+    assert_eq!(
+        load_error("[[plugin]]\nurl = \"x\"\ncommit = \"main\"\n"),
+        "3:10: commit must be 7 to 40 hexadecimal digits"
+    );
+    assert_eq!(
+        load_error("[[plugin]]\nurl = \"x\"\nname = \"../x\"\n"),
+        "3:8: \"../x\" is not a valid plugin name; give one with `name`"
+    );
+    assert_eq!(
+        load_error("[[plugin]]\nurl = \"a/x\"\n\n[[plugin]]\nurl = \"b/x.git\"\n"),
+        "5:7: .vim/bundle/x is already cloned by the plugin on line 2"
+    );
+}
@@ -0,0 +1,162 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{write, Fixture};
+use dotfiles::pathogen::{join, legacy_join, split, Runtimepath};
+
+/// Bundles in `vimfiles/bundle` that pathogen treats in every way it can,
+/// and a system-wide one under `vim/vimfiles/bundle`. Returns the default
+/// runtimepath for them, with the `d` bundle disabled.
+fn bundles(root: &Path) -> Runtimepath {
+    let vimfiles = root.join(".vim");
+    for dir in [
+        "a/after",
+        "B",
+        "b~/after",
+        ".hidden",
+        "c.o/after",
+        "d/after",
+        "x,y",
+        "after",
+    ] {
+        fs::create_dir_all(vimfiles.join("bundle").join(dir)).unwrap();
+    }
+    fs::create_dir_all(vimfiles.join("after")).unwrap();
+    write(&vimfiles.join("bundle/file"), "");
+    fs::create_dir_all(root.join("vim/vimfiles/bundle/z/after")).unwrap();
+
+    let mut rtp = Runtimepath::vim_default(&vimfiles, &root.join("vim"), &root.join("rt"));
+    rtp.disabled = vec!["d".to_string()];
+    rtp
+}
+
+// The expected runtimepaths are what Vim 9.0 makes of the same directories
+// with `.vim/autoload/pathogen.vim`.
+
+#[test]
+fn infect_appends_each_entrys_bundles() {
+    let fx = Fixture::new();
+    let root = fx.home.display().to_string();
+    let mut rtp = bundles(&fx.home);
+
+    rtp.infect("bundle");
+    let v = format!("{root}/.vim");
+    let expected = [
+        v.clone(),
+        format!("{v}/bundle/B"),
+        format!("{v}/bundle/a"),
+        format!("{v}/bundle/after"),
+        format!("{v}/bundle/x\\,y"),
+        format!("{v}/bundle/c.o"),
+        format!("{root}/vim/vimfiles"),
+        format!("{root}/vim/vimfiles/bundle/z"),
+        format!("{root}/rt"),
+        format!("{root}/vim/vimfiles/bundle/z/after"),
+        format!("{root}/vim/vimfiles/after"),
+        format!("{v}/bundle/a/after"),
+        format!("{v}/bundle/c.o/after"),
+        format!("{v}/after"),
+    ];
+    assert_eq!(rtp.option(), expected.join(","));
+    assert_eq!(rtp.dirs()[4], format!("{v}/bundle/x,y"));
+
+    // A second call with the same name does nothing.
+    let before = rtp.clone();
+    assert!(!rtp.append_all_bundles("bundle"));
+    assert_eq!(rtp, before);
+}
+
+#[test]
+fn infect_with_a_path_prepends_its_subdirectories() {
+    let fx = Fixture::new();
+    let root = fx.home.display().to_string();
+    let mut rtp = bundles(&fx.home);
+
+    rtp.infect(&format!("{root}/.vim/bundle"));
+    let v = format!("{root}/.vim");
+    let expected = [
+        format!("{v}/bundle/B"),
+        format!("{v}/bundle/a"),
+        format!("{v}/bundle/after"),
+        format!("{v}/bundle/x\\,y"),
+        format!("{v}/bundle/c.o"),
+        v.clone(),
+        format!("{root}/vim/vimfiles"),
+        format!("{root}/rt"),
+        format!("{root}/vim/vimfiles/after"),
+        format!("{v}/after"),
+        format!("{v}/bundle/a/after"),
+        format!("{v}/bundle/c.o/after"),
+    ];
+    assert_eq!(rtp.option(), expected.join(","));
+}
+
+#[test]
+fn split_and_join_escape_as_vim_does() {
+    let cases: &[(&str, &[&str])] = &[
+        (",,a,,b,,", &["a", "", "b", ""]),
+        ("a,", &["a"]),
+        (",", &[]),
+        (r"a\,b,c\\,d\\\,e", &["a,b", r"c\", r"d\,e"]),
+        (r"x\\\\,y\q", &[r"x\\", r"y\q"]),
+    ];
+    for (option, items) in cases {
+        assert_eq!(split(option), *items, "{option}");
+    }
+
+    let list: Vec<String> = ["a,b", "c d", r"e\f", r"g\,h", r"i\\j", "", r"k\ l"]
+        .map(String::from)
+        .to_vec();
+    assert_eq!(join(&list), r"a\,b,c d,e\f,g\\\,h,i\\\j,,k\ l");
+    assert_eq!(legacy_join(&list), r"a\,b,c\ d,e\f,g\\\,h,i\\\j,,k\\\ l");
+    assert_eq!(join(&["".to_string(), "a".to_string()]), ",a");
+}
+
+#[test]
+fn find_and_complete_runtime_files_as_vedit_does() {
+    let fx = Fixture::new();
+    let root = fx.home.display().to_string();
+    for file in [
+        ".vim/bundle/a/syntax/nginx.vim",
+        ".vim/bundle/a/after/syntax/nginx.vim",
+        ".vim/bundle/a/.hidden/x.vim",
+        ".vim/bundle/b/syntax/nginx.vim",
+        ".vim/bundle/b/plugin/b.vim",
+        "rt/syntax/nginx.vim",
+        "rt/syntax/ngx.vim",
+    ] {
+        write(&fx.home.join(file), "");
+    }
+    std::os::unix::fs::symlink(fx.home.join(".vim/bundle/a"), fx.home.join(".vim/bundle/c"))
+        .unwrap();
+    let mut rtp = Runtimepath::new(&format!("{root}/.vim,{root}/rt,{root}/.vim/after"));
+    rtp.infect("bundle");
+
+    // `c` is `a` by another name, so its copy is not counted twice.
+    let found: Vec<_> = rtp.find("syntax/nginx.vim");
+    assert_eq!(
+        found,
+        [
+            fx.home.join(".vim/bundle/a/syntax/nginx.vim"),
+            fx.home.join(".vim/bundle/b/syntax/nginx.vim"),
+            fx.home.join("rt/syntax/nginx.vim"),
+            fx.home.join(".vim/bundle/a/after/syntax/nginx.vim"),
+        ]
+    );
+    assert!(rtp.find("syntax").is_empty());
+
+    assert_eq!(rtp.complete("s/ng"), ["syntax/nginx.vim", "syntax/ngx.vim"]);
+    assert_eq!(
+        rtp.complete("sy/n*x"),
+        ["syntax/nginx.vim", "syntax/ngx.vim"]
+    );
+    assert_eq!(rtp.complete("p"), ["plugin/"]);
+    assert_eq!(rtp.complete("p/"), ["plugin/b.vim"]);
+    assert_eq!(
+        rtp.complete(""),
+        ["after/", "bundle/", "plugin/", "syntax/"]
+    );
+    assert_eq!(rtp.complete(".h"), [".hidden/"]);
+}
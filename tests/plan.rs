@@ -0,0 +1,86 @@
+mod common;
+
+use std::fs;
+
+use common::{write, Fixture};
+use dotfiles::install::{apply, Outcome};
+use dotfiles::plan::{plan, Action, Plan};
+use dotfiles::Error;
+
+#[test]
+fn table_lists_every_operation() {
+    let fx = Fixture::new();
+    write(&fx.home.join(".vimrc"), "mine\n");
+    fs::create_dir(fx.home.join(".gitconfig")).unwrap();
+
+    let table = plan(&fx.layout()).unwrap().to_string();
+
+    assert!(table.contains("+ create   .dircolors\n"));
+    assert!(table.contains("+ mkdir    .vim/after\n+ mkdir    .vim/after/syntax\n"));
+    assert!(table.contains("> backup   .vimrc (file)\n~ replace  .vimrc\n"));
+    assert!(table.contains("! refuse   .gitconfig (a directory already exists)\n"));
+}
+
+#[test]
+fn json_round_trips() {
+    let fx = Fixture::new();
+    write(&fx.home.join(".vimrc"), "mine\n");
+    fs::create_dir(fx.home.join(".gitconfig")).unwrap();
+    let original = plan(&fx.layout()).unwrap();
+
+    let json = serde_json::to_string(&original).unwrap();
+    let parsed: Plan = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(parsed, original);
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let vimrc = value["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|item| item["path"] == ".vimrc")
+        .unwrap();
+    assert_eq!(vimrc["action"], "replace");
+    assert_eq!(vimrc["existing"], "file");
+}
+
+#[test]
+fn saved_plan_can_be_applied() {
+    let fx = Fixture::new();
+    let layout = fx.layout();
+    let saved: Plan =
+        serde_json::from_str(&serde_json::to_string(&plan(&layout).unwrap()).unwrap()).unwrap();
+
+    let report = apply(&layout, saved).unwrap();
+
+    assert!(report.results.iter().all(|(_, o)| *o == Outcome::Linked));
+    let replanned = plan(&layout).unwrap();
+    assert!(replanned.items.iter().all(|i| i.action == Action::Skip));
+}
+
+#[test]
+fn stale_plan_is_rejected() {
+    let fx = Fixture::new();
+    let layout = fx.layout();
+    let saved = plan(&layout).unwrap();
+    write(&fx.home.join(".tmux.conf"), "written after planning\n");
+
+    let err = apply(&layout, saved).unwrap_err();
+
+    match err {
+        Error::StalePlan(paths) => assert_eq!(paths, [std::path::PathBuf::from(".tmux.conf")]),
+        other => panic!("unexpected error: {other}"),
+    }
+    assert!(fs::symlink_metadata(fx.home.join(".dircolors")).is_err());
+}
+
+#[test]
+fn plan_for_another_home_is_rejected() {
+    let fx = Fixture::new();
+    let other = Fixture::new();
+    let saved = plan(&other.layout()).unwrap();
+
+    assert!(matches!(
+        apply(&fx.layout(), saved),
+        Err(Error::PlanMismatch { .. })
+    ));
+}
@@ -0,0 +1,127 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{git, git_repo, with_bundles, write, Fixture};
+use dotfiles::manifest::{Manifest, Pin};
+use dotfiles::plugins::{install, migrate, Outcome};
+
+/// An upstream plugin with a tagged first commit, a second on `main` and
+/// a third on `dev`. Returns its URL and the three commits.
+fn upstream(fx: &Fixture) -> (String, [String; 3]) {
+    let dir = fx.repo.parent().unwrap().join("upstream");
+    git_repo(&dir, &[("plugin/p.vim", "\" one\n")]);
+    git(&dir, &["tag", "v1"]);
+    let first = git(&dir, &["rev-parse", "HEAD"]);
+    write(&dir.join("plugin/p.vim"), "\" two\n");
+    git(&dir, &["commit", "-qam", "two"]);
+    let second = git(&dir, &["rev-parse", "HEAD"]);
+    git(&dir, &["checkout", "-qb", "dev"]);
+    write(&dir.join("plugin/p.vim"), "\" three\n");
+    git(&dir, &["commit", "-qam", "three"]);
+    let third = git(&dir, &["rev-parse", "HEAD"]);
+    git(&dir, &["checkout", "-q", "main"]);
+    (format!("file://{}", dir.display()), [first, second, third])
+}
+
+fn head(dir: &Path) -> (String, String) {
+    (
+        git(dir, &["rev-parse", "HEAD"]),
+        git(dir, &["rev-parse", "--abbrev-ref", "HEAD"]),
+    )
+}
+
+#[test]
+fn installs_plugins_on_their_pins() {
+    let fx = Fixture::new();
+    let (url, [first, second, third]) = upstream(&fx);
+    write(
+        &fx.repo_path("dotfiles.toml"),
+        &format!(
+            "[[plugin]]\nurl = \"{url}\"\nname = \"default\"\n\n\
+             [[plugin]]\nurl = \"{url}\"\nname = \"tag\"\ntag = \"v1\"\n\n\
+             [[plugin]]\nurl = \"{url}\"\nname = \"branch\"\nbranch = \"dev\"\n\n\
+             [[plugin]]\nurl = \"{url}\"\nname = \"commit\"\ncommit = \"{}\"\n\n\
+             [[plugin]]\nurl = \"file:///nowhere\"\nname = \"broken\"\n",
+            &first[..10]
+        ),
+    );
+
+    let results = install(&fx.layout(), 3).unwrap();
+    let outcomes: Vec<_> = results.iter().map(|(_, o)| o.as_ref().ok()).collect();
+    let cloned = Some(&Outcome::Cloned);
+    assert_eq!(outcomes, [cloned, cloned, cloned, cloned, None]);
+    let bundle = |name: &str| fx.repo_path(&format!(".vim/bundle/{name}"));
+    assert_eq!(head(&bundle("default")), (second, "main".into()));
+    assert_eq!(head(&bundle("tag")), (first.clone(), "HEAD".into()));
+    assert_eq!(head(&bundle("branch")), (third, "dev".into()));
+    assert_eq!(head(&bundle("commit")), (first, "HEAD".into()));
+    assert_eq!(
+        git(
+            &bundle("branch"),
+            &["rev-parse", "--abbrev-ref", "dev@{upstream}"]
+        ),
+        "origin/dev"
+    );
+
+    let results = install(&fx.layout(), 1).unwrap();
+    let outcomes: Vec<_> = results.iter().map(|(_, o)| o.as_ref().ok()).collect();
+    let current = Some(&Outcome::Current);
+    assert_eq!(outcomes, [current, current, current, current, None]);
+}
+
+#[test]
+fn moves_existing_checkouts_onto_changed_pins() {
+    let fx = Fixture::new();
+    let (url, [first, ..]) = upstream(&fx);
+    write(
+        &fx.repo_path("dotfiles.toml"),
+        &format!("[[plugin]]\nurl = \"{url}\"\nname = \"p\"\n"),
+    );
+    install(&fx.layout(), 1).unwrap();
+
+    write(
+        &fx.repo_path("dotfiles.toml"),
+        &format!("[[plugin]]\nurl = \"{url}\"\nname = \"p\"\ntag = \"v1\"\n"),
+    );
+    let results = install(&fx.layout(), 1).unwrap();
+    assert_eq!(results[0].1.as_ref().unwrap(), &Outcome::CheckedOut);
+    assert_eq!(head(&fx.repo_path(".vim/bundle/p")).0, first);
+    assert_eq!(
+        fs::read_to_string(fx.repo_path(".vim/bundle/p/plugin/p.vim")).unwrap(),
+        "\" one\n"
+    );
+}
+
+#[test]
+fn migrates_submodules_to_plugins() {
+    let fx = Fixture::new();
+    let upstream = with_bundles(&fx, &["a", "b"]);
+    let commit = git(&upstream, &["rev-parse", "HEAD"]);
+
+    let migrated = migrate(&fx.layout()).unwrap();
+    assert_eq!(Manifest::load(&fx.repo).unwrap().plugins, migrated);
+    let [a, b] = &migrated[..] else {
+        panic!("{migrated:?}")
+    };
+    assert_eq!(a.path, Path::new(".vim/bundle/a"));
+    assert_eq!(b.name, "b");
+    assert_eq!(a.url, format!("file://{}", upstream.display()));
+    assert_eq!(a.pin, Pin::Commit(commit));
+
+    assert!(!fx.repo_path(".gitmodules").exists());
+    assert!(!git(&fx.repo, &["ls-files", "--stage"]).contains("160000"));
+    let gitignore = fs::read_to_string(fx.repo_path(".gitignore")).unwrap();
+    assert_eq!(gitignore, "target/\n/.vim/bundle/a/\n/.vim/bundle/b/\n");
+    // The gitlinks are staged for removal, and the checkouts are ignored.
+    assert_eq!(
+        git(&fx.repo, &["status", "--porcelain", ".vim"]),
+        "D  .vim/bundle/a\nD  .vim/bundle/b"
+    );
+
+    let results = install(&fx.layout(), 2).unwrap();
+    assert!(results
+        .iter()
+        .all(|(_, o)| o.as_ref().unwrap() == &Outcome::Current));
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+
+use common::{write, Fixture};
+use dotfiles::discover::discover;
+use dotfiles::host::Host;
+use dotfiles::install::install;
+use dotfiles::{Error, Layout};
+
+const MANIFEST: &str = r#"
+[[entry]]
+path = "bin"
+dest = ".local/bin"
+
+[profile.jump]
+hosts = ["jump-*"]
+exclude = [".vim/bundle"]
+overlay = "profiles/jump"
+
+[profile.build]
+hosts = ["build-*", "ci-*"]
+include = [".vim", ".vimrc", "bin"]
+"#;
+
+fn fixture() -> Fixture {
+    let fx = Fixture::new();
+    write(&fx.repo.join("bin/tmux-session"), "#!/bin/sh\n");
+    write(
+        &fx.repo.join("profiles/jump/.tmux.conf"),
+        "set -g prefix C-b\n",
+    );
+    write(
+        &fx.repo.join("profiles/jump/.ssh/config"),
+        "ForwardAgent no\n",
+    );
+    write(&fx.repo.join("dotfiles.toml"), MANIFEST);
+    fx
+}
+
+fn on(fx: &Fixture, hostname: &str) -> Layout {
+    fx.layout().with_host(Host {
+        os: "linux".into(),
+        name: hostname.into(),
+    })
+}
+
+fn installed(layout: &Layout) -> Vec<(PathBuf, PathBuf)> {
+    discover(layout)
+        .unwrap()
+        .into_iter()
+        .map(|e| (e.path, e.source))
+        .collect()
+}
+
+#[test]
+fn hostname_picks_the_profile() {
+    let fx = fixture();
+
+    let jump = installed(&on(&fx, "jump-01"));
+    assert!(jump.contains(&(".tmux.conf".into(), "profiles/jump/.tmux.conf".into())));
+    assert!(jump.contains(&(".ssh/config".into(), "profiles/jump/.ssh/config".into())));
+    assert!(jump.contains(&(".local/bin/tmux-session".into(), "bin/tmux-session".into())));
+    assert!(!jump.iter().any(|(p, _)| p.starts_with(".vim/bundle")));
+    assert!(jump.iter().any(|(p, _)| p.starts_with(".vim/autoload")));
+
+    let build: Vec<PathBuf> = installed(&on(&fx, "ci-7"))
+        .into_iter()
+        .map(|e| e.0)
+        .collect();
+    assert_eq!(
+        build,
+        [
+            ".local/bin/tmux-session",
+            ".vim/after/syntax/html.vim",
+            ".vim/autoload/pathogen.vim",
+            ".vim/bundle/nginx/syntax/nginx.vim",
+            ".vimrc",
+        ]
+        .map(PathBuf::from)
+    );
+
+    // No profile: the base configuration, without any overlay files.
+    let laptop = installed(&on(&fx, "laptop"));
+    assert!(laptop.contains(&(".tmux.conf".into(), ".tmux.conf".into())));
+    assert!(!laptop.iter().any(|(_, s)| s.starts_with("profiles")));
+}
+
+#[test]
+fn explicit_profile_wins_over_hostname() {
+    let fx = fixture();
+    let layout = on(&fx, "build-3").with_profile("jump");
+
+    install(&layout).unwrap();
+
+    assert_eq!(
+        fs::read_link(fx.home.join(".tmux.conf")).unwrap(),
+        fx.repo_path("profiles/jump/.tmux.conf")
+    );
+    assert!(!fx.home.join(".vim/bundle").exists());
+}
+
+#[test]
+fn unknown_and_ambiguous_profiles_are_errors() {
+    let fx = fixture();
+    let err = discover(&on(&fx, "laptop").with_profile("nested")).unwrap_err();
+    assert!(matches!(err, Error::UnknownProfile(ref name) if name == "nested"));
+
+    write(
+        &fx.repo.join("dotfiles.toml"),
+        &format!("{MANIFEST}\n[profile.everything]\nhosts = [\"*\"]\n"),
+    );
+    let err = discover(&on(&fx, "jump-01")).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "host jump-01 matches more than one profile (everything, jump); pick one with --profile"
+    );
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use std::cell::Cell;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::rc::Rc;
+
+use common::{write, Fixture};
+use dotfiles::install::{apply_with, install, install_with};
+use dotfiles::plan::plan_with;
+use dotfiles::secrets::{SecretStore, Secrets, PASSPHRASE_VAR};
+use dotfiles::status::{status, State};
+use dotfiles::template::{render, Values};
+use dotfiles::Error;
+
+fn mode(path: &std::path::Path) -> u32 {
+    fs::metadata(path).unwrap().permissions().mode() & 0o777
+}
+
+#[test]
+fn store_round_trips_and_needs_the_passphrase() {
+    let fx = Fixture::new();
+    let store = SecretStore::new(&fx.layout());
+    let mut secrets = Secrets::default();
+    secrets.set("github.token", "ghp_abc".into()).unwrap();
+
+    store.save(&secrets, "correct horse").unwrap();
+
+    assert_eq!(mode(store.path()), 0o600);
+    assert!(!fs::read(store.path())
+        .unwrap()
+        .windows(7)
+        .any(|w| w == b"ghp_abc"));
+    assert_eq!(store.open("correct horse").unwrap(), secrets);
+    assert!(matches!(store.open("wrong"), Err(Error::SecretsLocked(_))));
+    assert!(matches!(
+        secrets.set("not a name", String::new()),
+        Err(Error::BadSecretName(_))
+    ));
+}
+
+#[test]
+fn placeholders_resolve_from_the_secrets() {
+    let mut secrets = Secrets::default();
+    secrets.set("github.token", "ghp_abc".into()).unwrap();
+    let values = Values::default().with_secrets(secrets);
+
+    assert_eq!(
+        render("token = {{ secret://github.token }}\n", &values),
+        Ok("token = ghp_abc\n".to_string())
+    );
+    assert_eq!(
+        render("{{ secret://npm.token }}", &values),
+        Err((1, "secret npm.token is not in the secret store".into()))
+    );
+    assert_eq!(
+        render("{{ secret://github.token }}", &Values::default()),
+        Err((1, "there is no secret store".into()))
+    );
+}
+
+#[test]
+fn rendered_secrets_are_private_and_not_diffed() {
+    let fx = Fixture::new();
+    write(
+        &fx.repo.join(".gitconfig"),
+        "[github]\n  token = {{ secret://github.token }}\n",
+    );
+    write(
+        &fx.repo.join("dotfiles.toml"),
+        "[[entry]]\npath = \".gitconfig\"\nstrategy = \"template\"\n",
+    );
+    let layout = fx.layout();
+    let mut secrets = Secrets::default();
+    secrets.set("github.token", "ghp_abc".into()).unwrap();
+    SecretStore::new(&layout)
+        .save(&secrets, "correct horse")
+        .unwrap();
+    std::env::set_var(PASSPHRASE_VAR, "correct horse");
+
+    install(&layout).unwrap();
+
+    let gitconfig = fx.home.join(".gitconfig");
+    assert_eq!(
+        fs::read_to_string(&gitconfig).unwrap(),
+        "[github]\n  token = ghp_abc\n"
+    );
+    assert_eq!(mode(&gitconfig), 0o600);
+    assert_eq!(mode(&fx.home.join(".vimrc")), mode(&fx.repo.join(".vimrc")));
+
+    fs::write(&gitconfig, "[github]\n  token = ghp_leaked\n").unwrap();
+    let statuses = status(&layout).unwrap();
+    let state = &statuses
+        .iter()
+        .find(|s| s.path.ends_with(".gitconfig"))
+        .unwrap()
+        .state;
+    let State::Diverged { diff, .. } = state else {
+        panic!("unexpected state {state:?}");
+    };
+    assert!(!diff.contains("ghp_"), "{diff}");
+}
+
+#[test]
+fn each_command_unlocks_the_store_once() {
+    let fx = Fixture::new();
+    write(
+        &fx.repo.join(".gitconfig"),
+        "[github]\n  token = {{ secret://github.token }}\n",
+    );
+    write(
+        &fx.repo.join("dotfiles.toml"),
+        "[[entry]]\npath = \".gitconfig\"\nstrategy = \"template\"\n",
+    );
+    let layout = fx.layout();
+    let mut secrets = Secrets::default();
+    secrets.set("github.token", "ghp_abc".into()).unwrap();
+    SecretStore::new(&layout)
+        .save(&secrets, "correct horse")
+        .unwrap();
+    let unlocks = Rc::new(Cell::new(0));
+    let values = || {
+        let unlocks = Rc::clone(&unlocks);
+        Values::load(&layout).unwrap().with_passphrase(move |_| {
+            unlocks.set(unlocks.get() + 1);
+            Ok("correct horse".to_string())
+        })
+    };
+
+    let saved = plan_with(&layout, &values()).unwrap();
+    assert_eq!(unlocks.get(), 1);
+    // Checking the plan and installing from it share one unlock.
+    apply_with(&layout, saved, values()).unwrap();
+    assert_eq!(unlocks.get(), 2);
+    install_with(&layout, values()).unwrap();
+    assert_eq!(unlocks.get(), 3);
+}
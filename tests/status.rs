@@ -0,0 +1,107 @@
+mod common;
+
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::path::Path;
+
+use common::{write, Fixture};
+use dotfiles::fsutil::FileKind;
+use dotfiles::install::install;
+use dotfiles::status::{status, FileStatus, State};
+
+fn state_of<'a>(statuses: &'a [FileStatus], path: &str) -> &'a State {
+    &statuses
+        .iter()
+        .find(|s| s.path == Path::new(path))
+        .unwrap_or_else(|| panic!("{path} not in status"))
+        .state
+}
+
+#[test]
+fn fresh_install_is_all_linked() {
+    let fx = Fixture::new();
+    install(&fx.layout()).unwrap();
+
+    let statuses = status(&fx.layout()).unwrap();
+
+    assert!(statuses.iter().all(|s| s.state.is_clean()), "{statuses:?}");
+}
+
+#[test]
+fn classifies_drift() {
+    let fx = Fixture::new();
+    write(
+        &fx.repo.join("dotfiles.toml"),
+        "[[entry]]\npath = \".gitconfig\"\nstrategy = \"copy\"\n",
+    );
+    let layout = fx.layout();
+    install(&layout).unwrap();
+
+    fs::remove_file(fx.home.join(".tmux.conf")).unwrap();
+    fs::remove_file(fx.home.join(".dircolors")).unwrap();
+    symlink("/nonexistent/dircolors", fx.home.join(".dircolors")).unwrap();
+    fs::remove_file(fx.home.join(".vimrc")).unwrap();
+    write(&fx.home.join(".vimrc"), "someone else's\n");
+    write(
+        &fx.home.join(".gitconfig"),
+        "[user]\n  name = Someone\n  email = someone@example.com\n",
+    );
+
+    let statuses = status(&layout).unwrap();
+
+    assert_eq!(state_of(&statuses, ".tmux.conf"), &State::Missing);
+    assert_eq!(
+        state_of(&statuses, ".dircolors"),
+        &State::BrokenLink("/nonexistent/dircolors".into())
+    );
+    assert_eq!(
+        state_of(&statuses, ".vimrc"),
+        &State::Foreign(FileKind::File)
+    );
+    assert_eq!(
+        state_of(&statuses, ".vim/after/syntax/html.vim"),
+        &State::Linked
+    );
+    match state_of(&statuses, ".gitconfig") {
+        State::Diverged { edited, diff } => {
+            assert!(edited);
+            assert!(diff.contains("+  email = someone@example.com\n"), "{diff}");
+            assert!(diff.starts_with("--- "), "{diff}");
+        }
+        other => panic!("unexpected state {other:?}"),
+    }
+}
+
+#[test]
+fn copies_behind_the_repository_are_diverged_but_not_edited() {
+    let fx = Fixture::new();
+    write(
+        &fx.repo.join("dotfiles.toml"),
+        "[[entry]]\npath = \".gitconfig\"\nstrategy = \"copy\"\n",
+    );
+    let layout = fx.layout();
+    install(&layout).unwrap();
+    write(&fx.repo.join(".gitconfig"), "[user]\n  name = Renamed\n");
+
+    let statuses = status(&layout).unwrap();
+
+    assert!(matches!(
+        state_of(&statuses, ".gitconfig"),
+        State::Diverged { edited: false, .. }
+    ));
+}
+
+#[test]
+fn files_removed_from_the_repository_show_as_broken() {
+    let fx = Fixture::new();
+    let layout = fx.layout();
+    install(&layout).unwrap();
+    fs::remove_file(fx.repo.join(".dircolors")).unwrap();
+
+    let statuses = status(&layout).unwrap();
+
+    assert!(matches!(
+        state_of(&statuses, ".dircolors"),
+        State::BrokenLink(_)
+    ));
+}
@@ -0,0 +1,172 @@
+mod common;
+
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+use common::{write, Fixture};
+use dotfiles::install::{install, Outcome, Refusal};
+use dotfiles::installed::Installed;
+use dotfiles::manifest::Manifest;
+use dotfiles::strategy::Strategy;
+use dotfiles::uninstall::{self, Modified};
+
+fn outcome_of<'a>(report: &'a dotfiles::install::Report, path: &str) -> &'a Outcome {
+    &report
+        .results
+        .iter()
+        .find(|(e, _)| e.path == Path::new(path))
+        .unwrap_or_else(|| panic!("{path} not in report"))
+        .1
+}
+
+const MANIFEST: &str = r#"
+[[entry]]
+path = ".gitconfig"
+strategy = "copy"
+
+[[entry]]
+path = ".vim"
+strategy = "hardlink"
+
+[[entry]]
+path = ".vim/after"
+strategy = "symlink"
+"#;
+
+#[test]
+fn most_specific_entry_wins() {
+    let fx = Fixture::new();
+    write(&fx.repo.join("dotfiles.toml"), MANIFEST);
+    let manifest = Manifest::load(&fx.repo).unwrap();
+
+    assert_eq!(
+        manifest.strategy_for(Path::new(".vimrc")),
+        Strategy::Symlink
+    );
+    assert_eq!(
+        manifest.strategy_for(Path::new(".gitconfig")),
+        Strategy::Copy
+    );
+    assert_eq!(
+        manifest.strategy_for(Path::new(".vim/autoload/pathogen.vim")),
+        Strategy::Hardlink
+    );
+    assert_eq!(
+        manifest.strategy_for(Path::new(".vim/after/syntax/html.vim")),
+        Strategy::Symlink
+    );
+}
+
+#[test]
+fn installs_copies_and_hardlinks() {
+    let fx = Fixture::new();
+    write(&fx.repo.join("dotfiles.toml"), MANIFEST);
+    let layout = fx.layout();
+
+    install(&layout).unwrap();
+
+    let gitconfig = fx.home.join(".gitconfig");
+    assert!(fs::symlink_metadata(&gitconfig).unwrap().is_file());
+    assert_eq!(
+        fs::read(&gitconfig).unwrap(),
+        fs::read(fx.repo.join(".gitconfig")).unwrap()
+    );
+    let pathogen = fs::metadata(fx.home.join(".vim/autoload/pathogen.vim")).unwrap();
+    let source = fs::metadata(fx.repo.join(".vim/autoload/pathogen.vim")).unwrap();
+    assert_eq!(pathogen.ino(), source.ino());
+    assert!(
+        fs::symlink_metadata(fx.home.join(".vim/after/syntax/html.vim"))
+            .unwrap()
+            .file_type()
+            .is_symlink()
+    );
+
+    let installed = Installed::load(&layout).unwrap();
+    let record = &installed.files[Path::new(".gitconfig")];
+    assert_eq!(record.strategy, Strategy::Copy);
+    assert!(record.hash.is_some());
+}
+
+#[test]
+fn outdated_copies_are_updated() {
+    let fx = Fixture::new();
+    write(&fx.repo.join("dotfiles.toml"), MANIFEST);
+    let layout = fx.layout();
+    install(&layout).unwrap();
+
+    write(
+        &fx.repo.join(".gitconfig"),
+        "[user]\n  name = Someone Else\n",
+    );
+    let report = install(&layout).unwrap();
+
+    assert_eq!(outcome_of(&report, ".gitconfig"), &Outcome::Updated);
+    assert_eq!(outcome_of(&report, ".vimrc"), &Outcome::AlreadyLinked);
+    assert_eq!(
+        fs::read_to_string(fx.home.join(".gitconfig")).unwrap(),
+        "[user]\n  name = Someone Else\n"
+    );
+    assert!(report.backup.is_none());
+}
+
+#[test]
+fn locally_edited_copies_are_reported_not_clobbered() {
+    let fx = Fixture::new();
+    write(&fx.repo.join("dotfiles.toml"), MANIFEST);
+    let layout = fx.layout();
+    install(&layout).unwrap();
+
+    write(
+        &fx.home.join(".gitconfig"),
+        "[user]\n  name = Edited Locally\n",
+    );
+    write(
+        &fx.repo.join(".gitconfig"),
+        "[user]\n  name = Upstream Change\n",
+    );
+    let report = install(&layout).unwrap();
+
+    assert_eq!(
+        outcome_of(&report, ".gitconfig"),
+        &Outcome::Refused(Refusal::LocallyModified)
+    );
+    assert_eq!(
+        fs::read_to_string(fx.home.join(".gitconfig")).unwrap(),
+        "[user]\n  name = Edited Locally\n"
+    );
+
+    let report = uninstall::uninstall(&layout).unwrap();
+    let (_, outcome) = report
+        .results
+        .iter()
+        .find(|(p, _)| p == Path::new(".gitconfig"))
+        .unwrap();
+    assert_eq!(outcome, &uninstall::Outcome::LeftAlone(Modified::Edited));
+    assert!(fx.home.join(".gitconfig").is_file());
+    assert!(!fx.home.join(".vim/autoload/pathogen.vim").exists());
+}
+
+#[test]
+fn changing_strategy_swaps_our_own_files_without_a_backup() {
+    let fx = Fixture::new();
+    let layout = fx.layout();
+    install(&layout).unwrap();
+
+    write(&fx.repo.join("dotfiles.toml"), MANIFEST);
+    let report = install(&layout).unwrap();
+
+    assert_eq!(outcome_of(&report, ".gitconfig"), &Outcome::Updated);
+    assert_eq!(
+        outcome_of(&report, ".vim/autoload/pathogen.vim"),
+        &Outcome::Updated
+    );
+    assert_eq!(
+        outcome_of(&report, ".vim/after/syntax/html.vim"),
+        &Outcome::AlreadyLinked
+    );
+    assert!(report.backup.is_none());
+    assert!(fs::symlink_metadata(fx.home.join(".gitconfig"))
+        .unwrap()
+        .is_file());
+}
@@ -0,0 +1,145 @@
+mod common;
+
+use std::fs;
+
+use common::{write, Fixture};
+use dotfiles::install::{install, Outcome, Refusal};
+use dotfiles::installed::Installed;
+use dotfiles::status::{status, State};
+use dotfiles::template::{render, Values};
+
+fn values(text: &str) -> Values {
+    Values::new(toml::from_str(text).unwrap())
+}
+
+#[test]
+fn renders_dotted_names_and_literals() {
+    let values = values("[user]\nname = \"Someone\"\nuid = 1000\n");
+
+    assert_eq!(
+        render(
+            "name = {{ user.name }}\nuid={{user.uid}}\n{{ \"{{\" }} x }}\n",
+            &values
+        ),
+        Ok("name = Someone\nuid=1000\n{{ x }}\n".to_string())
+    );
+    assert_eq!(
+        render("a\nb {{ user.email }}\n", &values),
+        Err((
+            2,
+            "user.email is not set in ~/.config/dotfiles/values.toml".into()
+        ))
+    );
+    assert_eq!(
+        render("{{ user }}", &values),
+        Err((1, "user is a table, not a value".into()))
+    );
+    assert_eq!(
+        render("\n\n{{ user.name\n}}", &values),
+        Err((3, "`{{` is not closed on the same line".into()))
+    );
+    assert_eq!(
+        render("{{ user name }}", &values),
+        Err((1, "`user name` is not a variable name".into()))
+    );
+}
+
+fn fixture() -> Fixture {
+    let fx = Fixture::new();
+    write(
+        &fx.repo.join(".gitconfig"),
+        "[user]\n  name = {{ user.name }}\n  email = {{ user.email }}\n",
+    );
+    write(
+        &fx.repo.join("dotfiles.toml"),
+        "[[entry]]\npath = \".gitconfig\"\nstrategy = \"template\"\n",
+    );
+    fx
+}
+
+fn set_values(fx: &Fixture, email: &str) {
+    write(
+        &fx.home.join(".config/dotfiles/values.toml"),
+        &format!("[user]\nname = \"Someone\"\nemail = \"{email}\"\n"),
+    );
+}
+
+#[test]
+fn installs_rendered_output_and_tracks_it() {
+    let fx = fixture();
+    set_values(&fx, "someone@work.example");
+    let layout = fx.layout();
+
+    install(&layout).unwrap();
+
+    let gitconfig = fx.home.join(".gitconfig");
+    assert_eq!(
+        fs::read_to_string(&gitconfig).unwrap(),
+        "[user]\n  name = Someone\n  email = someone@work.example\n"
+    );
+    let record = &Installed::load(&layout).unwrap().files[std::path::Path::new(".gitconfig")];
+    assert!(record.hash.is_some());
+    assert!(status(&layout).unwrap().iter().all(|s| s.state.is_clean()));
+
+    // New values re-render the file in place.
+    set_values(&fx, "someone@home.example");
+    let report = install(&layout).unwrap();
+    assert!(report
+        .results
+        .iter()
+        .any(|(e, o)| e.path.ends_with(".gitconfig") && *o == Outcome::Updated));
+    assert!(fs::read_to_string(&gitconfig)
+        .unwrap()
+        .contains("someone@home.example"));
+}
+
+#[test]
+fn local_edits_are_reported_against_the_rendered_output() {
+    let fx = fixture();
+    set_values(&fx, "someone@work.example");
+    let layout = fx.layout();
+    install(&layout).unwrap();
+    let gitconfig = fx.home.join(".gitconfig");
+    let edited = fs::read_to_string(&gitconfig).unwrap() + "[merge]\n  ff = false\n";
+    fs::write(&gitconfig, edited).unwrap();
+
+    let statuses = status(&layout).unwrap();
+    let state = &statuses
+        .iter()
+        .find(|s| s.path.ends_with(".gitconfig"))
+        .unwrap()
+        .state;
+    let State::Diverged { edited: true, diff } = state else {
+        panic!("unexpected state {state:?}");
+    };
+    assert!(!diff.contains("{{"), "{diff}");
+    assert!(diff.contains("+[merge]\n"), "{diff}");
+
+    let report = install(&layout).unwrap();
+    assert!(report
+        .refused()
+        .any(|(e, r)| e.path.ends_with(".gitconfig") && *r == Refusal::LocallyModified));
+}
+
+#[test]
+fn missing_values_hold_back_only_that_template() {
+    let fx = fixture();
+    write(
+        &fx.home.join(".config/dotfiles/values.toml"),
+        "[user]\nname = \"Someone\"\n",
+    );
+    let layout = fx.layout();
+
+    let report = install(&layout).unwrap();
+
+    let refusal = report
+        .refused()
+        .find(|(e, _)| e.path.ends_with(".gitconfig"))
+        .map(|(_, r)| r.clone());
+    let Some(refusal @ Refusal::Unrenderable { line: 3, .. }) = refusal else {
+        panic!("unexpected refusal {refusal:?}");
+    };
+    assert!(refusal.to_string().contains("user.email"), "{refusal}");
+    assert!(!fx.home.join(".gitconfig").exists());
+    assert!(fx.home.join(".vimrc").exists());
+}
@@ -0,0 +1,149 @@
+mod common;
+
+use std::fs;
+
+use common::{write, Fixture};
+use dotfiles::backup::BackupStore;
+use dotfiles::install::install;
+use dotfiles::plan::plan;
+use dotfiles::template::{Values, VALUES};
+use dotfiles::transaction::Transaction;
+use dotfiles::Error;
+
+#[test]
+fn failed_step_rolls_back_everything_before_it() {
+    let fx = Fixture::new();
+    write(&fx.home.join(".gitconfig"), "[user]\n  name = Original\n");
+    let layout = fx.layout();
+
+    let plan = plan(&layout).unwrap();
+    // Something appears in the way between planning and applying.
+    fs::create_dir_all(fx.home.join(".vim/bundle/nginx/syntax/nginx.vim")).unwrap();
+
+    let err = Transaction::begin(
+        &layout,
+        plan.operations(&layout),
+        Values::load(&layout).unwrap(),
+    )
+    .unwrap()
+    .commit()
+    .unwrap_err();
+    assert!(matches!(err, Error::TransactionFailed { .. }), "{err}");
+
+    assert!(fs::symlink_metadata(fx.home.join(".dircolors")).is_err());
+    assert!(fs::symlink_metadata(fx.home.join(".vim/after")).is_err());
+    assert_eq!(
+        fs::read_to_string(fx.home.join(".gitconfig")).unwrap(),
+        "[user]\n  name = Original\n"
+    );
+    assert!(!Transaction::is_pending(&layout));
+    assert!(BackupStore::new(&layout).list().unwrap().is_empty());
+}
+
+#[test]
+fn interrupted_install_can_be_resumed() {
+    let fx = Fixture::new();
+    write(&fx.home.join(".gitconfig"), "mine\n");
+    let layout = fx.layout();
+
+    let plan = plan(&layout).unwrap();
+    let mut transaction = Transaction::begin(
+        &layout,
+        plan.operations(&layout),
+        Values::load(&layout).unwrap(),
+    )
+    .unwrap();
+    for _ in 0..3 {
+        assert!(transaction.step().unwrap());
+    }
+    drop(transaction);
+
+    assert!(Transaction::is_pending(&layout));
+    assert!(matches!(install(&layout), Err(Error::JournalPending)));
+
+    let resumed = Transaction::load(&layout).unwrap();
+    assert_eq!(resumed.completed(), 3);
+    let backup = resumed.commit().unwrap();
+
+    assert!(backup.is_some());
+    assert!(!Transaction::is_pending(&layout));
+    for item in &plan.items {
+        let dest = fx.home_path(item.entry.path.to_str().unwrap());
+        assert_eq!(
+            fs::read_link(&dest).unwrap(),
+            layout.source(&item.entry.path)
+        );
+    }
+}
+
+#[test]
+fn interrupted_install_can_be_rolled_back() {
+    let fx = Fixture::new();
+    write(&fx.home.join(".gitconfig"), "mine\n");
+    let layout = fx.layout();
+
+    let plan = plan(&layout).unwrap();
+    let mut transaction = Transaction::begin(
+        &layout,
+        plan.operations(&layout),
+        Values::load(&layout).unwrap(),
+    )
+    .unwrap();
+    while transaction.remaining() > 2 {
+        transaction.step().unwrap();
+    }
+    drop(transaction);
+
+    Transaction::load(&layout).unwrap().rollback().unwrap();
+
+    assert!(!Transaction::is_pending(&layout));
+    assert_eq!(
+        fs::read_to_string(fx.home.join(".gitconfig")).unwrap(),
+        "mine\n"
+    );
+    let mut left: Vec<_> = fs::read_dir(&fx.home)
+        .unwrap()
+        .map(|e| e.unwrap().file_name())
+        .collect();
+    left.sort();
+    assert_eq!(left, [".gitconfig", ".local"]);
+}
+
+#[test]
+fn broken_values_leave_no_journal_behind() {
+    let fx = Fixture::new();
+    let layout = fx.layout();
+    write(&fx.home.join(VALUES), "not = [toml\n");
+
+    let err = install(&layout).unwrap_err();
+    assert!(matches!(err, Error::Toml { .. }), "{err}");
+    assert!(!Transaction::is_pending(&layout));
+    assert!(BackupStore::new(&layout).list().unwrap().is_empty());
+}
+
+#[test]
+fn interrupted_install_rolls_back_without_values() {
+    let fx = Fixture::new();
+    write(
+        &fx.repo.join("dotfiles.toml"),
+        "[[entry]]\npath = \".gitconfig\"\nstrategy = \"template\"\n",
+    );
+    let layout = fx.layout();
+    let plan = plan(&layout).unwrap();
+    let mut transaction = Transaction::begin(
+        &layout,
+        plan.operations(&layout),
+        Values::load(&layout).unwrap(),
+    )
+    .unwrap();
+    while transaction.step().unwrap() {}
+    drop(transaction);
+    assert!(fx.home.join(".gitconfig").is_file());
+
+    // The values a template was rendered with have since been broken.
+    write(&fx.home.join(VALUES), "not = [toml\n");
+    Transaction::load(&layout).unwrap().rollback().unwrap();
+
+    assert!(!Transaction::is_pending(&layout));
+    assert!(fs::symlink_metadata(fx.home.join(".gitconfig")).is_err());
+}
@@ -0,0 +1,115 @@
+mod common;
+
+use std::fs;
+use std::os::unix::fs::symlink;
+
+use common::{write, Fixture};
+use dotfiles::backup::BackupStore;
+use dotfiles::fsutil::FileKind;
+use dotfiles::install::install;
+use dotfiles::installed::Installed;
+use dotfiles::uninstall::{uninstall, Modified, Outcome};
+
+fn outcome_of<'a>(report: &'a dotfiles::uninstall::Report, path: &str) -> &'a Outcome {
+    &report
+        .results
+        .iter()
+        .find(|(p, _)| p.to_str() == Some(path))
+        .unwrap_or_else(|| panic!("{path} not in report"))
+        .1
+}
+
+#[test]
+fn removes_links_and_restores_originals() {
+    let fx = Fixture::new();
+    write(&fx.home.join(".vimrc"), "original\n");
+    let layout = fx.layout();
+    let id = install(&layout).unwrap().backup.unwrap();
+
+    let report = uninstall(&layout).unwrap();
+
+    assert_eq!(outcome_of(&report, ".vimrc"), &Outcome::Restored(id));
+    assert_eq!(outcome_of(&report, ".dircolors"), &Outcome::Removed);
+    assert_eq!(
+        fs::read_to_string(fx.home.join(".vimrc")).unwrap(),
+        "original\n"
+    );
+    assert!(report.kept_backups.is_empty());
+    assert!(BackupStore::new(&layout).list().unwrap().is_empty());
+    assert_eq!(Installed::load(&layout).unwrap(), Installed::default());
+
+    let mut left: Vec<_> = fs::read_dir(&fx.home)
+        .unwrap()
+        .map(|e| e.unwrap().file_name())
+        .collect();
+    left.sort();
+    assert_eq!(left, [".local", ".vimrc"]);
+}
+
+#[test]
+fn leaves_modified_paths_and_their_backups_alone() {
+    let fx = Fixture::new();
+    write(&fx.home.join(".tmux.conf"), "original\n");
+    let layout = fx.layout();
+    let id = install(&layout).unwrap().backup.unwrap();
+
+    fs::remove_file(fx.home.join(".tmux.conf")).unwrap();
+    write(&fx.home.join(".tmux.conf"), "edited by hand\n");
+    fs::remove_file(fx.home.join(".gitconfig")).unwrap();
+    symlink("/etc/gitconfig", fx.home.join(".gitconfig")).unwrap();
+
+    let report = uninstall(&layout).unwrap();
+
+    assert_eq!(
+        outcome_of(&report, ".tmux.conf"),
+        &Outcome::LeftAlone(Modified::Replaced(FileKind::File))
+    );
+    assert_eq!(
+        outcome_of(&report, ".gitconfig"),
+        &Outcome::LeftAlone(Modified::Relinked("/etc/gitconfig".into()))
+    );
+    assert_eq!(
+        fs::read_to_string(fx.home.join(".tmux.conf")).unwrap(),
+        "edited by hand\n"
+    );
+    assert_eq!(report.kept_backups, [id.as_str()]);
+    assert_eq!(
+        BackupStore::new(&layout).load(&id).unwrap().entries.len(),
+        1
+    );
+    assert!(!fx.home.join(".vim").exists());
+}
+
+#[test]
+fn restores_originals_even_if_the_link_was_deleted() {
+    let fx = Fixture::new();
+    write(&fx.home.join(".vimrc"), "original\n");
+    let layout = fx.layout();
+    let id = install(&layout).unwrap().backup.unwrap();
+    fs::remove_file(fx.home.join(".vimrc")).unwrap();
+
+    let report = uninstall(&layout).unwrap();
+
+    assert_eq!(
+        outcome_of(&report, ".vimrc"),
+        &Outcome::Missing { restored: Some(id) }
+    );
+    assert_eq!(
+        fs::read_to_string(fx.home.join(".vimrc")).unwrap(),
+        "original\n"
+    );
+}
+
+#[test]
+fn keeps_directories_with_foreign_files_in_them() {
+    let fx = Fixture::new();
+    let layout = fx.layout();
+    install(&layout).unwrap();
+    write(&fx.home.join(".vim/after/syntax/go.vim"), "\" mine\n");
+
+    uninstall(&layout).unwrap();
+
+    assert!(fx.home.join(".vim/after/syntax/go.vim").is_file());
+    assert!(!fx.home.join(".vim/after/syntax/html.vim").exists());
+    assert!(!fx.home.join(".vim/bundle").exists());
+}
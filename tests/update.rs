@@ -0,0 +1,169 @@
+mod common;
+
+use std::path::PathBuf;
+
+use common::{git, with_bundles, write, Fixture};
+use dotfiles::lockfile::{lock, Lockfile};
+use dotfiles::manifest::{Manifest, Pin};
+use dotfiles::plugins::migrate;
+use dotfiles::update::{apply, check};
+
+fn commit(dir: &std::path::Path, file: &str, message: &str) -> String {
+    write(&dir.join(file), message);
+    git(dir, &["add", "-A"]);
+    git(dir, &["commit", "-q", "-m", message]);
+    git(dir, &["rev-parse", "HEAD"])
+}
+
+#[test]
+fn check_lists_new_commits_and_breaking_changes() {
+    let fx = Fixture::new();
+    let upstream = with_bundles(&fx, &["a", "b"]);
+    let old = git(&upstream, &["rev-parse", "HEAD"]);
+    lock(&fx.layout()).unwrap();
+    commit(&upstream, "plugin/b.vim", "fix: quote paths");
+    commit(&upstream, "plugin/c.vim", "feat(maps)!: drop <Leader>x");
+    let tip = commit(
+        &upstream,
+        "plugin/d.vim",
+        "Rename options\n\nBREAKING CHANGE: g:a_opt is now g:a_option",
+    );
+
+    let results = check(&fx.layout(), 2).unwrap();
+    assert_eq!(results.len(), 2);
+    let (path, update) = &results[0];
+    assert_eq!(path, &PathBuf::from(".vim/bundle/a"));
+    let update = update.as_ref().unwrap().as_ref().unwrap();
+    assert_eq!((&update.from, &update.to), (&old, &tip));
+    assert!(update.fast_forward);
+    let subjects: Vec<_> = update
+        .commits
+        .iter()
+        .map(|c| (c.subject.as_str(), c.breaking))
+        .collect();
+    assert_eq!(
+        subjects,
+        [
+            ("Rename options", true),
+            ("feat(maps)!: drop <Leader>x", true),
+            ("fix: quote paths", false),
+        ]
+    );
+    assert_eq!(update.breaking(), 2);
+
+    // Fetching alone moves nothing.
+    assert_eq!(
+        git(&fx.repo_path(".vim/bundle/a"), &["rev-parse", "HEAD"]),
+        old
+    );
+}
+
+#[test]
+fn apply_moves_and_locks_only_accepted_updates() {
+    let fx = Fixture::new();
+    let upstream = with_bundles(&fx, &["a", "b"]);
+    let old = git(&upstream, &["rev-parse", "HEAD"]);
+    lock(&fx.layout()).unwrap();
+    let tip = commit(&upstream, "plugin/b.vim", "fix: quote paths");
+
+    let updates: Vec<_> = check(&fx.layout(), 8)
+        .unwrap()
+        .into_iter()
+        .map(|(_, u)| u.unwrap().unwrap())
+        .collect();
+    let lockfile = apply(&fx.layout(), &updates[..1]).unwrap();
+    assert_eq!(Lockfile::load(&fx.repo).unwrap(), Some(lockfile.clone()));
+    let commits: Vec<_> = lockfile.bundles.iter().map(|b| &b.commit).collect();
+    assert_eq!(commits, [&tip, &old]);
+    assert_eq!(
+        git(&fx.repo_path(".vim/bundle/a"), &["rev-parse", "HEAD"]),
+        tip
+    );
+    assert_eq!(
+        git(&fx.repo_path(".vim/bundle/b"), &["rev-parse", "HEAD"]),
+        old
+    );
+    assert_eq!(
+        git(&fx.repo, &["status", "--porcelain", "--", ".vim/bundle"]),
+        "M  .vim/bundle/a"
+    );
+
+    let results = check(&fx.layout(), 8).unwrap();
+    assert_eq!(results[0].1.as_ref().unwrap(), &None);
+    assert!(results[1].1.as_ref().unwrap().is_some());
+}
+
+#[test]
+fn updates_plugins_and_their_pins() {
+    let fx = Fixture::new();
+    let upstream = with_bundles(&fx, &["a", "b"]);
+    let old = git(&upstream, &["rev-parse", "HEAD"]);
+    migrate(&fx.layout()).unwrap();
+    git(&upstream, &["tag", "v1.0"]);
+    let url = upstream.to_str().unwrap();
+    git(&fx.repo, &["clone", "-q", url, ".vim/bundle/c"]);
+    let manifest = fx.repo_path("dotfiles.toml");
+    let text = std::fs::read_to_string(&manifest).unwrap();
+    let pinned = format!("commit = \"{old}\"\n");
+    let (a, b) = text.split_at(text.rfind(&pinned).unwrap());
+    let b = b.replacen(&pinned, "tag = \"v1.0\" # release\n", 1);
+    write(
+        &manifest,
+        &format!(
+            "{a}{b}\n[[plugin]]\nurl = {url:?}\nname = \"c\"\nbranch = \"main\" # tracks main\n"
+        ),
+    );
+    let release = commit(&upstream, "plugin/b.vim", "fix: quote paths");
+    git(&upstream, &["tag", "v1.1"]);
+    commit(&upstream, "plugin/c.vim", "feat: maps");
+    git(&upstream, &["tag", "v2.0-rc1"]);
+    let tip = commit(&upstream, "plugin/d.vim", "wip");
+
+    let updates: Vec<_> = check(&fx.layout(), 8)
+        .unwrap()
+        .into_iter()
+        .map(|(_, u)| u.unwrap().unwrap())
+        .collect();
+    // The plugin pinned to a commit is not offered anything, and the one
+    // pinned to a tag only the newest release.
+    let offered: Vec<_> = updates
+        .iter()
+        .map(|u| (u.path.clone(), u.to.clone(), u.tag.clone()))
+        .collect();
+    assert_eq!(
+        offered,
+        [
+            (".vim/bundle/b".into(), release.clone(), Some("v1.1".into())),
+            (".vim/bundle/c".into(), tip.clone(), None),
+        ]
+    );
+    let lockfile = apply(&fx.layout(), &updates).unwrap();
+
+    let commits: Vec<_> = lockfile.bundles.iter().map(|b| &b.commit).collect();
+    assert_eq!(commits, [&old, &release, &tip]);
+    let pins: Vec<_> = Manifest::load(&fx.repo)
+        .unwrap()
+        .plugins
+        .into_iter()
+        .map(|p| p.pin)
+        .collect();
+    assert_eq!(
+        pins,
+        [
+            Pin::Commit(old),
+            Pin::Tag("v1.1".into()),
+            Pin::Branch("main".into())
+        ]
+    );
+    let text = std::fs::read_to_string(&manifest).unwrap();
+    assert!(text.contains("tag = \"v1.1\" # release\n"), "{text}");
+    assert!(text.contains("branch = \"main\" # tracks main\n"));
+    assert_eq!(
+        git(&fx.repo_path(".vim/bundle/b"), &["rev-parse", "HEAD"]),
+        release
+    );
+    let c = fx.repo_path(".vim/bundle/c");
+    assert_eq!(git(&c, &["symbolic-ref", "HEAD"]), "refs/heads/main");
+    assert_eq!(git(&c, &["rev-parse", "HEAD"]), tip);
+    assert_eq!(git(&c, &["status", "--porcelain"]), "");
+}
@@ -0,0 +1,102 @@
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use common::{git, with_bundles, write, Fixture};
+use dotfiles::lockfile::{lock, verify};
+use dotfiles::vendor::{install, vendor, Outcome};
+use dotfiles::{Error, Layout};
+
+/// A fresh clone of the fixture's repository, with its bundles not yet
+/// checked out.
+fn clone(fx: &Fixture) -> Layout {
+    let clone = fx.repo.parent().unwrap().join("clone");
+    git(
+        Path::new("/"),
+        &[
+            "clone",
+            "-q",
+            fx.repo.to_str().unwrap(),
+            clone.to_str().unwrap(),
+        ],
+    );
+    Layout::new(&clone, &fx.home).unwrap()
+}
+
+fn outcomes(results: Vec<(PathBuf, Outcome)>) -> Vec<Outcome> {
+    results.into_iter().map(|(_, outcome)| outcome).collect()
+}
+
+#[test]
+fn installs_offline_from_a_vendored_cache() {
+    let fx = Fixture::new();
+    let upstream = with_bundles(&fx, &["a", "b"]);
+    lock(&fx.layout()).unwrap();
+    git(&fx.repo, &["add", "bundles.lock"]);
+    git(&fx.repo, &["commit", "-q", "-m", "lock"]);
+    let cache = fx.repo.parent().unwrap().join("cache");
+
+    let vendored = vendor(&fx.layout(), &cache).unwrap();
+    assert_eq!(vendored.len(), 2);
+    assert_eq!(vendored[0].1, git(&upstream, &["rev-parse", "HEAD"]));
+    // Vendoring again changes nothing.
+    assert_eq!(vendor(&fx.layout(), &cache).unwrap(), vendored);
+
+    fs::remove_dir_all(&upstream).unwrap();
+    let layout = clone(&fx);
+    let results = install(&layout, Some(&cache), true).unwrap();
+    assert_eq!(outcomes(results), [Outcome::Installed, Outcome::Installed]);
+    assert_eq!(verify(&layout).unwrap(), []);
+    assert_eq!(
+        fs::read_to_string(layout.repo().join(".vim/bundle/b/plugin/a.vim")).unwrap(),
+        "\" a\n"
+    );
+
+    let results = install(&layout, Some(&cache), true).unwrap();
+    assert_eq!(outcomes(results), [Outcome::Current, Outcome::Current]);
+}
+
+#[test]
+fn offline_install_refuses_what_the_cache_lacks() {
+    let fx = Fixture::new();
+    with_bundles(&fx, &["a"]);
+    let cache = fx.repo.parent().unwrap().join("cache");
+    git(
+        fx.repo.parent().unwrap(),
+        &["init", "-q", "--bare", "cache"],
+    );
+
+    let layout = clone(&fx);
+    let err = install(&layout, Some(&cache), true).unwrap_err();
+    assert!(matches!(err, Error::NotInCache { .. }), "{err}");
+    assert_eq!(
+        fs::read_dir(layout.repo().join(".vim/bundle/a"))
+            .unwrap()
+            .count(),
+        0
+    );
+
+    write(&layout.repo().join(".vim/bundle/a/stray.vim"), "\n");
+    let err = install(&layout, None, false).unwrap_err();
+    assert!(matches!(err, Error::BundleInTheWay { .. }), "{err}");
+}
+
+#[test]
+fn installs_from_the_url_without_a_cache() {
+    let fx = Fixture::new();
+    let upstream = with_bundles(&fx, &["a"]);
+    let layout = clone(&fx);
+
+    let results = install(&layout, None, false).unwrap();
+    assert_eq!(outcomes(results), [Outcome::Installed]);
+    let bundle = layout.repo().join(".vim/bundle/a");
+    assert_eq!(
+        git(&bundle, &["rev-parse", "HEAD"]),
+        git(&upstream, &["rev-parse", "HEAD"])
+    );
+    assert_eq!(
+        git(&bundle, &["remote", "get-url", "origin"]),
+        format!("file://{}", upstream.display())
+    );
+}
@@ -0,0 +1,32 @@
+use dotfiles::vimrc::Vimrc;
+
+#[test]
+fn reads_and_rewrites_the_disabled_list() {
+    let text = "\" let g:pathogen_disabled = ['old']\n\
+                let g:pathogen_disabled = [ 'a''b', \"c\\\"d\" ] \" comment\n\
+                call pathogen#infect('~/.vim/bundle')\n";
+    let mut vimrc = Vimrc::parse(text).unwrap();
+    assert_eq!(vimrc.disabled(), ["a'b", "c\"d"]);
+    assert_eq!(vimrc.infect_source(), Some("~/.vim/bundle"));
+
+    vimrc.set_disabled(&["a'b".to_string(), "e".to_string()]);
+    assert_eq!(
+        vimrc.to_string(),
+        "\" let g:pathogen_disabled = ['old']\n\
+         let g:pathogen_disabled = ['a''b', 'e'] \" comment\n\
+         call pathogen#infect('~/.vim/bundle')\n"
+    );
+}
+
+#[test]
+fn refuses_lists_it_cannot_edit() {
+    for text in [
+        "let g:pathogen_disabled = []\ncall add(g:pathogen_disabled, 'a')\n",
+        "let g:pathogen_disabled = ['a',\n  \\ 'b']\n",
+        "let g:pathogen_disabled = split('a b')\n",
+    ] {
+        assert!(Vimrc::parse(text).is_err(), "{text}");
+    }
+    let vimrc = Vimrc::parse("set nocompatible\n").unwrap();
+    assert_eq!(vimrc.infect_source(), None);
+}